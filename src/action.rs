@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use strum::Display;
 
+use crate::database::{ObjectKind, ParameterizedStatement};
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Display, Deserialize)]
 pub enum MenuPreview {
   Rows,
@@ -8,11 +10,19 @@ pub enum MenuPreview {
   Constraints,
   Indexes,
   Policies,
+  Definition,
+  Source,
+  Sequence,
+  Trigger,
+  Enum,
+  /// The statements that would create the object.
+  Ddl(ObjectKind),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Display, Deserialize)]
 pub enum ExportFormat {
   CSV,
+  JSON,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Display, Deserialize)]
@@ -28,6 +38,7 @@ pub enum Action {
   SubmitEditorQuery,
   SubmitEditorQueryBypassParser,
   Query(Vec<String>, bool, bool), // (query_lines, execution_confirmed, bypass_parser)
+  RerunUnlimited,
   MenuPreview(MenuPreview, String, String), // (preview, schema, table)
   QueryToEditor(Vec<String>),
   ClearHistory,
@@ -37,9 +48,14 @@ pub enum Action {
   FocusHistory,
   FocusData,
   FocusFavorites,
+  FocusNotifications,
+  FocusActivity,
   CycleFocusForwards,
   CycleFocusBackwards,
   LoadMenu,
+  LoadCompletionColumns(String, String),           // (schema, table)
+  LoadEditTable(Option<String>, String),           // (schema if named, table)
+  ApplyEdits(Vec<ParameterizedStatement>, String), // (updates, the SELECT the edited results came from)
   CopyData(String),
   RequestExportData(i64),
   ExportData(ExportFormat),
@@ -47,4 +63,21 @@ pub enum Action {
   RequestSaveFavorite(Vec<String>),
   SaveFavorite(String, Vec<String>),
   DeleteFavorite(String),
+  RequestSwitchConnection,
+  RequestNewSession,
+  CloseSession,
+  NextSession,
+  PreviousSession,
+  BeginTransaction,
+  CommitTransaction,
+  RollbackTransaction,
+  CreateSavepoint,
+  RollbackToSavepoint,
+  RequestListen,
+  UnlistenAll,
+  ClearNotifications,
+  RefreshActivity,
+  RefreshLockWaits,
+  RequestCancelSession(String),    // session id
+  RequestTerminateSession(String), // session id
 }
@@ -1,41 +1,55 @@
 #[cfg(not(feature = "termux"))]
 use arboard::Clipboard;
 use color_eyre::eyre::{Result, eyre};
-use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
 use ratatui::{
   Frame,
-  layout::{Constraint, Direction, Layout, Position},
+  layout::{Alignment, Constraint, Direction, Layout, Position},
   prelude::Rect,
   style::{Color, Style, Stylize},
-  text::Line,
+  text::{Line, Span, Text},
   widgets::{Block, Borders, Clear, Padding, Paragraph, Tabs, Wrap},
 };
 use sqlparser::ast::Statement;
 use strum::IntoEnumIterator;
-use tokio::sync::mpsc::{self};
+use tokio::sync::mpsc::{self, UnboundedSender};
 
 use crate::{
-  action::{Action, ExportFormat, MenuPreview},
-  cli::{Cli, Driver},
+  action::{Action, MenuPreview},
+  cli::{Cli, Driver, extract_database_from_url},
   components::{
     Component, ComponentImpls,
-    data::{Data, DataComponent},
-    editor::Editor,
+    activity::Activity,
     favorites::{FavoriteEntries, Favorites},
     history::History,
-    menu::{Menu, MenuComponent},
+    notifications::Notifications,
   },
   config::Config,
-  database::{self, Database, DbTaskResult, ExecutionType, Rows},
+  database::{self, DbTaskResult, ExecutionType, ObjectKind, Rows, ServerSession, StatementKind, TableInfo},
   focus::Focus,
+  keyring::Password,
   popups::{
-    PopUp, PopUpPayload, confirm_bypass::ConfirmBypass, confirm_export::ConfirmExport, confirm_query::ConfirmQuery,
-    confirm_tx::ConfirmTx, exporting::Exporting, name_favorite::NameFavorite,
+    PopUp, PopUpPayload,
+    confirm_bypass::ConfirmBypass,
+    confirm_export::ConfirmExport,
+    confirm_kill_session::ConfirmKillSession,
+    confirm_query::ConfirmQuery,
+    confirm_tx::ConfirmTx,
+    connection_picker::{ConnectionEntry, ConnectionPicker},
+    exporting::Exporting,
+    listen_channel::ListenChannel,
+    name_favorite::NameFavorite,
   },
+  session::{Session, SessionState, new_database},
   tui,
+  tunnel::SshTunnel,
   ui::center,
 };
 
+// name shown for the connection given with the command line arguments or
+// DATABASE_URL, rather than picked from the config
+const CLI_CONNECTION: &str = "command line";
+
 pub struct HistoryEntry {
   pub query_lines: Vec<String>,
   pub timestamp: chrono::DateTime<chrono::Local>,
@@ -45,23 +59,33 @@ pub struct AppState {
   pub focus: Focus,
   pub history: Vec<HistoryEntry>,
   pub favorites: FavoriteEntries,
-  pub last_query_start: Option<chrono::DateTime<chrono::Utc>>,
-  pub last_query_end: Option<chrono::DateTime<chrono::Utc>>,
-  pub query_task_running: bool,
+  pub sessions: Vec<SessionState>,
+  pub active_session: usize,
+}
+
+impl AppState {
+  pub fn session(&self) -> &SessionState {
+    &self.sessions[self.active_session]
+  }
+
+  pub fn session_mut(&mut self) -> &mut SessionState {
+    &mut self.sessions[self.active_session]
+  }
 }
 
-pub struct Components<'a> {
-  pub menu: Box<dyn MenuComponent<'a>>,
-  pub editor: Box<dyn Component>,
+// components that are shared by every session
+pub struct Components {
   pub history: Box<dyn Component>,
-  pub data: Box<dyn DataComponent<'a>>,
   pub favorites: Box<dyn Component>,
+  pub notifications: Box<dyn Component>,
+  pub activity: Box<dyn Component>,
 }
 
 pub struct App {
   pub mouse_mode_override: Option<bool>,
   pub config: Config,
-  pub components: Components<'static>,
+  pub components: Components,
+  pub sessions: Vec<Session>,
   pub should_quit: bool,
   pub last_tick_key_events: Vec<KeyEvent>,
   pub last_frame_mouse_event: Option<MouseEvent>,
@@ -69,45 +93,198 @@ pub struct App {
   last_focused_tab: Focus,
   last_focused_component: Focus,
   popup: Option<Box<dyn PopUp>>,
+  args: Option<Cli>,
+  // the command line connection can only be switched back to if it
+  // doesn't have to prompt for anything, which means it needs a url
+  cli_connection: Option<(Driver, String)>,
 }
 
 impl App {
   pub fn new(mouse_mode_override: Option<bool>, config: Config) -> Result<Self> {
     let focus = Focus::Menu;
-    let menu = Menu::new();
-    let editor = Editor::new();
     let history = History::new();
-    let data = Data::new();
     let favorites = Favorites::new();
+    let notifications = Notifications::new();
+    let activity = Activity::new();
     let favorite_entries = FavoriteEntries::new(&config.config._favorites_dir)?;
 
     Ok(Self {
       components: Components {
-        menu: Box::new(menu),
-        editor: Box::new(editor),
         history: Box::new(history),
-        data: Box::new(data),
         favorites: Box::new(favorites),
+        notifications: Box::new(notifications),
+        activity: Box::new(activity),
       },
+      sessions: vec![],
       should_quit: false,
       mouse_mode_override,
       config,
       last_tick_key_events: Vec::new(),
       last_frame_mouse_event: None,
-      state: AppState {
-        focus,
-        history: vec![],
-        last_query_start: None,
-        last_query_end: None,
-        favorites: favorite_entries,
-        query_task_running: false,
-      },
+      state: AppState { focus, history: vec![], favorites: favorite_entries, sessions: vec![], active_session: 0 },
       last_focused_tab: Focus::Editor,
       last_focused_component: focus,
       popup: None,
+      args: None,
+      cli_connection: None,
     })
   }
 
+  fn session(&mut self) -> &mut Session {
+    &mut self.sessions[self.state.active_session]
+  }
+
+  fn connection_entries(&self) -> Vec<ConnectionEntry> {
+    let mut entries: Vec<ConnectionEntry> = self
+      .config
+      .db
+      .iter()
+      .map(|(name, conn)| ConnectionEntry { name: name.clone(), username: conn.username().map(str::to_string) })
+      .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    if self.cli_connection.is_some() {
+      entries.insert(0, ConnectionEntry { name: CLI_CONNECTION.to_string(), username: None });
+    }
+    entries
+  }
+
+  // builds and initializes a driver for the named connection
+  async fn connect(&self, name: &str, password: Option<Password>) -> Result<(Session, SessionState)> {
+    let args = self.args.clone().ok_or_else(|| eyre!("Not connected yet"))?;
+    let (driver, url, socket, tls, tunnel) = match self.config.db.get(name) {
+      Some(conn) => {
+        let tunnel = conn.open_tunnel().await?;
+        (conn.driver, conn.connection_string(password, tunnel.as_ref())?, conn.socket(), conn.tls(), tunnel)
+      },
+      None => {
+        let (driver, url) = self.cli_connection.clone().ok_or_else(|| eyre!("Unknown connection: {name}"))?;
+        (driver, url, args.socket.clone(), args.tls.clone(), None)
+      },
+    };
+    let state = self.new_session_state(name.to_string(), driver, extract_database_from_url(&url));
+    let read_only = self.is_read_only(name);
+    let mut database = new_database(driver);
+    database
+      .init(Cli {
+        connection_url: Some(url),
+        driver: Some(driver),
+        user: None,
+        password: None,
+        host: None,
+        port: None,
+        socket,
+        database: None,
+        read_only,
+        tls,
+        ..args
+      })
+      .await?;
+    log::info!("Connected to {name} ({driver:?})");
+    Ok((Session::new(database, driver, read_only, self.config.execution_policy(name), tunnel), state))
+  }
+
+  fn new_session_state(&self, name: String, driver: Driver, database_name: Option<String>) -> SessionState {
+    let protected = self.config.db.get(&name).is_some_and(|conn| conn.protected);
+    SessionState { driver: Some(driver), protected, database_name, ..SessionState::new(name) }
+  }
+
+  // the --read-only flag applies to every connection
+  fn is_read_only(&self, name: &str) -> bool {
+    self.args.as_ref().is_some_and(|args| args.read_only) || self.config.db.get(name).is_some_and(|conn| conn.read_only)
+  }
+
+  // replaces the connection of the active session, keeping its editor.
+  // the current connection is kept if the new one can't be made.
+  async fn switch_connection(&mut self, name: String, password: Option<Password>) -> Result<()> {
+    // streamed results hold on to connections from the old pool
+    self.session().data.set_data_state(None, None);
+    if let Err(e) = self.session().database.rollback_tx().await {
+      log::error!("Failed to roll back before switching connections: {e:?}");
+    }
+    self.session().database.abort_query().await?;
+    if let Err(e) = self.session().database.rollback_session_tx().await {
+      log::error!("Failed to roll back the session's transaction before switching connections: {e:?}");
+    }
+    let state = self.state.session_mut();
+    state.query_task_running = false;
+    state.last_query_start = None;
+    state.last_query_end = None;
+    state.pending_tx = None;
+    state.transaction = None;
+    state.savepoints = vec![];
+    state.listening = vec![];
+    state.activity = vec![];
+    state.activity_error = None;
+    state.lock_waits = vec![];
+    state.lock_waits_error = None;
+    state.activity_loaded_at = None;
+
+    let (new, new_state) = self.connect(&name, password).await?;
+    let session = self.session();
+    session.database.close().await?;
+    session.database = new.database;
+    // the old tunnel is closed once the database it forwarded to is
+    session.tunnel = new.tunnel;
+    session.driver = new.driver;
+    session.read_only = new.read_only;
+    session.execution_policy = new.execution_policy;
+    let state = self.state.session_mut();
+    state.connection_name = new_state.connection_name;
+    state.driver = new_state.driver;
+    state.protected = new_state.protected;
+    state.database_name = new_state.database_name;
+    Ok(())
+  }
+
+  async fn open_session(
+    &mut self,
+    name: String,
+    password: Option<Password>,
+    action_tx: UnboundedSender<Action>,
+    area: Rect,
+  ) -> Result<()> {
+    let (mut session, state) = self.connect(&name, password).await?;
+    session.register(action_tx.clone(), &self.config, area)?;
+    self.sessions.push(session);
+    self.state.sessions.push(state);
+    self.select_session(self.sessions.len() - 1);
+    action_tx.send(Action::LoadMenu)?;
+    Ok(())
+  }
+
+  async fn close_session(&mut self) -> Result<()> {
+    if self.sessions.len() < 2 {
+      return Ok(());
+    }
+    let index = self.state.active_session;
+    let mut session = self.sessions.remove(index);
+    self.state.sessions.remove(index);
+    self.state.active_session = 0;
+    self.select_session(index.min(self.sessions.len() - 1));
+    session.database.close().await
+  }
+
+  fn select_session(&mut self, index: usize) {
+    self.state.active_session = index;
+    // a transaction that finished while its tab was in the background
+    // still has to be committed or rolled back
+    if !self.confirm_pending_tx() && self.state.focus == Focus::PopUp {
+      self.last_focused_component();
+    }
+  }
+
+  // brings the confirmation of the session's pending transaction back up,
+  // since nothing else can run on the session until it's been dealt with
+  fn confirm_pending_tx(&mut self) -> bool {
+    match self.state.session().pending_tx.clone() {
+      Some((rows_affected, statement, inspectable)) => {
+        self.set_popup(Box::new(ConfirmTx::new(rows_affected, statement, inspectable)));
+        true
+      },
+      None => false,
+    }
+  }
+
   fn add_to_history(&mut self, query_lines: Vec<String>) {
     self.state.history.insert(0, HistoryEntry { query_lines, timestamp: chrono::Local::now() });
     if self.state.history.len() > 50 {
@@ -125,7 +302,7 @@ impl App {
       self.popup = None;
       self.last_focused_component = focus;
     }
-    if focus == Focus::Editor || focus == Focus::History || focus == Focus::Favorites {
+    if matches!(focus, Focus::Editor | Focus::History | Focus::Favorites | Focus::Notifications | Focus::Activity) {
       self.last_focused_tab = focus;
     }
   }
@@ -140,6 +317,8 @@ impl App {
       Focus::Editor => self.set_focus(Focus::Editor),
       Focus::History => self.set_focus(Focus::History),
       Focus::Favorites => self.set_focus(Focus::Favorites),
+      Focus::Notifications => self.set_focus(Focus::Notifications),
+      Focus::Activity => self.set_focus(Focus::Activity),
       _ => {},
     }
   }
@@ -151,18 +330,32 @@ impl App {
       Focus::Data => self.set_focus(Focus::Data),
       Focus::History => self.set_focus(Focus::History),
       Focus::Favorites => self.set_focus(Focus::Favorites),
+      Focus::Notifications => self.set_focus(Focus::Notifications),
+      Focus::Activity => self.set_focus(Focus::Activity),
       Focus::PopUp => {},
     }
   }
 
-  pub async fn run(&mut self, driver: Driver, args: Cli) -> Result<()> {
-    let mut database: Box<dyn Database> = match driver {
-      Driver::Postgres => Box::new(database::PostgresDriver::new()),
-      Driver::MySql => Box::new(database::MySqlDriver::new()),
-      Driver::Sqlite => Box::new(database::SqliteDriver::new()),
-      Driver::Oracle => Box::new(database::OracleDriver::new()),
+  pub async fn run(
+    &mut self,
+    driver: Driver,
+    args: Cli,
+    connection_name: Option<String>,
+    tunnel: Option<SshTunnel>,
+  ) -> Result<()> {
+    self.cli_connection = match connection_name {
+      Some(_) => None,
+      None => args.connection_url.clone().map(|url| (driver, url)),
     };
-    database.init(args).await?;
+    self.args = Some(args.clone());
+    let connection_name = connection_name.unwrap_or_else(|| CLI_CONNECTION.to_string());
+    let read_only = self.is_read_only(&connection_name);
+    let database_name = args.connection_url.as_deref().and_then(extract_database_from_url).or(args.database.clone());
+    let mut database = new_database(driver);
+    database.init(Cli { read_only, ..args }).await?;
+    let execution_policy = self.config.execution_policy(&connection_name);
+    self.sessions.push(Session::new(database, driver, read_only, execution_policy, tunnel));
+    self.state.sessions.push(self.new_session_state(connection_name, driver, database_name));
     let (action_tx, mut action_rx) = mpsc::unbounded_channel();
     log::info!("{driver:?}");
 
@@ -173,24 +366,23 @@ impl App {
     #[cfg(not(feature = "termux"))]
     let mut clipboard = Clipboard::new();
 
-    self.components.menu.register_action_handler(action_tx.clone())?;
-    self.components.editor.register_action_handler(action_tx.clone())?;
     self.components.history.register_action_handler(action_tx.clone())?;
-    self.components.data.register_action_handler(action_tx.clone())?;
     self.components.favorites.register_action_handler(action_tx.clone())?;
+    self.components.notifications.register_action_handler(action_tx.clone())?;
+    self.components.activity.register_action_handler(action_tx.clone())?;
 
-    self.components.menu.register_config_handler(self.config.clone())?;
-    self.components.editor.register_config_handler(self.config.clone())?;
     self.components.history.register_config_handler(self.config.clone())?;
-    self.components.data.register_config_handler(self.config.clone())?;
     self.components.favorites.register_config_handler(self.config.clone())?;
+    self.components.notifications.register_config_handler(self.config.clone())?;
+    self.components.activity.register_config_handler(self.config.clone())?;
 
     let size = tui.size()?;
-    self.components.menu.init(Rect { width: size.width, height: size.height, x: 0, y: 0 })?;
-    self.components.editor.init(Rect { width: size.width, height: size.height, x: 0, y: 0 })?;
-    self.components.history.init(Rect { width: size.width, height: size.height, x: 0, y: 0 })?;
-    self.components.data.init(Rect { width: size.width, height: size.height, x: 0, y: 0 })?;
-    self.components.favorites.init(Rect { width: size.width, height: size.height, x: 0, y: 0 })?;
+    let area = Rect { width: size.width, height: size.height, x: 0, y: 0 };
+    self.sessions[0].register(action_tx.clone(), &self.config, area)?;
+    self.components.history.init(area)?;
+    self.components.favorites.init(area)?;
+    self.components.notifications.init(area)?;
+    self.components.activity.init(area)?;
 
     action_tx.send(Action::LoadMenu)?;
 
@@ -198,23 +390,42 @@ impl App {
       if self.popup.is_some() {
         self.set_focus(Focus::PopUp);
       }
-      match database.get_query_results().await? {
-        DbTaskResult::Finished(results) => {
-          self.components.data.set_data_state(Some(results.results), results.statement_type);
-          self.state.last_query_end = Some(chrono::Utc::now());
-          self.state.query_task_running = false;
-        },
-        DbTaskResult::ConfirmTx(rows_affected, statement) => {
-          self.state.last_query_end = Some(chrono::Utc::now());
-          self.set_popup(Box::new(ConfirmTx::new(rows_affected, statement)));
-          self.state.query_task_running = true;
-        },
-        DbTaskResult::Pending => {
-          self.state.query_task_running = true;
-        },
-        DbTaskResult::NoTask => {
-          self.state.query_task_running = false;
-        },
+      // every session is polled, so queries keep running in background tabs
+      let mut confirm_tx = None;
+      for (i, session) in self.sessions.iter_mut().enumerate() {
+        let state = &mut self.state.sessions[i];
+        match session.database.get_query_results().await? {
+          DbTaskResult::Finished(results) => {
+            if let Some(count) = state.transaction.as_mut() {
+              *count += results.len();
+            }
+            session.data.set_script_results(results);
+            state.last_query_end = Some(chrono::Utc::now());
+            state.query_task_running = false;
+          },
+          DbTaskResult::ConfirmTx(rows_affected, statement, previews) => {
+            let inspectable = !previews.is_empty();
+            if inspectable {
+              session.data.set_tx_preview(previews);
+            }
+            state.last_query_end = Some(chrono::Utc::now());
+            state.pending_tx = Some((rows_affected, statement.clone(), inspectable));
+            state.query_task_running = true;
+            if i == self.state.active_session {
+              confirm_tx = Some(ConfirmTx::new(rows_affected, statement, inspectable));
+            }
+          },
+          DbTaskResult::Pending => {
+            state.query_task_running = true;
+          },
+          DbTaskResult::NoTask => {
+            state.query_task_running = false;
+          },
+        }
+        state.add_notifications(session.database.take_notifications());
+      }
+      if let Some(popup) = confirm_tx {
+        self.set_popup(Box::new(popup));
       }
       if let Some(e) = tui.next().await {
         let mut event_consumed = false;
@@ -225,7 +436,14 @@ impl App {
           tui::Event::Resize(x, y) => action_tx.send(Action::Resize(x, y))?,
           tui::Event::Mouse(event) => self.last_frame_mouse_event = Some(event),
           tui::Event::Key(key) => {
-            if let Some(keymap) = self.config.keybindings.get(&self.state.focus) {
+            // a cell being edited takes typed characters before the keybindings do
+            let editing = self.state.focus == Focus::Data
+              && self.session().data.is_editing()
+              && matches!(key.code, KeyCode::Char(_))
+              && (key.modifiers - KeyModifiers::SHIFT).is_empty();
+            if let Some(keymap) = self.config.keybindings.get(&self.state.focus)
+              && !editing
+            {
               if let Some(action) = keymap.get(&vec![key]) {
                 log::info!("Got action: {action:?}");
                 action_tx.send(action.clone())?;
@@ -236,7 +454,7 @@ impl App {
                 let payload = popup.handle_key_events(key, &mut self.state)?;
                 match payload {
                   Some(PopUpPayload::SetDataTable(result, statement)) => {
-                    self.components.data.set_data_state(result, statement);
+                    self.session().data.set_data_state(result, statement);
                     self.set_focus(Focus::Editor);
                   },
                   Some(PopUpPayload::ConfirmQuery(query)) => {
@@ -247,9 +465,9 @@ impl App {
                     action_tx.send(Action::Query(vec![query], true, true))?;
                     self.set_focus(Focus::Editor);
                   },
-                  Some(PopUpPayload::ConfirmExport(confirmed)) => {
-                    if confirmed {
-                      action_tx.send(Action::ExportData(ExportFormat::CSV))?;
+                  Some(PopUpPayload::ConfirmExport(format)) => {
+                    if let Some(format) = format {
+                      action_tx.send(Action::ExportData(format))?;
                       self.set_popup(Box::new(Exporting::new()));
                     } else {
                       self.set_focus(Focus::Data);
@@ -262,18 +480,66 @@ impl App {
                     self.state.favorites.add_entry(name, query_lines);
                     self.set_focus(Focus::Editor);
                   },
+                  Some(PopUpPayload::SwitchConnection(name, password)) => {
+                    match self.switch_connection(name, password).await {
+                      Ok(()) => action_tx.send(Action::LoadMenu)?,
+                      Err(e) => self.session().data.set_data_state(Some(Err(e)), None),
+                    }
+                    self.set_focus(Focus::Menu);
+                  },
+                  Some(PopUpPayload::OpenSession(name, password)) => {
+                    self.set_focus(Focus::Menu);
+                    if let Err(e) = self.open_session(name, password, action_tx.clone(), area).await {
+                      self.session().data.set_data_state(Some(Err(e)), None);
+                    }
+                  },
+                  Some(PopUpPayload::Listen(channel)) => {
+                    match self.session().database.listen(&channel).await {
+                      Ok(()) => {
+                        let listening = &mut self.state.session_mut().listening;
+                        if !listening.contains(&channel) {
+                          listening.push(channel);
+                        }
+                      },
+                      Err(e) => self.session().data.set_data_state(Some(Err(e)), None),
+                    }
+                    self.set_focus(Focus::Notifications);
+                  },
+                  Some(PopUpPayload::KillSession(id, terminate)) => {
+                    let session = self.session();
+                    let result = match (session.read_only, terminate) {
+                      (true, _) => Err(eyre!("Sessions can't be cancelled or terminated on a read-only connection")),
+                      (false, true) => session.database.terminate_session(&id).await,
+                      (false, false) => session.database.cancel_session(&id).await,
+                    };
+                    if let Err(e) = result {
+                      self.session().data.set_data_state(Some(Err(e)), None);
+                    }
+                    self.set_focus(Focus::Activity);
+                    action_tx.send(Action::RefreshActivity)?;
+                  },
                   Some(PopUpPayload::CommitTx) => {
-                    let response = database.commit_tx().await?;
-                    self.state.last_query_end = Some(chrono::Utc::now());
-                    if let Some(results) = response {
-                      self.components.data.set_data_state(Some(results.results), results.statement_type);
+                    let response = self.session().database.commit_tx().await?;
+                    let state = self.state.session_mut();
+                    state.last_query_end = Some(chrono::Utc::now());
+                    state.pending_tx = None;
+                    // edits are shown as they were saved by running their query again
+                    if let Some(query) = state.rerun_after_commit.take() {
+                      action_tx.send(Action::Query(vec![query], false, false))?;
+                      self.set_focus(Focus::Data);
+                    } else if !response.is_empty() {
+                      self.session().data.set_script_results(response);
                       self.set_focus(Focus::Editor);
                     }
                   },
+                  Some(PopUpPayload::InspectTx) => {
+                    self.set_focus(Focus::Data);
+                  },
                   Some(PopUpPayload::RollbackTx) => {
-                    database.rollback_tx().await?;
-                    self.state.last_query_end = Some(chrono::Utc::now());
-                    self.components.data.set_data_state(
+                    self.session().database.rollback_tx().await?;
+                    self.state.session_mut().last_query_end = Some(chrono::Utc::now());
+                    self.state.session_mut().pending_tx = None;
+                    self.session().data.set_data_state(
                       Some(Ok(Rows { headers: vec![], rows: vec![], rows_affected: None })),
                       Some(Statement::Rollback { chain: false, savepoint: None }),
                     );
@@ -300,12 +566,13 @@ impl App {
         }
         if !event_consumed {
           for i in ComponentImpls::iter() {
+            let session = &mut self.sessions[self.state.active_session];
             let action = match i {
               ComponentImpls::Menu => {
-                self.components.menu.handle_events(Some(e.clone()), self.last_tick_key_events.clone(), &self.state)?
+                session.menu.handle_events(Some(e.clone()), self.last_tick_key_events.clone(), &self.state)?
               },
               ComponentImpls::Editor => {
-                self.components.editor.handle_events(Some(e.clone()), self.last_tick_key_events.clone(), &self.state)?
+                session.editor.handle_events(Some(e.clone()), self.last_tick_key_events.clone(), &self.state)?
               },
               ComponentImpls::History => self.components.history.handle_events(
                 Some(e.clone()),
@@ -313,13 +580,23 @@ impl App {
                 &self.state,
               )?,
               ComponentImpls::Data => {
-                self.components.data.handle_events(Some(e.clone()), self.last_tick_key_events.clone(), &self.state)?
+                session.data.handle_events(Some(e.clone()), self.last_tick_key_events.clone(), &self.state)?
               },
               ComponentImpls::Favorites => self.components.favorites.handle_events(
                 Some(e.clone()),
                 self.last_tick_key_events.clone(),
                 &self.state,
               )?,
+              ComponentImpls::Notifications => self.components.notifications.handle_events(
+                Some(e.clone()),
+                self.last_tick_key_events.clone(),
+                &self.state,
+              )?,
+              ComponentImpls::Activity => self.components.activity.handle_events(
+                Some(e.clone()),
+                self.last_tick_key_events.clone(),
+                &self.state,
+              )?,
             };
             if let Some(action) = action {
               action_tx.send(action)?;
@@ -355,32 +632,122 @@ impl App {
           Action::FocusData => self.set_focus(Focus::Data),
           Action::FocusHistory => self.set_focus(Focus::History),
           Action::FocusFavorites => self.set_focus(Focus::Favorites),
+          Action::FocusNotifications => self.set_focus(Focus::Notifications),
+          Action::FocusActivity => self.set_focus(Focus::Activity),
           Action::CycleFocusForwards => match self.state.focus {
             Focus::Menu => self.set_focus(Focus::Editor),
             Focus::Editor => self.set_focus(Focus::Data),
             Focus::Data => self.set_focus(Focus::History),
             Focus::History => self.set_focus(Focus::Favorites),
-            Focus::Favorites => self.set_focus(Focus::Menu),
+            Focus::Favorites => self.set_focus(Focus::Notifications),
+            Focus::Notifications => self.set_focus(Focus::Activity),
+            Focus::Activity => self.set_focus(Focus::Menu),
             Focus::PopUp => {},
           },
           Action::CycleFocusBackwards => match self.state.focus {
             Focus::History => self.set_focus(Focus::Data),
             Focus::Data => self.set_focus(Focus::Editor),
             Focus::Editor => self.set_focus(Focus::Menu),
-            Focus::Menu => self.set_focus(Focus::Favorites),
+            Focus::Menu => self.set_focus(Focus::Activity),
+            Focus::Activity => self.set_focus(Focus::Notifications),
+            Focus::Notifications => self.set_focus(Focus::Favorites),
             Focus::Favorites => self.set_focus(Focus::History),
             Focus::PopUp => {},
           },
           Action::LoadMenu => {
-            let rows = database.load_menu().await;
-            self.components.menu.set_table_list(Some(rows));
+            let session = self.session();
+            let rows = session.database.load_menu().await;
+            let tables = match session.database.load_tables().await {
+              Ok(tables) => tables,
+              // drivers without introspection can still complete what's in the menu
+              Err(e) => {
+                log::warn!("Falling back to the menu for completion: {e}");
+                rows.as_ref().map_or(vec![], |rows| {
+                  rows
+                    .rows
+                    .iter()
+                    .map(|row| TableInfo {
+                      schema: row[0].to_string(),
+                      name: row[1].to_string(),
+                      kind: row[2].to_string().parse().unwrap_or(ObjectKind::Table),
+                      comment: None,
+                    })
+                    .filter(|t| matches!(t.kind, ObjectKind::Table | ObjectKind::View | ObjectKind::MaterializedView))
+                    .collect()
+                })
+              },
+            };
+            session.editor.set_completion_tables(session.driver, tables);
+            session.menu.set_table_list(Some(rows));
+          },
+          Action::LoadCompletionColumns(schema, table) => {
+            let session = self.session();
+            let columns = match session.database.describe_table(schema, table).await {
+              Ok(details) => details.columns,
+              Err(e) => {
+                log::error!("Failed to load columns of {schema}.{table}: {e}");
+                vec![]
+              },
+            };
+            session.editor.set_completion_columns(schema.clone(), table.clone(), columns);
+          },
+          Action::LoadEditTable(schema, table) => {
+            let session = &mut self.sessions[self.state.active_session];
+            // what an unqualified name refers to, short of asking the database
+            let default_schema = match session.driver {
+              Driver::Postgres => Some("public".to_owned()),
+              _ => self.state.session().database_name.clone(),
+            };
+            let found = match (session.read_only, session.execution_policy.execution_type(StatementKind::Update)) {
+              (true, _) => Err(eyre!("Rows can't be edited on a read-only connection")),
+              (_, ExecutionType::Forbid) => Err(eyre!("UPDATE is forbidden by the execution policy")),
+              _ => session.database.load_tables().await.and_then(|tables| {
+                database::find_table(&tables, schema.as_deref(), table, default_schema.as_deref()).cloned()
+              }),
+            };
+            match found {
+              Ok(info) => {
+                let details = session.database.describe_table(&info.schema, &info.name).await;
+                session.data.set_edit_table(info.schema, info.name, details, session.driver);
+              },
+              Err(e) => {
+                session.data.set_edit_table(schema.clone().unwrap_or_default(), table.clone(), Err(e), session.driver)
+              },
+            }
+          },
+          Action::ApplyEdits(statements, query) => 'apply_edits: {
+            if self.confirm_pending_tx() {
+              break 'apply_edits;
+            }
+            let session = self.session();
+            session.data.set_loading();
+            match session.database.start_tx_with_params(statements.clone()).await {
+              Ok(()) => {
+                let state = self.state.session_mut();
+                state.last_query_start = Some(chrono::Utc::now());
+                state.last_query_end = None;
+                state.rerun_after_commit = Some(query.clone());
+              },
+              Err(e) => session.data.set_data_state(Some(Err(e)), None),
+            }
           },
           Action::Query(query_lines, confirmed, bypass) => 'query_action: {
             let query_string = query_lines.clone().join(" \n");
-            if query_string.is_empty() {
+            if query_string.is_empty() || self.confirm_pending_tx() {
               break 'query_action;
             }
             self.add_to_history(query_lines.clone());
+            if *bypass && self.session().read_only {
+              let e = eyre!("The parser can't be bypassed on a read-only connection");
+              self.session().data.set_data_state(Some(Err(e)), None);
+              break 'query_action;
+            }
+            // a bypassed query isn't parsed, so nothing it does can be forbidden
+            if *bypass && self.session().execution_policy.configures_forbid() {
+              let e = eyre!("The parser can't be bypassed on a connection whose execution policy forbids statements");
+              self.session().data.set_data_state(Some(Err(e)), None);
+              break 'query_action;
+            }
             if *bypass && !confirmed {
               log::warn!("Bypassing parser");
               self.set_popup(Box::new(ConfirmBypass::new(query_string.clone())));
@@ -388,45 +755,108 @@ impl App {
             }
             let execution_info = match *bypass && *confirmed {
               true => Ok((ExecutionType::Normal, None)),
-              false => database::get_execution_type(query_string.clone(), *confirmed, driver),
+              false => {
+                let session = self.session();
+                database::get_execution_type(
+                  query_string.clone(),
+                  *confirmed,
+                  session.driver,
+                  session.read_only,
+                  &session.execution_policy,
+                )
+              },
             };
+            let mut query_options = self.config.settings.query_options();
+            if std::mem::take(&mut self.state.session_mut().skip_row_limit) {
+              query_options.max_rows = None;
+            }
+            let in_transaction = self.state.session().transaction.is_some();
+            let session = &mut self.sessions[self.state.active_session];
             match execution_info {
-              Ok((ExecutionType::Transaction, _)) => {
-                self.components.data.set_loading();
-                database.start_tx(query_string).await?;
-                self.state.last_query_start = Some(chrono::Utc::now());
-                self.state.last_query_end = None;
+              // typing the statements that start or end a transaction does the
+              // same as their keybindings, so that the connection gets pinned
+              Ok((_, Some(Statement::StartTransaction { .. }))) if !*bypass => {
+                action_tx.send(Action::BeginTransaction)?;
+              },
+              Ok((_, Some(Statement::Commit { .. }))) if in_transaction && !*bypass => {
+                action_tx.send(Action::CommitTransaction)?;
+              },
+              Ok((_, Some(Statement::Rollback { savepoint: None, .. }))) if in_transaction && !*bypass => {
+                action_tx.send(Action::RollbackTransaction)?;
+              },
+              // the session's transaction already waits for a commit, but on a
+              // protected connection the statement still has to be confirmed
+              Ok((ExecutionType::Transaction, Some(statement))) if in_transaction && self.state.session().protected => {
+                self.set_popup(Box::new(ConfirmQuery::new(query_string.clone(), statement)));
+              },
+              Ok((ExecutionType::Transaction, _)) if !in_transaction => {
+                session.data.set_loading();
+                session.database.start_tx(query_string, query_options).await?;
+                let state = self.state.session_mut();
+                state.last_query_start = Some(chrono::Utc::now());
+                state.last_query_end = None;
+                state.rerun_after_commit = None;
               },
               Ok((ExecutionType::Confirm, Some(statement_type))) => {
                 self.set_popup(Box::new(ConfirmQuery::new(query_string.clone(), statement_type)));
               },
-              Ok((ExecutionType::Normal, _)) => {
-                self.components.data.set_loading();
-                database.start_query(query_string, *bypass).await?;
-                self.state.last_query_start = Some(chrono::Utc::now());
-                self.state.last_query_end = None;
+              Ok((ExecutionType::Normal | ExecutionType::Transaction, _)) => {
+                session.data.set_loading();
+                session.database.start_query(query_string, *bypass, query_options).await?;
+                let state = self.state.session_mut();
+                state.last_query_start = Some(chrono::Utc::now());
+                state.last_query_end = None;
+                state.last_query = query_lines.clone();
+                state.row_limit = query_options.max_rows;
               },
-              Err(e) => self.components.data.set_data_state(Some(Err(e)), None),
-              _ => self.components.data.set_data_state(Some(Err(eyre!("Missing statement type but not bypass"))), None),
+              Err(e) => session.data.set_data_state(Some(Err(e)), None),
+              _ => session.data.set_data_state(Some(Err(eyre!("Missing statement type but not bypass"))), None),
             }
           },
-          Action::AbortQuery => match database.abort_query().await {
+          Action::RerunUnlimited => {
+            let state = self.state.session_mut();
+            if !state.last_query.is_empty() && !state.query_task_running {
+              state.skip_row_limit = true;
+              action_tx.send(Action::Query(state.last_query.clone(), false, false))?;
+            }
+          },
+          Action::AbortQuery if self.state.session().pending_tx.is_some() => {
+            self.confirm_pending_tx();
+          },
+          Action::AbortQuery => match self.session().database.abort_query().await {
             Ok(true) => {
-              self.components.data.set_cancelled();
-              self.state.last_query_end = Some(chrono::Utc::now());
+              self.session().data.set_cancelled();
+              self.state.session_mut().last_query_end = Some(chrono::Utc::now());
             },
             Ok(false) => {},
             Err(e) => {
-              self.components.data.set_data_state(Some(Err(e)), None);
+              self.session().data.set_data_state(Some(Err(e)), None);
             },
           },
+          // unlike the other previews, this is shown as it's loaded rather
+          // than run as a query in the editor
+          Action::MenuPreview(MenuPreview::Ddl(kind), schema, name) => {
+            let ddl = self.session().database.load_ddl(*kind, schema, name).await;
+            let driver = self.session().driver;
+            match ddl {
+              Ok(ddl) => self.session().data.set_ddl(ddl, driver),
+              Err(e) => self.session().data.set_data_state(Some(Err(e)), None),
+            }
+          },
           Action::MenuPreview(preview_type, schema, table) => {
+            let database = &self.session().database;
             let preview_query = match preview_type {
               MenuPreview::Rows => database.preview_rows_query(schema, table),
               MenuPreview::Columns => database.preview_columns_query(schema, table),
               MenuPreview::Constraints => database.preview_constraints_query(schema, table),
               MenuPreview::Indexes => database.preview_indexes_query(schema, table),
               MenuPreview::Policies => database.preview_policies_query(schema, table),
+              MenuPreview::Definition => database.preview_definition_query(schema, table),
+              MenuPreview::Source => database.preview_source_query(schema, table),
+              MenuPreview::Sequence => database.preview_sequence_query(schema, table),
+              MenuPreview::Trigger => database.preview_trigger_query(schema, table),
+              MenuPreview::Enum => database.preview_enum_query(schema, table),
+              MenuPreview::Ddl(_) => unreachable!(),
             };
             action_tx.send(Action::QueryToEditor(vec![preview_query.clone()]))?;
             action_tx.send(Action::FocusEditor)?;
@@ -446,6 +876,60 @@ impl App {
           Action::ClearHistory => {
             self.clear_history();
           },
+          Action::RequestListen => {
+            self.set_popup(Box::new(ListenChannel::new()));
+          },
+          Action::UnlistenAll => match self.session().database.unlisten_all().await {
+            Ok(()) => self.state.session_mut().listening = vec![],
+            Err(e) => self.session().data.set_data_state(Some(Err(e)), None),
+          },
+          Action::ClearNotifications => {
+            self.state.session_mut().notifications = vec![];
+          },
+          Action::RefreshActivity => {
+            let activity = self.session().database.load_activity().await;
+            let state = self.state.session_mut();
+            match activity {
+              Ok(activity) => {
+                state.activity = activity;
+                state.activity_error = None;
+              },
+              Err(e) => state.activity_error = Some(e.to_string()),
+            }
+            state.activity_loaded_at = Some(chrono::Local::now());
+          },
+          Action::RefreshLockWaits => {
+            let lock_waits = self.session().database.load_lock_waits().await;
+            let state = self.state.session_mut();
+            match lock_waits {
+              Ok(lock_waits) => {
+                state.lock_waits = lock_waits;
+                state.lock_waits_error = None;
+              },
+              Err(e) => state.lock_waits_error = Some(e.to_string()),
+            }
+            state.activity_loaded_at = Some(chrono::Local::now());
+          },
+          Action::RequestCancelSession(_) | Action::RequestTerminateSession(_) if self.session().read_only => {
+            let e = eyre!("Sessions can't be cancelled or terminated on a read-only connection");
+            self.session().data.set_data_state(Some(Err(e)), None);
+          },
+          Action::RequestCancelSession(id) | Action::RequestTerminateSession(id) => {
+            let terminate = matches!(action, Action::RequestTerminateSession(_));
+            let state = self.state.session();
+            // the lock inspector knows less about a session than the list of them
+            let session = state.activity.iter().find(|session| &session.id == id).cloned().or_else(|| {
+              state.lock_waits.iter().find(|wait| &wait.id == id).map(|wait| ServerSession {
+                id: wait.id.clone(),
+                state: wait.state.clone(),
+                query: wait.query.clone(),
+                ..Default::default()
+              })
+            });
+            if let Some(session) = session {
+              self.set_popup(Box::new(ConfirmKillSession::new(session, terminate)));
+            }
+          },
           Action::CopyData(data) => {
             #[cfg(not(feature = "termux"))]
             {
@@ -461,6 +945,90 @@ impl App {
               );
             }
           },
+          Action::RequestSwitchConnection => {
+            let current = self.state.session().connection_name.clone();
+            self.set_popup(Box::new(ConnectionPicker::new(self.connection_entries(), Some(current))));
+          },
+          Action::RequestNewSession => {
+            self.set_popup(Box::new(ConnectionPicker::new(self.connection_entries(), None)));
+          },
+          Action::CloseSession => self.close_session().await?,
+          Action::NextSession => {
+            self.select_session((self.state.active_session + 1) % self.sessions.len());
+          },
+          Action::PreviousSession => {
+            self.select_session((self.state.active_session + self.sessions.len() - 1) % self.sessions.len());
+          },
+          Action::BeginTransaction => 'begin_action: {
+            let state = self.state.session();
+            if state.transaction.is_some() || state.query_task_running || state.pending_tx.is_some() {
+              break 'begin_action;
+            }
+            match self.session().database.begin_session_tx().await {
+              Ok(()) => {
+                let state = self.state.session_mut();
+                state.transaction = Some(0);
+                state.savepoints = vec![];
+                self.session().data.set_data_state(
+                  Some(Ok(Rows { headers: vec![], rows: vec![], rows_affected: None })),
+                  Some(Statement::StartTransaction {
+                    modes: vec![],
+                    begin: true,
+                    transaction: None,
+                    modifier: None,
+                    statements: vec![],
+                    exception: None,
+                    has_end_keyword: false,
+                  }),
+                );
+              },
+              Err(e) => self.session().data.set_data_state(Some(Err(e)), None),
+            }
+          },
+          Action::CommitTransaction | Action::RollbackTransaction if self.state.session().pending_tx.is_some() => {
+            self.confirm_pending_tx();
+          },
+          Action::CommitTransaction | Action::RollbackTransaction => 'end_action: {
+            let state = self.state.session();
+            if state.transaction.is_none() || state.query_task_running {
+              break 'end_action;
+            }
+            let (result, statement) = match action {
+              Action::CommitTransaction => (
+                self.session().database.commit_session_tx().await,
+                Statement::Commit { chain: false, end: false, modifier: None },
+              ),
+              _ => (
+                self.session().database.rollback_session_tx().await,
+                Statement::Rollback { chain: false, savepoint: None },
+              ),
+            };
+            let state = self.state.session_mut();
+            state.transaction = None;
+            state.savepoints = vec![];
+            self.session().data.set_data_state(
+              Some(result.map(|_| Rows { headers: vec![], rows: vec![], rows_affected: None })),
+              Some(statement),
+            );
+          },
+          Action::CreateSavepoint => 'savepoint_action: {
+            let state = self.state.session_mut();
+            if state.transaction.is_none() || state.query_task_running {
+              break 'savepoint_action;
+            }
+            let name = format!("rainfrog_{}", state.savepoints.len() + 1);
+            state.savepoints.push(name.clone());
+            action_tx.send(Action::Query(vec![format!("SAVEPOINT {name}")], false, false))?;
+          },
+          Action::RollbackToSavepoint => 'rollback_to_action: {
+            let state = self.state.session_mut();
+            if state.transaction.is_none() || state.query_task_running {
+              break 'rollback_to_action;
+            }
+            if let Some(name) = state.savepoints.pop() {
+              action_tx.send(Action::Query(vec![format!("ROLLBACK TO SAVEPOINT {name}")], false, false))?;
+            }
+          },
           Action::RequestExportData(row_count) => {
             self.set_popup(Box::new(ConfirmExport::new(*row_count)));
           },
@@ -470,13 +1038,24 @@ impl App {
           _ => {},
         }
         if !action_consumed {
+          // background sessions still tick, so their results keep streaming in
+          if action == Action::Tick {
+            for (i, session) in self.sessions.iter_mut().enumerate() {
+              if i != self.state.active_session {
+                session.data.update(action.clone(), &self.state)?;
+              }
+            }
+          }
           for i in ComponentImpls::iter() {
+            let session = &mut self.sessions[self.state.active_session];
             let action = match i {
-              ComponentImpls::Menu => self.components.menu.update(action.clone(), &self.state)?,
-              ComponentImpls::Editor => self.components.editor.update(action.clone(), &self.state)?,
+              ComponentImpls::Menu => session.menu.update(action.clone(), &self.state)?,
+              ComponentImpls::Editor => session.editor.update(action.clone(), &self.state)?,
               ComponentImpls::History => self.components.history.update(action.clone(), &self.state)?,
-              ComponentImpls::Data => self.components.data.update(action.clone(), &self.state)?,
+              ComponentImpls::Data => session.data.update(action.clone(), &self.state)?,
               ComponentImpls::Favorites => self.components.favorites.update(action.clone(), &self.state)?,
+              ComponentImpls::Notifications => self.components.notifications.update(action.clone(), &self.state)?,
+              ComponentImpls::Activity => self.components.activity.update(action.clone(), &self.state)?,
             };
             if let Some(action) = action {
               log::info!("{action:?}");
@@ -492,7 +1071,10 @@ impl App {
         })?;
       }
       if self.should_quit {
-        database.abort_query().await?;
+        for session in self.sessions.iter_mut() {
+          session.database.abort_query().await?;
+          session.database.rollback_session_tx().await?;
+        }
         tui.stop()?;
         break;
       }
@@ -502,13 +1084,17 @@ impl App {
   }
 
   fn draw_layout(&mut self, f: &mut Frame, action_tx: mpsc::UnboundedSender<Action>) -> Result<()> {
+    let sessions_layout = Layout::default()
+      .direction(Direction::Vertical)
+      .constraints([Constraint::Length(1), Constraint::Fill(1)])
+      .split(f.area());
     let hints_layout = Layout::default()
       .direction(Direction::Vertical)
       .constraints(match f.area().width {
         x if x < 160 => [Constraint::Fill(1), Constraint::Length(2)],
         _ => [Constraint::Fill(1), Constraint::Length(1)],
       })
-      .split(f.area());
+      .split(sessions_layout[1]);
     let root_layout = Layout::default()
       .direction(Direction::Horizontal)
       .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
@@ -551,6 +1137,16 @@ impl App {
             }
           },
           Focus::Favorites => {
+            if matches!(event.kind, MouseEventKind::Up(_)) {
+              self.set_focus(Focus::Notifications);
+            }
+          },
+          Focus::Notifications => {
+            if matches!(event.kind, MouseEventKind::Up(_)) {
+              self.set_focus(Focus::Activity);
+            }
+          },
+          Focus::Activity => {
             if matches!(event.kind, MouseEventKind::Up(_)) {
               self.set_focus(Focus::Editor);
             }
@@ -563,20 +1159,28 @@ impl App {
         self.last_frame_mouse_event = None;
       }
     }
-    let tabs = Tabs::new(vec![" 󰤏 query <alt+2>", "   history <alt+4>", "   favorites <alt+5>"])
-      .highlight_style(Style::new().fg(self.state.focus.tab_color()).reversed())
-      .select(self.last_focused_tab.tab_index())
-      .padding(" ", "")
-      .divider(" ");
+    let tabs = Tabs::new(vec![
+      " 󰤏 query <alt+2>",
+      "   history <alt+4>",
+      "   favorites <alt+5>",
+      "   notifications <alt+6>",
+      "   activity <alt+7>",
+    ])
+    .highlight_style(Style::new().fg(self.state.focus.tab_color()).reversed())
+    .select(self.last_focused_tab.tab_index())
+    .padding(" ", "")
+    .divider(" ");
 
+    self.render_sessions(f, sessions_layout[0]);
     let state = &self.state;
+    let session = &mut self.sessions[state.active_session];
 
     f.render_widget(tabs, tabs_layout[0]);
     f.render_widget(Clear, tabs_layout[1]);
 
     match self.last_focused_tab {
       Focus::Editor => {
-        self.components.editor.draw(f, tabs_layout[1], state).unwrap();
+        session.editor.draw(f, tabs_layout[1], state).unwrap();
       },
       Focus::History => {
         self.components.history.draw(f, tabs_layout[1], state).unwrap();
@@ -584,11 +1188,17 @@ impl App {
       Focus::Favorites => {
         self.components.favorites.draw(f, tabs_layout[1], state).unwrap();
       },
+      Focus::Notifications => {
+        self.components.notifications.draw(f, tabs_layout[1], state).unwrap();
+      },
+      Focus::Activity => {
+        self.components.activity.draw(f, tabs_layout[1], state).unwrap();
+      },
       Focus::Menu | Focus::Data | Focus::PopUp => (),
     };
 
-    self.components.menu.draw(f, root_layout[0], state).unwrap();
-    self.components.data.draw(f, right_layout[1], state).unwrap();
+    session.menu.draw(f, root_layout[0], state).unwrap();
+    session.data.draw(f, right_layout[1], state).unwrap();
     self.render_hints(f, hints_layout[1]);
 
     if let Some(popup) = &self.popup {
@@ -597,26 +1207,75 @@ impl App {
     Ok(())
   }
 
+  fn render_sessions(&self, frame: &mut Frame, area: Rect) {
+    let titles = self.state.sessions.iter().enumerate().map(|(i, session)| {
+      let mut spans = vec![Span::raw(format!(" 󰆼 {} {}", i + 1, session.connection_name))];
+      if self.sessions[i].read_only {
+        spans.push(Span::raw(" [read-only]").green());
+      }
+      if session.protected {
+        spans.push(Span::raw(" [protected]").magenta());
+      }
+      if session.pending_tx.is_some() {
+        spans.push(Span::raw(" [tx]").red());
+      } else if session.query_task_running {
+        spans.push(Span::raw(" [...]").yellow());
+      }
+      if let Some(count) = session.transaction {
+        let plural = if count == 1 { "" } else { "s" };
+        spans.push(Span::raw(format!(" in transaction ({count} statement{plural})")).red());
+      }
+      spans.push(Span::raw(" "));
+      Line::from(spans)
+    });
+    let tabs = Tabs::new(titles)
+      .highlight_style(Style::new().reversed())
+      .select(self.state.active_session)
+      .padding("", "")
+      .divider("│");
+    frame.render_widget(tabs, area);
+    frame.render_widget(
+      Line::from("<alt+t> new tab <alt+w> close tab <alt+n|p> next|prev tab <alt+c> switch connection ")
+        .right_aligned()
+        .dim(),
+      area,
+    );
+  }
+
   fn render_hints(&self, frame: &mut Frame, area: Rect) {
     let block = Block::default().style(Style::default().fg(Color::Blue));
     let help_text = format!(
-      "{}{}",
-      match self.state.query_task_running {
+      "{}{}{}",
+      match self.state.session().query_task_running {
         false => "",
+        _ if self.state.focus == Focus::PopUp => "",
+        _ if self.state.session().pending_tx.is_some() => "[<alt + y>] commit or roll back transaction ",
         _ if self.state.focus == Focus::Editor => "[<alt + q>] abort ",
-        _ if self.state.focus != Focus::PopUp => "[q] abort ",
+        _ => "[q] abort ",
+      },
+      match self.state.session().transaction {
+        Some(_) if self.state.focus != Focus::PopUp =>
+          "[<alt + y>] commit [<alt + u>] rollback [<alt + s>] savepoint [<alt + z>] rollback to savepoint ",
         _ => "",
       },
       match self.state.focus {
         Focus::Menu =>
           "[R] refresh [j|↓] down [k|↑] up [l|<enter>] table list [h|󰁮 ] schema list [/] search [g] top [G] bottom",
-        Focus::Editor if !self.state.query_task_running =>
+        Focus::Editor if !self.state.session().query_task_running && self.state.session().transaction.is_none() =>
+          "[<alt + enter>|<f5>] execute query [<alt + b>] begin transaction [<ctrl + f>|<alt + f>] save query to favorites",
+        Focus::Editor if !self.state.session().query_task_running =>
           "[<alt + enter>|<f5>] execute query [<ctrl + f>|<alt + f>] save query to favorites",
         Focus::History => "[j|↓] down [k|↑] up [y] copy query [I] edit query [D] clear history",
         Focus::Favorites =>
           "[j|↓] down [k|↑] up [y] copy query [I] edit query [D] delete entry [/] search [<esc>] clear search",
-        Focus::Data if !self.state.query_task_running =>
-          "[P] export [j|↓] next row [k|↑] prev row [w|e] next col [b] prev col [v] select field [V] select row [y] copy [g] top [G] bottom [0] first col [$] last col",
+        Focus::Notifications =>
+          "[L] listen on a channel [U] unlisten all [j|↓] down [k|↑] up [y] copy payload [p] pretty-print json [D] clear",
+        Focus::Activity =>
+          "[l] sessions|locks [j|↓] down [k|↑] up [c] cancel query [T] terminate session [r] refresh [y] copy query [I] edit query",
+        Focus::Data if self.sessions[self.state.active_session].data.is_editing() =>
+          "[<enter>] keep edit [<ctrl+n>] set to NULL [<backspace>] delete [<esc>] cancel",
+        Focus::Data if !self.state.session().query_task_running || self.state.session().pending_tx.is_some() =>
+          "[i] edit [A] apply edits [u] undo edit [X] discard edits [P] export [j|↓] next row [k|↑] prev row [w|e] next col [b] prev col [v] select field [V] select row [y] copy [g] top [G] bottom [0] first col [$] last col [[|]] prev|next result",
         Focus::PopUp => "[<esc>] cancel",
         _ => "",
       }
@@ -637,7 +1296,9 @@ impl App {
       .direction(Direction::Vertical)
       .split(block.inner(area));
 
-    let popup_cta = Paragraph::new(Line::from(popup.get_cta_text(&self.state)).centered()).wrap(Wrap { trim: false });
+    let popup_cta = Paragraph::new(Text::from(popup.get_cta_text(&self.state)))
+      .alignment(Alignment::Center)
+      .wrap(Wrap { trim: false });
     let popup_actions = Paragraph::new(Line::from(popup.get_actions_text(&self.state)).centered());
     frame.render_widget(Clear, area);
     frame.render_widget(block, area);
@@ -645,3 +1306,54 @@ impl App {
     frame.render_widget(popup_actions, center(layout[1], Constraint::Fill(1), Constraint::Percentage(50)));
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use clap::Parser;
+
+  use super::*;
+  use crate::database::StatementKind;
+
+  #[tokio::test]
+  async fn test_switch_connection() -> Result<()> {
+    let mut config = Config::new()?;
+    config.config._favorites_dir = std::env::temp_dir().join("rainfrog_switch_favorites");
+    config.db = ::config::Config::builder()
+      .add_source(::config::File::from_str(
+        r#"
+          [first]
+          driver = "sqlite"
+          connection_string = "sqlite:file:switch_first?mode=memory&cache=shared"
+
+          [second]
+          driver = "sqlite"
+          connection_string = "sqlite:file:switch_second?mode=memory&cache=shared"
+          read_only = true
+
+          [second.execution_policy]
+          drop = "Forbid"
+        "#,
+        ::config::FileFormat::Toml,
+      ))
+      .build()?
+      .try_deserialize()?;
+    let mut app = App::new(None, config)?;
+    app.args = Some(Cli::parse_from(["rainfrog"]));
+    let (session, state) = app.connect("first", None).await?;
+    app.sessions.push(session);
+    app.state.sessions.push(state);
+    app.state.session_mut().transaction = Some(1);
+
+    app.switch_connection("second".to_owned(), None).await?;
+    assert_eq!(app.state.session().connection_name, "second");
+    assert_eq!(app.state.session().transaction, None);
+    assert!(app.session().read_only);
+    assert_eq!(app.session().execution_policy.execution_type(StatementKind::Drop), ExecutionType::Forbid);
+    assert!(app.session().tunnel.is_none());
+
+    // the current connection is kept if the new one can't be made
+    assert!(app.switch_connection("third".to_owned(), None).await.is_err());
+    assert_eq!(app.state.session().connection_name, "second");
+    Ok(())
+  }
+}
@@ -1,9 +1,10 @@
 use std::{
   io::{self, Write},
+  path::PathBuf,
   str::FromStr,
 };
 
-use clap::Parser;
+use clap::{Args, Parser};
 use color_eyre::eyre::{self, Result};
 use serde::Deserialize;
 
@@ -43,11 +44,103 @@ pub struct Cli {
   #[arg(long = "port", value_name = "PORT", help = "Port for database connection (ex. 5432)")]
   pub port: Option<u16>,
 
+  #[arg(
+    long = "socket",
+    value_name = "SOCKET",
+    help = "Unix socket for database connection, used instead of host and port (ex. /var/run/postgresql for postgres, /var/run/mysqld/mysqld.sock for mysql)"
+  )]
+  pub socket: Option<PathBuf>,
+
   #[arg(long = "database", value_name = "DATABASE", help = "Name of database for connection (ex. postgres)")]
   pub database: Option<String>,
 
   #[arg(long = "driver", value_name = "DRIVER", help = "Driver for database connection (ex. postgres)")]
   pub driver: Option<Driver>,
+
+  #[arg(
+    long = "read-only",
+    help = "Only allow queries, and open connections read-only on the server where the database supports it"
+  )]
+  pub read_only: bool,
+
+  #[command(flatten)]
+  pub tls: TlsOptions,
+}
+
+/// TLS settings for Postgres and MySQL connections. Anything left unset
+/// falls back to the connection URL, then to the driver's default.
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TlsOptions {
+  #[arg(
+    long = "ssl-mode",
+    value_name = "SSL_MODE",
+    help = "Whether and how strictly to use TLS (disable, prefer, require, verify-ca, verify-full)"
+  )]
+  pub ssl_mode: Option<SslMode>,
+
+  #[arg(
+    long = "ssl-root-cert",
+    value_name = "PATH",
+    help = "CA certificate to verify the server's certificate against"
+  )]
+  pub ssl_root_cert: Option<PathBuf>,
+
+  #[arg(long = "ssl-cert", value_name = "PATH", help = "Client certificate to authenticate with")]
+  pub ssl_cert: Option<PathBuf>,
+
+  #[arg(long = "ssl-key", value_name = "PATH", help = "Private key for the client certificate")]
+  pub ssl_key: Option<PathBuf>,
+}
+
+impl TlsOptions {
+  pub fn is_empty(&self) -> bool {
+    *self == Self::default()
+  }
+
+  /// Fills in whatever isn't set here from `fallback`.
+  pub fn or(self, fallback: TlsOptions) -> Self {
+    Self {
+      ssl_mode: self.ssl_mode.or(fallback.ssl_mode),
+      ssl_root_cert: self.ssl_root_cert.or(fallback.ssl_root_cert),
+      ssl_cert: self.ssl_cert.or(fallback.ssl_cert),
+      ssl_key: self.ssl_key.or(fallback.ssl_key),
+    }
+  }
+}
+
+/// Named after libpq's `sslmode` values. MySQL's are mapped onto them,
+/// with `verify-full` standing in for `VERIFY_IDENTITY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum SslMode {
+  Disable,
+  Prefer,
+  Require,
+  VerifyCa,
+  VerifyFull,
+}
+
+impl FromStr for SslMode {
+  type Err = eyre::Report;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().replace('_', "-").as_str() {
+      "disable" | "disabled" => Ok(SslMode::Disable),
+      "prefer" | "preferred" => Ok(SslMode::Prefer),
+      "require" | "required" => Ok(SslMode::Require),
+      "verify-ca" => Ok(SslMode::VerifyCa),
+      "verify-full" | "verify-identity" => Ok(SslMode::VerifyFull),
+      _ => Err(eyre::Report::msg("Invalid SSL mode")),
+    }
+  }
+}
+
+impl TryFrom<String> for SslMode {
+  type Error = eyre::Report;
+
+  fn try_from(s: String) -> Result<Self, Self::Error> {
+    s.parse()
+  }
 }
 
 #[derive(Parser, Debug, Clone, Copy, Deserialize)]
@@ -87,6 +180,15 @@ pub fn extract_driver_from_url(url: &str) -> Result<Driver> {
   }
 }
 
+// the database is the last part of the url's path, e.g. `dbname` in
+// postgres://localhost:5432/dbname or service in jdbc:oracle:thin:@host:1521/service
+pub fn extract_database_from_url(url: &str) -> Option<String> {
+  let url = url.trim().split(['?', '#']).next()?;
+  let path = url.split_once("://").map_or(url, |(_, rest)| rest);
+  let (_, database) = path.rsplit_once('/')?;
+  (!database.is_empty()).then(|| database.to_string())
+}
+
 pub fn prompt_for_database_selection(config: &Config) -> Result<Option<(DatabaseConnection, String)>> {
   match config.db.len() {
     0 => Ok(None),
@@ -121,3 +223,35 @@ pub fn prompt_for_database_selection(config: &Config) -> Result<Option<(Database
     },
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_extract_database_from_url() {
+    assert_eq!(
+      extract_database_from_url("postgres://user:pw@localhost:5432/app?sslmode=require").as_deref(),
+      Some("app")
+    );
+    assert_eq!(extract_database_from_url("mysql://root@localhost:3306/shop").as_deref(), Some("shop"));
+    assert_eq!(extract_database_from_url("sqlite:///tmp/data.db").as_deref(), Some("data.db"));
+    assert_eq!(extract_database_from_url("jdbc:oracle:thin:@localhost:1521/FREEPDB1").as_deref(), Some("FREEPDB1"));
+    assert_eq!(extract_database_from_url("postgres://localhost:5432"), None);
+    assert_eq!(extract_database_from_url("postgres://localhost:5432/"), None);
+    assert_eq!(extract_database_from_url("sqlite://:memory:"), None);
+  }
+
+  #[test]
+  fn test_parse_ssl_mode() {
+    assert_eq!("verify-full".parse::<SslMode>().unwrap(), SslMode::VerifyFull);
+    assert_eq!("VERIFY_IDENTITY".parse::<SslMode>().unwrap(), SslMode::VerifyFull);
+    assert_eq!("Required".parse::<SslMode>().unwrap(), SslMode::Require);
+    assert!("allow".parse::<SslMode>().is_err());
+
+    let args = Cli::parse_from(["rainfrog", "--ssl-mode", "verify-ca", "--ssl-root-cert", "/etc/ca.pem"]);
+    assert_eq!(args.tls.ssl_mode, Some(SslMode::VerifyCa));
+    assert_eq!(args.tls.ssl_root_cert, Some(PathBuf::from("/etc/ca.pem")));
+    assert_eq!(args.tls.ssl_cert, None);
+  }
+}
@@ -0,0 +1,601 @@
+// completion suggestions for the query editor. the query is usually
+// incomplete while it's being typed, so rather than parsing it, the
+// tables in scope are found by walking its tokens.
+use std::collections::{HashMap, HashSet};
+
+use sqlparser::{
+  keywords::Keyword,
+  tokenizer::{Token, TokenWithSpan, Tokenizer},
+};
+
+use crate::{
+  cli::Driver,
+  database::{ColumnInfo, ObjectKind, TableInfo, get_dialect, get_keywords},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SuggestionKind {
+  Column,
+  Table,
+  View,
+  Schema,
+  Function,
+  Keyword,
+}
+
+impl SuggestionKind {
+  pub fn label(&self) -> &'static str {
+    match self {
+      SuggestionKind::Column => "column",
+      SuggestionKind::Table => "table",
+      SuggestionKind::View => "view",
+      SuggestionKind::Schema => "schema",
+      SuggestionKind::Function => "function",
+      SuggestionKind::Keyword => "keyword",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+  /// The name as it's shown in the popup.
+  pub label: String,
+  /// What replaces the word being typed when the suggestion is accepted.
+  pub text: String,
+  pub kind: SuggestionKind,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completion {
+  /// Length in chars of the partial word before the cursor, which the
+  /// accepted suggestion replaces.
+  pub replace_len: usize,
+  pub suggestions: Vec<Suggestion>,
+}
+
+/// A table named in a FROM, JOIN, UPDATE or INTO clause.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRef {
+  pub schema: Option<String>,
+  pub name: String,
+  pub alias: Option<String>,
+}
+
+const MAX_SUGGESTIONS: usize = 50;
+
+/// Keeps the schema objects of a connection around for completion.
+#[derive(Debug, Default)]
+pub struct Completer {
+  driver: Option<Driver>,
+  tables: Vec<TableInfo>,
+  columns: HashMap<(String, String), Vec<ColumnInfo>>,
+  // tables whose columns have been asked for, so they're only loaded once
+  requested: HashSet<(String, String)>,
+}
+
+impl Completer {
+  pub fn set_tables(&mut self, driver: Driver, tables: Vec<TableInfo>) {
+    self.driver = Some(driver);
+    self.tables = tables;
+    self.columns.clear();
+    self.requested.clear();
+  }
+
+  pub fn set_columns(&mut self, schema: String, table: String, columns: Vec<ColumnInfo>) {
+    self.columns.insert((schema, table), columns);
+  }
+
+  /// Returns the (schema, table) pairs in scope at the cursor whose columns
+  /// haven't been loaded or asked for yet, and marks them as asked for.
+  pub fn missing_columns(&mut self, lines: &[String], cursor: (usize, usize)) -> Vec<(String, String)> {
+    let missing: Vec<(String, String)> = self
+      .tables_in_scope(lines, cursor)
+      .into_iter()
+      .map(|(table, _)| (table.schema.clone(), table.name.clone()))
+      .filter(|key| !self.columns.contains_key(key) && !self.requested.contains(key))
+      .collect();
+    self.requested.extend(missing.iter().cloned());
+    missing
+  }
+
+  /// Suggestions for the word being typed at the cursor, if there are any.
+  /// Words that aren't qualified need at least two characters before
+  /// anything is suggested, so typing isn't interrupted all the time.
+  pub fn complete(&self, lines: &[String], cursor: (usize, usize)) -> Option<Completion> {
+    let line: Vec<char> = lines.get(cursor.0)?.chars().collect();
+    let before = &line[..cursor.1.min(line.len())];
+    let word_start = before.iter().rposition(|c| !is_word_char(*c)).map_or(0, |i| i + 1);
+    let word: String = before[word_start..].iter().collect();
+    let (qualifier, partial) = match word.rsplit_once('.') {
+      Some((qualifier, partial)) => (Some(unquote(qualifier.rsplit('.').next().unwrap_or_default())), partial),
+      None => (None, word.as_str()),
+    };
+    let partial = partial.trim_start_matches(['"', '`']);
+    if qualifier.is_none() && partial.chars().count() < 2 {
+      return None;
+    }
+
+    let candidates = match &qualifier {
+      Some(qualifier) => self.qualified_candidates(qualifier, lines, cursor),
+      None => self.candidates(partial, lines, cursor),
+    };
+    let mut scored: Vec<(i32, Suggestion)> = candidates
+      .into_iter()
+      .filter(|s| !s.label.eq_ignore_ascii_case(partial))
+      .filter_map(|s| fuzzy_score(&s.label, partial).map(|score| (score, s)))
+      .collect();
+    scored.sort_by(|(a_score, a), (b_score, b)| {
+      b_score.cmp(a_score).then(a.kind.cmp(&b.kind)).then(a.label.len().cmp(&b.label.len())).then(a.label.cmp(&b.label))
+    });
+    let mut seen = HashSet::new();
+    let suggestions: Vec<Suggestion> = scored
+      .into_iter()
+      .map(|(_, s)| s)
+      .filter(|s| seen.insert((s.text.clone(), s.kind)))
+      .take(MAX_SUGGESTIONS)
+      .collect();
+    if suggestions.is_empty() {
+      return None;
+    }
+    let replace_len = word.rsplit('.').next().unwrap_or_default().chars().count();
+    Some(Completion { replace_len, suggestions })
+  }
+
+  // after a dot: columns of a table or alias, or tables in a schema
+  fn qualified_candidates(&self, qualifier: &str, lines: &[String], cursor: (usize, usize)) -> Vec<Suggestion> {
+    let in_scope = self.tables_in_scope(lines, cursor);
+    let mut tables: Vec<&TableInfo> = in_scope
+      .iter()
+      .filter(|(table, table_ref)| {
+        table_ref
+          .alias
+          .as_deref()
+          .map_or(table.name.eq_ignore_ascii_case(qualifier), |a| a.eq_ignore_ascii_case(qualifier))
+      })
+      .map(|(table, _)| *table)
+      .collect();
+    if tables.is_empty() {
+      tables = self.tables.iter().filter(|t| t.name.eq_ignore_ascii_case(qualifier)).collect();
+    }
+    let mut candidates: Vec<Suggestion> = tables.iter().flat_map(|t| self.column_suggestions(t)).collect();
+    candidates.extend(
+      self.tables.iter().filter(|t| t.schema.eq_ignore_ascii_case(qualifier)).map(|t| self.table_suggestion(t)),
+    );
+    candidates
+  }
+
+  fn candidates(&self, partial: &str, lines: &[String], cursor: (usize, usize)) -> Vec<Suggestion> {
+    let mut candidates: Vec<Suggestion> =
+      self.tables_in_scope(lines, cursor).iter().flat_map(|(t, _)| self.column_suggestions(t)).collect();
+    candidates.extend(self.tables.iter().map(|t| self.table_suggestion(t)));
+    let schemas: HashSet<&str> = self.tables.iter().map(|t| t.schema.as_str()).filter(|s| !s.is_empty()).collect();
+    candidates.extend(schemas.into_iter().map(|s| Suggestion {
+      label: s.to_owned(),
+      text: self.identifier(s),
+      kind: SuggestionKind::Schema,
+    }));
+    let upper = partial.chars().all(|c| !c.is_lowercase());
+    candidates.extend(self.functions().iter().map(|f| Suggestion {
+      label: f.to_string(),
+      text: format!("{}(", if upper { f.to_uppercase() } else { f.to_string() }),
+      kind: SuggestionKind::Function,
+    }));
+    candidates.extend(get_keywords().into_iter().map(|k| Suggestion {
+      text: if upper { k.clone() } else { k.to_lowercase() },
+      label: k.to_lowercase(),
+      kind: SuggestionKind::Keyword,
+    }));
+    candidates
+  }
+
+  fn table_suggestion(&self, table: &TableInfo) -> Suggestion {
+    Suggestion {
+      label: table.name.clone(),
+      text: self.identifier(&table.name),
+      kind: match table.kind {
+        ObjectKind::View | ObjectKind::MaterializedView => SuggestionKind::View,
+        _ => SuggestionKind::Table,
+      },
+    }
+  }
+
+  fn column_suggestions(&self, table: &TableInfo) -> Vec<Suggestion> {
+    self.columns.get(&(table.schema.clone(), table.name.clone())).map_or(vec![], |columns| {
+      columns
+        .iter()
+        .map(|c| Suggestion { label: c.name.clone(), text: self.identifier(&c.name), kind: SuggestionKind::Column })
+        .collect()
+    })
+  }
+
+  // the known tables referenced by the statement under the cursor
+  fn tables_in_scope(&self, lines: &[String], cursor: (usize, usize)) -> Vec<(&TableInfo, TableRef)> {
+    let Some(driver) = self.driver else {
+      return vec![];
+    };
+    referenced_tables(&lines.join("\n"), cursor, driver)
+      .into_iter()
+      .filter_map(|table_ref| {
+        let matches = |t: &&TableInfo| {
+          t.name.eq_ignore_ascii_case(&table_ref.name)
+            && table_ref.schema.as_ref().is_none_or(|s| t.schema.eq_ignore_ascii_case(s))
+        };
+        // unqualified names are looked up in the default schema first
+        let table = self
+          .tables
+          .iter()
+          .filter(matches)
+          .find(|t| t.schema == "public" || t.schema.is_empty())
+          .or_else(|| self.tables.iter().find(matches))?;
+        Some((table, table_ref))
+      })
+      .collect()
+  }
+
+  // quotes names that wouldn't survive being written bare
+  fn identifier(&self, name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+      && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+      // postgres folds unquoted names to lowercase
+      && (!matches!(self.driver, Some(Driver::Postgres)) || !name.chars().any(|c| c.is_ascii_uppercase()));
+    match (plain, self.driver) {
+      (true, _) => name.to_owned(),
+      (false, Some(Driver::MySql)) => format!("`{}`", name.replace('`', "``")),
+      (false, _) => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+  }
+
+  fn functions(&self) -> Vec<&'static str> {
+    let mut functions = COMMON_FUNCTIONS.to_vec();
+    functions.extend_from_slice(match self.driver {
+      Some(Driver::Postgres) => POSTGRES_FUNCTIONS,
+      Some(Driver::MySql) => MYSQL_FUNCTIONS,
+      Some(Driver::Sqlite) => SQLITE_FUNCTIONS,
+      Some(Driver::Oracle) => ORACLE_FUNCTIONS,
+      None => &[],
+    });
+    functions
+  }
+}
+
+const COMMON_FUNCTIONS: &[&str] = &[
+  "abs", "avg", "cast", "coalesce", "count", "lower", "max", "min", "nullif", "replace", "round", "sum", "trim",
+  "upper",
+];
+
+const POSTGRES_FUNCTIONS: &[&str] = &[
+  "age",
+  "array_agg",
+  "array_length",
+  "concat",
+  "current_date",
+  "current_timestamp",
+  "date_part",
+  "date_trunc",
+  "dense_rank",
+  "extract",
+  "gen_random_uuid",
+  "generate_series",
+  "greatest",
+  "json_agg",
+  "json_build_object",
+  "jsonb_agg",
+  "jsonb_build_object",
+  "lag",
+  "lead",
+  "least",
+  "length",
+  "now",
+  "rank",
+  "regexp_replace",
+  "row_number",
+  "split_part",
+  "string_agg",
+  "substring",
+  "to_char",
+  "to_timestamp",
+  "unnest",
+];
+
+const MYSQL_FUNCTIONS: &[&str] = &[
+  "char_length",
+  "concat",
+  "concat_ws",
+  "curdate",
+  "date_add",
+  "date_format",
+  "date_sub",
+  "datediff",
+  "dense_rank",
+  "group_concat",
+  "if",
+  "ifnull",
+  "json_arrayagg",
+  "json_extract",
+  "json_object",
+  "lag",
+  "last_insert_id",
+  "lead",
+  "length",
+  "now",
+  "rank",
+  "row_number",
+  "str_to_date",
+  "substring",
+  "timestampdiff",
+  "uuid",
+];
+
+const SQLITE_FUNCTIONS: &[&str] = &[
+  "date",
+  "datetime",
+  "group_concat",
+  "ifnull",
+  "iif",
+  "instr",
+  "json_extract",
+  "json_group_array",
+  "json_object",
+  "julianday",
+  "last_insert_rowid",
+  "length",
+  "printf",
+  "random",
+  "strftime",
+  "substr",
+  "time",
+  "total",
+  "typeof",
+];
+
+const ORACLE_FUNCTIONS: &[&str] = &[
+  "add_months",
+  "decode",
+  "instr",
+  "length",
+  "listagg",
+  "months_between",
+  "nvl",
+  "nvl2",
+  "substr",
+  "sysdate",
+  "systimestamp",
+  "to_char",
+  "to_date",
+  "to_number",
+  "trunc",
+];
+
+fn is_word_char(c: char) -> bool {
+  c.is_alphanumeric() || matches!(c, '_' | '$' | '.' | '"' | '`')
+}
+
+fn unquote(s: &str) -> String {
+  s.trim_matches(['"', '`']).to_owned()
+}
+
+/// Scores how well `pattern` fuzzy matches `candidate`, or returns `None` if
+/// the characters of `pattern` don't all appear in it, in order. Prefixes
+/// and consecutive characters score higher.
+pub fn fuzzy_score(candidate: &str, pattern: &str) -> Option<i32> {
+  let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+  let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+  let mut score = 0;
+  let mut next = 0;
+  let mut last: Option<usize> = None;
+  for p in pattern.iter() {
+    let i = next + candidate[next..].iter().position(|c| c == p)?;
+    score += match last {
+      Some(last) if i == last + 1 => 8,
+      _ if i == 0 => 10,
+      // the start of a word in a snake_case name
+      _ if candidate[i - 1] == '_' => 6,
+      _ => 0,
+    };
+    score -= (i - next) as i32;
+    last = Some(i);
+    next = i + 1;
+  }
+  if candidate.starts_with(&pattern) {
+    score += 20;
+  }
+  Some(score)
+}
+
+/// Finds the tables named in the statement under the cursor, with their
+/// aliases. Returns nothing if the query can't be tokenized, e.g. while
+/// the cursor is inside an unfinished string.
+pub fn referenced_tables(query: &str, cursor: (usize, usize), driver: Driver) -> Vec<TableRef> {
+  let dialect = get_dialect(driver);
+  let Ok(tokens) = Tokenizer::new(&*dialect, query).tokenize_with_location() else {
+    return vec![];
+  };
+  let cursor = (cursor.0 as u64 + 1, cursor.1 as u64 + 1);
+  let mut statement: Vec<&TokenWithSpan> = vec![];
+  for token in tokens.iter() {
+    match token.token {
+      Token::SemiColon if (token.span.start.line, token.span.start.column) >= cursor => break,
+      Token::SemiColon => statement.clear(),
+      Token::Whitespace(_) => {},
+      _ => statement.push(token),
+    }
+  }
+  let tokens: Vec<&Token> = statement.into_iter().map(|t| &t.token).collect();
+
+  let mut tables = vec![];
+  let mut i = 0;
+  while i < tokens.len() {
+    let starts_list = matches!(tokens[i], Token::Word(w) if matches!(w.keyword, Keyword::FROM | Keyword::JOIN | Keyword::UPDATE | Keyword::INTO));
+    i += 1;
+    if !starts_list {
+      continue;
+    }
+    // a comma separated list of tables, each with an optional alias
+    while let Some((table_ref, len)) = table_ref(&tokens[i..]) {
+      tables.push(table_ref);
+      i += len;
+      if matches!(tokens.get(i), Some(Token::Comma)) {
+        i += 1;
+      } else {
+        break;
+      }
+    }
+  }
+  tables
+}
+
+// reads `[schema.]table [[as] alias]`, returning how many tokens it took
+fn table_ref(tokens: &[&Token]) -> Option<(TableRef, usize)> {
+  let mut names = vec![];
+  let mut i = 0;
+  while let Some(Token::Word(word)) = tokens.get(i) {
+    names.push(word.value.clone());
+    i += 1;
+    if matches!(tokens.get(i), Some(Token::Period)) {
+      i += 1;
+    } else {
+      break;
+    }
+  }
+  let name = names.pop()?;
+  let schema = names.pop();
+  if matches!(tokens.get(i), Some(Token::Word(w)) if w.keyword == Keyword::AS) {
+    i += 1;
+  }
+  let alias = match tokens.get(i) {
+    Some(Token::Word(w)) if w.keyword == Keyword::NoKeyword || w.quote_style.is_some() => {
+      i += 1;
+      Some(w.value.clone())
+    },
+    _ => None,
+  };
+  Some((TableRef { schema, name, alias }, i))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lines(query: &str) -> Vec<String> {
+    query.lines().map(str::to_owned).collect()
+  }
+
+  fn table(schema: &str, name: &str) -> TableInfo {
+    TableInfo { schema: schema.to_owned(), name: name.to_owned(), kind: ObjectKind::Table, comment: None }
+  }
+
+  fn column(name: &str) -> ColumnInfo {
+    ColumnInfo { name: name.to_owned(), type_name: "text".to_owned(), nullable: true, default: None, comment: None }
+  }
+
+  fn completer() -> Completer {
+    let mut completer = Completer::default();
+    completer.set_tables(
+      Driver::Postgres,
+      vec![
+        table("public", "users"),
+        table("public", "user_roles"),
+        table("billing", "invoices"),
+        table("public", "Orders"),
+      ],
+    );
+    completer.set_columns("public".to_owned(), "users".to_owned(), vec![column("id"), column("email")]);
+    completer.set_columns("billing".to_owned(), "invoices".to_owned(), vec![column("id"), column("amount")]);
+    completer
+  }
+
+  fn labels(completion: Option<Completion>) -> Vec<(String, SuggestionKind)> {
+    completion.unwrap().suggestions.into_iter().map(|s| (s.label, s.kind)).collect()
+  }
+
+  #[test]
+  fn test_referenced_tables() {
+    let query = "select * from public.users u, user_roles join billing.invoices as i on i.id = u.id where";
+    assert_eq!(
+      referenced_tables(query, (0, query.len()), Driver::Postgres),
+      vec![
+        TableRef { schema: Some("public".to_owned()), name: "users".to_owned(), alias: Some("u".to_owned()) },
+        TableRef { schema: None, name: "user_roles".to_owned(), alias: None },
+        TableRef { schema: Some("billing".to_owned()), name: "invoices".to_owned(), alias: Some("i".to_owned()) },
+      ]
+    );
+    assert_eq!(
+      referenced_tables("update users set x = 1", (0, 0), Driver::Postgres),
+      vec![TableRef { schema: None, name: "users".to_owned(), alias: None }]
+    );
+  }
+
+  #[test]
+  fn test_referenced_tables_of_statement_under_cursor() {
+    let query = "select * from users;\nselect  from invoices;\nselect 1";
+    let tables = referenced_tables(query, (1, 7), Driver::Postgres);
+    assert_eq!(tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["invoices"]);
+    assert!(referenced_tables(query, (2, 8), Driver::Postgres).is_empty());
+    assert!(referenced_tables("select 'unterminated from users", (0, 5), Driver::Postgres).is_empty());
+  }
+
+  #[test]
+  fn test_fuzzy_score() {
+    assert!(fuzzy_score("user_roles", "ur").is_some());
+    assert!(fuzzy_score("users", "xu").is_none());
+    assert!(fuzzy_score("users", "us") > fuzzy_score("user_roles", "ur"));
+    assert!(fuzzy_score("user_roles", "ur") > fuzzy_score("hours", "ur"));
+  }
+
+  #[test]
+  fn test_columns_in_scope_come_first() {
+    let completer = completer();
+    let query = lines("select em from users");
+    let suggestions = labels(completer.complete(&query, (0, 9)));
+    assert_eq!(suggestions[0], ("email".to_owned(), SuggestionKind::Column));
+    // columns of tables that aren't in the query aren't suggested
+    let query = lines("select amo from users");
+    assert!(!labels(completer.complete(&query, (0, 10))).contains(&("amount".to_owned(), SuggestionKind::Column)));
+  }
+
+  #[test]
+  fn test_qualified_completion() {
+    let completer = completer();
+    let query = lines("select i. from billing.invoices i");
+    let completion = completer.complete(&query, (0, 9)).unwrap();
+    assert_eq!(completion.replace_len, 0);
+    assert_eq!(
+      labels(Some(completion)),
+      vec![("id".to_owned(), SuggestionKind::Column), ("amount".to_owned(), SuggestionKind::Column)]
+    );
+
+    let query = lines("select * from billing.inv");
+    let completion = completer.complete(&query, (0, 25)).unwrap();
+    assert_eq!(completion.replace_len, 3);
+    assert_eq!(labels(Some(completion)), vec![("invoices".to_owned(), SuggestionKind::Table)]);
+  }
+
+  #[test]
+  fn test_suggestion_text() {
+    let completer = completer();
+    let query = lines("select * from ord");
+    let completion = completer.complete(&query, (0, 17)).unwrap();
+    assert_eq!(completion.suggestions[0].text, "\"Orders\"");
+    let query = lines("SEL");
+    assert_eq!(completer.complete(&query, (0, 3)).unwrap().suggestions[0].text, "SELECT");
+    let query = lines("select coal");
+    let completion = completer.complete(&query, (0, 11)).unwrap();
+    assert_eq!(completion.suggestions[0].text, "coalesce(");
+    assert_eq!(completion.replace_len, 4);
+  }
+
+  #[test]
+  fn test_short_words_are_not_completed() {
+    let completer = completer();
+    assert!(completer.complete(&lines("select u"), (0, 8)).is_none());
+    assert!(completer.complete(&lines(""), (0, 0)).is_none());
+  }
+
+  #[test]
+  fn test_missing_columns_are_requested_once() {
+    let mut completer = completer();
+    let query = lines("select * from users join user_roles on true");
+    assert_eq!(completer.missing_columns(&query, (0, 8)), vec![("public".to_owned(), "user_roles".to_owned())]);
+    assert!(completer.missing_columns(&query, (0, 8)).is_empty());
+  }
+}
@@ -0,0 +1,409 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, symbols::scrollbar, widgets::*};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{Component, Frame};
+use crate::{
+  action::Action,
+  app::AppState,
+  config::Config,
+  database::{BlockingTree, LockWait, blocking_trees},
+  focus::Focus,
+};
+
+const DEFAULT_REFRESH_INTERVAL: u64 = 2;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum View {
+  #[default]
+  Sessions,
+  /// Who blocks whom, as trees.
+  Locks,
+}
+
+// a row of the lock view, along with the session at the root of its tree
+struct BlockingRow<'a> {
+  depth: usize,
+  root: &'a str,
+  session: &'a LockWait,
+}
+
+fn blocking_rows(waits: &[LockWait]) -> Vec<BlockingRow<'_>> {
+  fn walk<'a>(tree: &BlockingTree<'a>, depth: usize, root: &'a str, rows: &mut Vec<BlockingRow<'a>>) {
+    rows.push(BlockingRow { depth, root, session: tree.session });
+    tree.children.iter().for_each(|child| walk(child, depth + 1, root, rows));
+  }
+  let mut rows = vec![];
+  for tree in blocking_trees(waits) {
+    walk(&tree, 0, &tree.session.id, &mut rows);
+  }
+  rows
+}
+
+#[derive(Default)]
+pub struct Activity {
+  command_tx: Option<UnboundedSender<Action>>,
+  config: Config,
+  view: View,
+  table_state: TableState,
+  // the selection follows the session across refreshes, which reorder them
+  selected_id: Option<String>,
+  lock_table_state: TableState,
+  last_refresh: Option<Instant>,
+  copied: bool,
+}
+
+impl Activity {
+  pub fn new() -> Self {
+    Activity {
+      command_tx: None,
+      config: Config::default(),
+      view: View::Sessions,
+      table_state: TableState::default(),
+      selected_id: None,
+      lock_table_state: TableState::default(),
+      last_refresh: None,
+      copied: false,
+    }
+  }
+
+  fn select(&mut self, i: usize, app_state: &AppState) {
+    match self.view {
+      View::Sessions => {
+        let sessions = &app_state.session().activity;
+        let i = i.min(sessions.len().saturating_sub(1));
+        self.table_state.select(Some(i));
+        self.selected_id = sessions.get(i).map(|session| session.id.clone());
+      },
+      View::Locks => {
+        let count = blocking_rows(&app_state.session().lock_waits).len();
+        self.lock_table_state.select(Some(i.min(count.saturating_sub(1))));
+      },
+    }
+  }
+
+  fn current(&self) -> usize {
+    match self.view {
+      View::Sessions => self.table_state.selected(),
+      View::Locks => self.lock_table_state.selected(),
+    }
+    .unwrap_or(0)
+  }
+
+  fn count(&self, app_state: &AppState) -> usize {
+    match self.view {
+      View::Sessions => app_state.session().activity.len(),
+      View::Locks => blocking_rows(&app_state.session().lock_waits).len(),
+    }
+  }
+
+  // the session that cancelling and terminating act on, which in the lock
+  // view is the one at the root of the selected row's tree
+  fn target(&self, app_state: &AppState) -> Option<String> {
+    let session = app_state.session();
+    match self.view {
+      View::Sessions => self.table_state.selected().and_then(|i| session.activity.get(i)).map(|s| s.id.clone()),
+      View::Locks => {
+        let rows = blocking_rows(&session.lock_waits);
+        self.lock_table_state.selected().and_then(|i| rows.get(i)).map(|row| row.root.to_owned())
+      },
+    }
+  }
+
+  fn selected_query(&self, app_state: &AppState) -> Option<String> {
+    let session = app_state.session();
+    match self.view {
+      View::Sessions => self.table_state.selected().and_then(|i| session.activity.get(i)).and_then(|s| s.query.clone()),
+      View::Locks => {
+        let rows = blocking_rows(&session.lock_waits);
+        self.lock_table_state.selected().and_then(|i| rows.get(i)).and_then(|row| row.session.query.clone())
+      },
+    }
+  }
+
+  fn refresh(&mut self) -> Action {
+    self.last_refresh = Some(Instant::now());
+    match self.view {
+      View::Sessions => Action::RefreshActivity,
+      View::Locks => Action::RefreshLockWaits,
+    }
+  }
+
+  fn refresh_interval(&self) -> Duration {
+    Duration::from_secs(self.config.settings.activity_refresh_interval.unwrap_or(DEFAULT_REFRESH_INTERVAL))
+  }
+
+  fn block(&self, focused: bool, title: String) -> Block<'static> {
+    Block::default()
+      .borders(Borders::ALL)
+      .border_style(if focused { Style::new().green() } else { Style::new().dim() })
+      .title(Line::from(title).right_aligned())
+  }
+
+  fn draw_sessions(&mut self, f: &mut Frame<'_>, area: Rect, app_state: &AppState) {
+    let focused = app_state.focus == Focus::Activity;
+    let session = app_state.session();
+    let sessions = &session.activity;
+    let title = match (&session.activity_error, session.activity_loaded_at) {
+      (Some(_), _) | (_, None) => "".to_string(),
+      (None, Some(loaded_at)) => format!(
+        " {}{} session{}, refreshed at {} ",
+        if self.copied { "copied! - " } else { "" },
+        sessions.len(),
+        if sessions.len() == 1 { "" } else { "s" },
+        loaded_at.format("%H:%M:%S")
+      ),
+    };
+    let block = self.block(focused, title);
+
+    if let Some(e) = &session.activity_error {
+      f.render_widget(Paragraph::new(e.as_str()).red().wrap(Wrap { trim: false }).block(block), area);
+      return;
+    }
+
+    // keep the selection on the same session if it's still there
+    let selected = self
+      .selected_id
+      .as_ref()
+      .and_then(|id| sessions.iter().position(|session| &session.id == id))
+      .unwrap_or(self.table_state.selected().unwrap_or(0));
+    self.select(selected, app_state);
+
+    let header = Row::new(["id", "user", "database", "client", "state", "waiting on", "duration", "query"])
+      .style(Style::new().bold())
+      .bottom_margin(1);
+    let rows = sessions.iter().map(|session| {
+      let state = session.state.clone().unwrap_or_default();
+      let wait_style = match &session.wait_event {
+        Some(wait) if wait.to_lowercase().contains("lock") => Style::new().red(),
+        _ => Style::new(),
+      };
+      Row::new([
+        Cell::from(session.id.clone()),
+        Cell::from(session.user.clone().unwrap_or_default()),
+        Cell::from(session.database.clone().unwrap_or_default()),
+        Cell::from(session.client.clone().unwrap_or_default()),
+        Cell::from(state.clone()).style(state_style(&state)),
+        Cell::from(session.wait_event.clone().unwrap_or_default()).style(wait_style),
+        Cell::from(session.duration.map(format_duration).unwrap_or_default()),
+        Cell::from(one_line(session.query.as_deref())),
+      ])
+    });
+    let widths = [
+      Constraint::Length(8),
+      Constraint::Length(12),
+      Constraint::Length(12),
+      Constraint::Length(24),
+      Constraint::Length(20),
+      Constraint::Length(20),
+      Constraint::Length(9),
+      Constraint::Fill(1),
+    ];
+    let table = Table::new(rows, widths)
+      .header(header)
+      .block(block)
+      .row_highlight_style(if focused { Style::new().reversed() } else { Style::new() })
+      .column_spacing(1);
+    f.render_stateful_widget(table, area, &mut self.table_state);
+    draw_scrollbar(f, area, focused, sessions.len(), self.table_state.selected().unwrap_or(0));
+  }
+
+  fn draw_locks(&mut self, f: &mut Frame<'_>, area: Rect, app_state: &AppState) {
+    let focused = app_state.focus == Focus::Activity;
+    let session = app_state.session();
+    let waiting = session.lock_waits.iter().filter(|wait| !wait.blocked_by.is_empty()).count();
+    let title = match (&session.lock_waits_error, session.activity_loaded_at) {
+      (Some(_), _) | (_, None) => "".to_string(),
+      (None, Some(loaded_at)) => format!(
+        " {}{} waiting on locks, refreshed at {} ",
+        if self.copied { "copied! - " } else { "" },
+        match waiting {
+          0 => "no sessions".to_string(),
+          1 => "1 session".to_string(),
+          n => format!("{n} sessions"),
+        },
+        loaded_at.format("%H:%M:%S")
+      ),
+    };
+    let block = self.block(focused, title);
+
+    if let Some(e) = &session.lock_waits_error {
+      f.render_widget(Paragraph::new(e.as_str()).red().wrap(Wrap { trim: false }).block(block), area);
+      return;
+    }
+
+    let blocking = blocking_rows(&session.lock_waits);
+    let selected = self.lock_table_state.selected().unwrap_or(0);
+    self.lock_table_state.select(Some(selected.min(blocking.len().saturating_sub(1))));
+
+    let header =
+      Row::new(["session", "state", "lock", "on", "age", "query"]).style(Style::new().bold()).bottom_margin(1);
+    let rows = blocking.iter().map(|row| {
+      let state = row.session.state.clone().unwrap_or_default();
+      let id = match row.depth {
+        0 => Span::styled(row.session.id.clone(), Style::new().red().bold()),
+        depth => Span::raw(format!("{}└ {}", "  ".repeat(depth - 1), row.session.id)),
+      };
+      Row::new([
+        Cell::from(id),
+        Cell::from(state.clone()).style(state_style(&state)),
+        Cell::from(row.session.lock_mode.clone().unwrap_or_default()),
+        Cell::from(row.session.relation.clone().unwrap_or_default()),
+        Cell::from(row.session.age.map(format_duration).unwrap_or_default()),
+        Cell::from(one_line(row.session.query.as_deref())),
+      ])
+    });
+    let widths = [
+      Constraint::Length(16),
+      Constraint::Length(20),
+      Constraint::Length(24),
+      Constraint::Length(24),
+      Constraint::Length(9),
+      Constraint::Fill(1),
+    ];
+    let table = Table::new(rows, widths)
+      .header(header)
+      .block(block)
+      .row_highlight_style(if focused { Style::new().reversed() } else { Style::new() })
+      .column_spacing(1);
+    f.render_stateful_widget(table, area, &mut self.lock_table_state);
+    draw_scrollbar(f, area, focused, blocking.len(), self.lock_table_state.selected().unwrap_or(0));
+  }
+}
+
+impl Component for Activity {
+  fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+    self.command_tx = Some(tx);
+    Ok(())
+  }
+
+  fn register_config_handler(&mut self, config: Config) -> Result<()> {
+    self.config = config;
+    Ok(())
+  }
+
+  fn handle_mouse_events(&mut self, mouse: MouseEvent, app_state: &AppState) -> Result<Option<Action>> {
+    if app_state.focus != Focus::Activity {
+      return Ok(None);
+    }
+    self.copied = false;
+    let current = self.current();
+    match mouse.kind {
+      MouseEventKind::ScrollDown => self.select(current.saturating_add(1), app_state),
+      MouseEventKind::ScrollUp => self.select(current.saturating_sub(1), app_state),
+      _ => {},
+    };
+    Ok(None)
+  }
+
+  fn handle_key_events(&mut self, key: KeyEvent, app_state: &AppState) -> Result<Option<Action>> {
+    if app_state.focus != Focus::Activity {
+      return Ok(None);
+    }
+    self.copied = false;
+    let current = self.current();
+    match key.code {
+      KeyCode::Down | KeyCode::Char('j') => self.select(current.saturating_add(1), app_state),
+      KeyCode::Up | KeyCode::Char('k') => self.select(current.saturating_sub(1), app_state),
+      KeyCode::Char('g') => self.select(0, app_state),
+      KeyCode::Char('G') => self.select(self.count(app_state).saturating_sub(1), app_state),
+      KeyCode::Char('l') => {
+        self.view = match self.view {
+          View::Sessions => View::Locks,
+          View::Locks => View::Sessions,
+        };
+        let action = self.refresh();
+        self.command_tx.as_ref().unwrap().send(action)?;
+      },
+      KeyCode::Char('r') => {
+        let action = self.refresh();
+        self.command_tx.as_ref().unwrap().send(action)?;
+      },
+      KeyCode::Char('c') => {
+        if let Some(id) = self.target(app_state) {
+          self.command_tx.as_ref().unwrap().send(Action::RequestCancelSession(id))?;
+        }
+      },
+      KeyCode::Char('T') => {
+        if let Some(id) = self.target(app_state) {
+          self.command_tx.as_ref().unwrap().send(Action::RequestTerminateSession(id))?;
+        }
+      },
+      KeyCode::Char('y') => {
+        if let Some(query) = self.selected_query(app_state) {
+          self.command_tx.as_ref().unwrap().send(Action::CopyData(query))?;
+          self.copied = true;
+        }
+      },
+      KeyCode::Char('I') => {
+        if let Some(query) = self.selected_query(app_state) {
+          let lines = query.lines().map(String::from).collect();
+          self.command_tx.as_ref().unwrap().send(Action::QueryToEditor(lines))?;
+          self.command_tx.as_ref().unwrap().send(Action::FocusEditor)?;
+        }
+      },
+      _ => {},
+    };
+    Ok(None)
+  }
+
+  // only reloads while the monitor is on screen
+  fn update(&mut self, action: Action, app_state: &AppState) -> Result<Option<Action>> {
+    if action != Action::Tick || app_state.focus != Focus::Activity {
+      return Ok(None);
+    }
+    if self.last_refresh.is_none_or(|last| last.elapsed() >= self.refresh_interval()) {
+      return Ok(Some(self.refresh()));
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect, app_state: &AppState) -> Result<()> {
+    match self.view {
+      View::Sessions => self.draw_sessions(f, area, app_state),
+      View::Locks => self.draw_locks(f, area, app_state),
+    }
+    Ok(())
+  }
+}
+
+fn state_style(state: &str) -> Style {
+  match state {
+    "active" | "Query" => Style::new().green(),
+    s if s.starts_with("idle in transaction") => Style::new().yellow(),
+    _ => Style::new(),
+  }
+}
+
+// queries are shown on one line
+fn one_line(query: Option<&str>) -> String {
+  query.unwrap_or_default().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn draw_scrollbar(f: &mut Frame<'_>, area: Rect, focused: bool, count: usize, position: usize) {
+  // the borders and the header take up four lines
+  if count <= area.height.saturating_sub(4) as usize {
+    return;
+  }
+  let vertical_scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+    .symbols(scrollbar::VERTICAL)
+    .style(if focused { Style::default().fg(Color::Green) } else { Style::default() });
+  let mut vertical_scrollbar_state = ScrollbarState::new(count.saturating_sub(1)).position(position);
+  f.render_stateful_widget(
+    vertical_scrollbar,
+    area.inner(Margin { vertical: 1, horizontal: 0 }),
+    &mut vertical_scrollbar_state,
+  );
+}
+
+fn format_duration(seconds: f64) -> String {
+  let whole = seconds as u64;
+  match whole {
+    0..60 => format!("{seconds:.1}s"),
+    60..3600 => format!("{}m {:02}s", whole / 60, whole % 60),
+    _ => format!("{}h {:02}m", whole / 3600, whole % 3600 / 60),
+  }
+}
@@ -1,25 +1,39 @@
+use std::collections::BTreeMap;
+
 use color_eyre::eyre::{self, Result};
 use crossterm::event::{KeyEvent, MouseEventKind};
 use csv::Writer;
 use ratatui::{prelude::*, symbols::scrollbar, widgets::*};
-use sqlparser::ast::Statement;
+use sqlparser::{
+  ast::Statement,
+  keywords::Keyword,
+  tokenizer::{Token, Tokenizer, Whitespace},
+};
 use tokio::sync::mpsc::UnboundedSender;
 use tui_textarea::{Input, Key};
 
-use super::{Frame, scroll_table::SelectionMode};
+use super::{Frame, plan_tree::PlanTree, scroll_table::SelectionMode};
 use crate::{
-  action::Action,
+  action::{Action, ExportFormat},
   app::AppState,
+  cli::Driver,
   components::{
     Component,
     scroll_table::{ScrollDirection, ScrollTable},
   },
   config::Config,
-  database::{Rows, header_to_vec, statement_type_string},
+  database::{
+    EditTarget, QueryResultsWithMetadata, RowStream, Rows, StreamState, TableDetails, TxPreview, Value, editable_table,
+    get_dialect, header_to_vec, is_unbounded_query, parse_plan, statement_type_string,
+  },
   focus::Focus,
   utils::get_export_dir,
 };
 
+// how close to the last fetched row the selection has to be before the
+// next page of a streamed result is fetched
+const FETCH_THRESHOLD: usize = 50;
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Default)]
 pub enum DataState<'a> {
@@ -29,6 +43,11 @@ pub enum DataState<'a> {
   NoResults,
   HasResults(Rows),
   Explain(Text<'a>),
+  /// An EXPLAIN whose plan could be read, shown as a tree.
+  Plan(PlanTree),
+  /// The statements that create an object, highlighted, and as they were
+  /// written for copying.
+  Ddl(Text<'a>, String),
   Error(eyre::Report),
   Cancelled,
   RowsAffected(u64),
@@ -43,8 +62,18 @@ pub struct ExplainOffsets {
 
 pub trait SettableDataTable<'a> {
   fn set_data_state(&mut self, data: Option<Result<Rows>>, statement_type: Option<Statement>);
+  fn set_script_results(&mut self, results: Vec<QueryResultsWithMetadata>);
+  fn set_tx_preview(&mut self, previews: Vec<TxPreview>);
   fn set_loading(&mut self);
   fn set_cancelled(&mut self);
+  fn set_ddl(&mut self, ddl: String, driver: Driver);
+  /// Sets the table that edits to the shown results are written to, as
+  /// asked for with `Action::LoadEditTable`, and starts editing the selected
+  /// cell.
+  fn set_edit_table(&mut self, schema: String, table: String, details: Result<TableDetails>, driver: Driver);
+  /// Whether a cell is being edited, in which case it takes typed characters
+  /// before the keybindings do.
+  fn is_editing(&self) -> bool;
 }
 
 pub trait DataComponent<'a>: Component + SettableDataTable<'a> {}
@@ -61,6 +90,19 @@ pub struct Data<'a> {
   explain_height: u16,
   explain_max_x_offset: u16,
   explain_max_y_offset: u16,
+  script_results: Vec<QueryResultsWithMetadata>,
+  script_index: usize,
+  // shown in the title in place of the statement number, one per result
+  script_labels: Vec<String>,
+  // the table the shown results were selected from, once it's been looked
+  // up for editing them
+  edit_target: Option<EditTarget>,
+  // edited values by row and column, with `None` for NULL
+  edits: BTreeMap<(usize, usize), Option<String>>,
+  // the row and column of the cell being edited, and what's been typed
+  editing: Option<(usize, usize, String)>,
+  // why the last edit couldn't be made, shown until the next key
+  edit_error: Option<String>,
 }
 
 impl Data<'_> {
@@ -75,11 +117,265 @@ impl Data<'_> {
       explain_height: 0,
       explain_max_x_offset: 0,
       explain_max_y_offset: 0,
+      script_results: vec![],
+      script_index: 0,
+      script_labels: vec![],
+      edit_target: None,
+      edits: BTreeMap::new(),
+      editing: None,
+      edit_error: None,
+    }
+  }
+
+  // results of a script are kept around so that the user can flip between
+  // them. errors can't be cloned, so they're re-created from their message.
+  // the rows of a result shown as a table are moved into `data_state`
+  // rather than copied, so that they're only held once, and moved back
+  // once another result is shown.
+  fn show_script_result(&mut self, index: usize) {
+    if index >= self.script_results.len() {
+      return;
+    }
+    self.return_shown_rows();
+    let result = &mut self.script_results[index];
+    let shown_as_table = !matches!(result.statement_type, Some(Statement::Explain { .. }));
+    let data = match &mut result.results {
+      Ok(rows) if shown_as_table && !rows.rows.is_empty() => Ok(Rows {
+        headers: rows.headers.clone(),
+        rows: std::mem::take(&mut rows.rows),
+        rows_affected: rows.rows_affected,
+      }),
+      Ok(rows) => Ok(rows.clone()),
+      Err(e) => Err(eyre::Report::msg(e.to_string())),
+    };
+    let statement_type = result.statement_type.clone();
+    self.script_index = index;
+    self.show_data(Some(data), statement_type);
+  }
+
+  fn return_shown_rows(&mut self) {
+    if let DataState::HasResults(shown) = &mut self.data_state
+      && let Some(QueryResultsWithMetadata { results: Ok(rows), .. }) = self.script_results.get_mut(self.script_index)
+      && rows.rows.is_empty()
+    {
+      rows.rows = std::mem::take(&mut shown.rows);
+    }
+  }
+
+  pub fn next_result(&mut self) {
+    if self.script_index + 1 < self.script_results.len() {
+      self.show_script_result(self.script_index + 1);
+    }
+  }
+
+  pub fn prev_result(&mut self) {
+    if self.script_index > 0 {
+      self.show_script_result(self.script_index - 1);
+    }
+  }
+
+  fn current_stream(&mut self) -> Option<&mut RowStream> {
+    self.script_results.get_mut(self.script_index).and_then(|result| result.stream.as_mut())
+  }
+
+  // asks for another page of rows once the selection gets close to the
+  // last row that has been fetched so far
+  fn fetch_rows_if_needed(&mut self) {
+    let DataState::HasResults(Rows { rows, .. }) = &self.data_state else {
+      return;
+    };
+    let (_, y) = self.scrollable.get_cell_offsets();
+    if y.saturating_add(FETCH_THRESHOLD) >= rows.len()
+      && let Some(stream) = self.current_stream()
+    {
+      stream.fetch_page();
+    }
+  }
+
+  fn poll_rows(&mut self) {
+    let Some(page) = self.current_stream().and_then(|stream| stream.try_next_page()) else {
+      return;
+    };
+    match page {
+      // the shown result's rows are in `data_state`
+      Ok(new_rows) => {
+        if let DataState::HasResults(rows) = &mut self.data_state {
+          rows.rows.extend(new_rows);
+        }
+        self.refresh_table();
+      },
+      Err(e) => {
+        log::error!("{e:?}");
+        self.script_results[self.script_index].results = Err(eyre::Report::msg(e.to_string()));
+        self.show_data(Some(Err(e)), None);
+      },
+    }
+  }
+
+  // rebuilds the table so that it shows the edits
+  fn refresh_table(&mut self) {
+    if let DataState::HasResults(rows) = &self.data_state {
+      let table = rows_table(rows, &self.edits, self.editing.as_ref());
+      self.scrollable.set_table(table, rows.headers.len(), rows.rows.len(), 36_u16);
+    }
+  }
+
+  // the schema, if it was named, and the table of the shown results, if
+  // their rows are rows of a single table
+  // only the results of a script with a single SELECT can be edited, since
+  // that's the statement that's run again to show the saved edits
+  fn edited_statement(&self) -> Option<&Statement> {
+    if self.script_results.len() != 1 || !self.script_labels.is_empty() {
+      return None;
+    }
+    self.script_results[0].statement_type.as_ref()
+  }
+
+  fn editable_table(&self) -> Option<(Option<String>, String)> {
+    editable_table(self.edited_statement()?)
+  }
+
+  fn edit_cell(&mut self) -> Result<()> {
+    let DataState::HasResults(Rows { headers, rows, .. }) = &self.data_state else {
+      return Ok(());
+    };
+    let Some((schema, table)) = self.editable_table() else {
+      self.edit_error = Some("Only the results of a single SELECT of columns from one table can be edited".to_owned());
+      return Ok(());
+    };
+    let Some(target) = &self.edit_target else {
+      self.command_tx.clone().unwrap().send(Action::LoadEditTable(schema, table))?;
+      return Ok(());
+    };
+    let (x, y) = self.scrollable.get_cell_offsets();
+    let (x, y) = (x as usize, y);
+    if !target.editable(x) {
+      self.edit_error = Some(format!("{} isn't a column of {}", headers[x].name, target.table));
+      return Ok(());
+    }
+    let text = match (self.edits.get(&(y, x)), &rows[y][x]) {
+      (Some(edit), _) => edit.clone().unwrap_or_default(),
+      (None, Value::Bytes(_)) => {
+        self.edit_error = Some("Binary values can't be edited".to_owned());
+        return Ok(());
+      },
+      (None, Value::Null) => String::new(),
+      (None, value) => value.to_string(),
+    };
+    self.editing = Some((y, x, text));
+    self.scrollable.transition_selection_mode(Some(SelectionMode::Cell));
+    self.refresh_table();
+    Ok(())
+  }
+
+  // keeps the edit, unless it puts back the value the cell had
+  fn stage_edit(&mut self, value: Option<String>) {
+    let (Some((y, x, _)), DataState::HasResults(Rows { rows, .. })) = (self.editing.take(), &self.data_state) else {
+      return;
+    };
+    let unchanged = match (&value, &rows[y][x]) {
+      (None, Value::Null) => true,
+      (Some(text), value) => !matches!(value, Value::Null) && *text == value.to_string(),
+      _ => false,
+    };
+    match unchanged {
+      true => self.edits.remove(&(y, x)),
+      false => self.edits.insert((y, x), value),
+    };
+    self.refresh_table();
+  }
+
+  fn handle_editing_input(&mut self, input: Input) {
+    let Some((_, _, text)) = self.editing.as_mut() else {
+      return;
+    };
+    match input {
+      Input { key: Key::Esc, .. } => {
+        self.editing = None;
+      },
+      Input { key: Key::Enter, .. } => {
+        let text = text.clone();
+        self.stage_edit(Some(text));
+        return;
+      },
+      Input { key: Key::Char('n'), ctrl: true, .. } => {
+        self.stage_edit(None);
+        return;
+      },
+      Input { key: Key::Backspace, .. } => {
+        text.pop();
+      },
+      Input { key: Key::Char(c), ctrl: false, alt: false, .. } => {
+        text.push(c);
+      },
+      _ => {},
+    }
+    self.refresh_table();
+  }
+
+  fn apply_edits(&mut self, app_state: &AppState) -> Result<()> {
+    let (DataState::HasResults(Rows { rows, .. }), Some(target)) = (&self.data_state, &self.edit_target) else {
+      return Ok(());
+    };
+    if self.edits.is_empty() {
+      return Ok(());
+    }
+    // the edits get a transaction of their own
+    if app_state.session().transaction.is_some() {
+      self.edit_error = Some("Commit or roll back the open transaction before applying edits".to_owned());
+      return Ok(());
+    }
+    let Some(statement) = self.edited_statement() else {
+      return Ok(());
+    };
+    match target.updates(rows, &self.edits) {
+      Ok(statements) => self.command_tx.clone().unwrap().send(Action::ApplyEdits(statements, statement.to_string()))?,
+      Err(e) => self.edit_error = Some(e.to_string()),
+    }
+    Ok(())
+  }
+
+  // the limit that `max_rows` put on the shown result, once it's been reached
+  fn reached_row_limit(&self, app_state: &AppState) -> Option<usize> {
+    let limit = app_state.session().row_limit?;
+    let result = self.script_results.get(self.script_index)?;
+    match (&self.data_state, &result.statement_type) {
+      (DataState::HasResults(rows), Some(statement)) if rows.rows.len() >= limit && is_unbounded_query(statement) => {
+        Some(limit)
+      },
+      _ => None,
+    }
+  }
+
+  fn row_count(&self, count: usize, row_limit: Option<usize>) -> String {
+    if let Some(limit) = row_limit {
+      return format!("limited to {limit} rows — press U to run unlimited");
+    }
+    let state = self.script_results.get(self.script_index).and_then(|result| result.stream.as_ref()).map(|s| s.state());
+    match state {
+      Some(StreamState::Idle) => format!("{count}+ rows"),
+      Some(StreamState::Fetching) => format!("{count}+ rows, fetching more..."),
+      Some(StreamState::Capped) => format!("{count} rows, capped"),
+      Some(StreamState::Expired) => format!("{count}+ rows, run again to fetch the rest"),
+      _ => format!("{count} rows"),
+    }
+  }
+
+  fn results_title(&self) -> String {
+    if let Some(label) = self.script_labels.get(self.script_index) {
+      return match self.script_results.len() {
+        n if n > 1 => format!(" 󰆼 results <alt+3> ({label}, {} of {})", self.script_index + 1, n),
+        _ => format!(" 󰆼 results <alt+3> ({label})"),
+      };
+    }
+    match self.script_results.len() {
+      n if n > 1 => format!(" 󰆼 results <alt+3> (result {} of {})", self.script_index + 1, n),
+      _ => " 󰆼 results <alt+3>".to_owned(),
     }
   }
 
   pub fn scroll(&mut self, direction: ScrollDirection) {
-    if let DataState::Explain(_) = self.data_state {
+    if let DataState::Explain(_) | DataState::Ddl(..) = self.data_state {
       if let Some(offsets) = self.explain_scroll.clone() {
         match direction {
           ScrollDirection::Up => {
@@ -100,13 +396,20 @@ impl Data<'_> {
           },
         };
       }
+    } else if let DataState::Plan(tree) = &mut self.data_state {
+      match direction {
+        ScrollDirection::Up => tree.up(),
+        ScrollDirection::Down => tree.down(),
+        ScrollDirection::Left => tree.scroll_left(),
+        ScrollDirection::Right => tree.scroll_right(),
+      }
     } else if let DataState::HasResults(_) = self.data_state {
       self.scrollable.scroll(direction);
     }
   }
 
   pub fn top(&mut self) {
-    if let DataState::Explain(_) = self.data_state {
+    if let DataState::Explain(_) | DataState::Ddl(..) = self.data_state {
       match self.explain_scroll {
         Some(ExplainOffsets { x_offset, .. }) => {
           self.explain_scroll = Some(ExplainOffsets { y_offset: 0, x_offset });
@@ -115,13 +418,15 @@ impl Data<'_> {
           self.explain_scroll = Some(ExplainOffsets { y_offset: 0, x_offset: 0 });
         },
       }
+    } else if let DataState::Plan(tree) = &mut self.data_state {
+      tree.top();
     } else if let DataState::HasResults(_) = self.data_state {
       self.scrollable.top_row();
     }
   }
 
   pub fn bottom(&mut self) {
-    if let DataState::Explain(_) = self.data_state {
+    if let DataState::Explain(_) | DataState::Ddl(..) = self.data_state {
       match self.explain_scroll {
         Some(ExplainOffsets { x_offset, .. }) => {
           self.explain_scroll = Some(ExplainOffsets { y_offset: self.explain_max_y_offset, x_offset });
@@ -130,13 +435,15 @@ impl Data<'_> {
           self.explain_scroll = Some(ExplainOffsets { y_offset: self.explain_max_y_offset, x_offset: 0 });
         },
       }
+    } else if let DataState::Plan(tree) = &mut self.data_state {
+      tree.bottom();
     } else if let DataState::HasResults(_) = self.data_state {
       self.scrollable.bottom_row();
     }
   }
 
   pub fn left(&mut self) {
-    if let DataState::Explain(_) = self.data_state {
+    if let DataState::Explain(_) | DataState::Ddl(..) = self.data_state {
       match self.explain_scroll {
         Some(ExplainOffsets { y_offset, .. }) => {
           self.explain_scroll = Some(ExplainOffsets { y_offset, x_offset: 0 });
@@ -145,13 +452,15 @@ impl Data<'_> {
           self.explain_scroll = Some(ExplainOffsets { y_offset: 0, x_offset: 0 });
         },
       }
+    } else if let DataState::Plan(tree) = &mut self.data_state {
+      tree.first_column();
     } else if let DataState::HasResults(_) = self.data_state {
       self.scrollable.first_column();
     }
   }
 
   pub fn right(&mut self) {
-    if let DataState::Explain(_) = self.data_state {
+    if let DataState::Explain(_) | DataState::Ddl(..) = self.data_state {
       match self.explain_scroll {
         Some(ExplainOffsets { y_offset, .. }) => {
           self.explain_scroll = Some(ExplainOffsets { y_offset, x_offset: self.explain_max_x_offset });
@@ -160,20 +469,24 @@ impl Data<'_> {
           self.explain_scroll = Some(ExplainOffsets { y_offset: 0, x_offset: self.explain_max_x_offset });
         },
       }
+    } else if let DataState::Plan(tree) = &mut self.data_state {
+      tree.last_column();
     } else if let DataState::HasResults(_) = self.data_state {
       self.scrollable.last_column();
     }
   }
-}
 
-impl<'a> SettableDataTable<'a> for Data<'a> {
-  fn set_data_state(&mut self, data: Option<Result<Rows>>, statement_type: Option<Statement>) {
+  fn show_data(&mut self, data: Option<Result<Rows>>, statement_type: Option<Statement>) {
     self.explain_width = 0;
     self.explain_height = 0;
     self.explain_max_x_offset = 0;
     self.explain_max_y_offset = 0;
     self.explain_scroll = None;
     self.scrollable = ScrollTable::default();
+    self.edit_target = None;
+    self.edits.clear();
+    self.editing = None;
+    self.edit_error = None;
     match data {
       Some(Ok(rows)) => {
         if rows.rows.is_empty() && rows.rows_affected.is_some_and(|n| n > 0) {
@@ -185,26 +498,19 @@ impl<'a> SettableDataTable<'a> for Data<'a> {
           self.data_state = DataState::StatementCompleted(statement_type.unwrap());
         } else if rows.rows.is_empty() {
           self.data_state = DataState::NoResults;
+        } else if matches!(statement_type, Some(Statement::Explain { .. }))
+          && let Some(plan) = parse_plan(&rows)
+        {
+          let raw = rows.rows.iter().map(|r| row_to_string(r)).collect::<Vec<_>>().join("\n");
+          self.data_state = DataState::Plan(PlanTree::new(plan, raw));
         } else if matches!(statement_type, Some(Statement::Explain { .. })) {
-          self.explain_width = rows.rows.iter().fold(0_u16, |acc, r| acc.max(r.join(" ").len() as u16));
+          self.explain_width = rows.rows.iter().fold(0_u16, |acc, r| acc.max(row_to_string(r).len() as u16));
           self.explain_height = rows.rows.len() as u16;
           self.explain_scroll = Some(ExplainOffsets { y_offset: 0, x_offset: 0 });
-          self.data_state = DataState::Explain(Text::from_iter(rows.rows.iter().map(|r| r.join(" "))));
+          self.data_state = DataState::Explain(Text::from_iter(rows.rows.iter().map(|r| row_to_string(r))));
         } else {
-          let header_row = Row::new(
-            rows.headers.iter().map(|h| Cell::from(format!("{}\n{}", h.name, h.type_name))).collect::<Vec<Cell>>(),
-          )
-          .height(2)
-          .bottom_margin(1);
-          let value_rows = rows.rows.iter().map(|r| Row::new(r.clone()).bottom_margin(1));
-          let buf_table = Table::default()
-            .rows(value_rows)
-            .header(header_row)
-            .style(Style::default())
-            .column_spacing(1)
-            .row_highlight_style(Style::default().fg(Color::LightBlue).reversed().bold());
-          self.scrollable.set_table(buf_table, rows.headers.len(), rows.rows.len(), 36_u16);
           self.data_state = DataState::HasResults(rows);
+          self.refresh_table();
         }
       },
       Some(Err(e)) => {
@@ -215,14 +521,214 @@ impl<'a> SettableDataTable<'a> for Data<'a> {
       },
     }
   }
+}
+
+// edited cells show their new values in yellow, and the cell being edited
+// shows what's been typed so far
+fn rows_table<'a>(
+  rows: &Rows,
+  edits: &BTreeMap<(usize, usize), Option<String>>,
+  editing: Option<&(usize, usize, String)>,
+) -> Table<'a> {
+  let header_row =
+    Row::new(rows.headers.iter().map(|h| Cell::from(format!("{}\n{}", h.name, h.type_name))).collect::<Vec<Cell>>())
+      .height(2)
+      .bottom_margin(1);
+  let cell = |y: usize, x: usize, value: &Value| match (editing, edits.get(&(y, x))) {
+    (Some((row, column, text)), _) if (*row, *column) == (y, x) => {
+      Cell::from(format!("{text}▏")).style(Style::default().fg(Color::Yellow).underlined())
+    },
+    (_, Some(Some(text))) => Cell::from(text.clone()).style(Style::default().fg(Color::Yellow).bold()),
+    (_, Some(None)) => value_cell(&Value::Null).style(Style::default().fg(Color::Yellow).dim().italic()),
+    _ => value_cell(value),
+  };
+  let value_rows = rows
+    .rows
+    .iter()
+    .enumerate()
+    .map(|(y, r)| Row::new(r.iter().enumerate().map(|(x, value)| cell(y, x, value))).bottom_margin(1));
+  Table::default()
+    .rows(value_rows)
+    .header(header_row)
+    .style(Style::default())
+    .column_spacing(1)
+    .row_highlight_style(Style::default().fg(Color::LightBlue).reversed().bold())
+}
+
+// NULLs are dimmed so they can't be mistaken for the text 'NULL', and
+// numbers are right-aligned so their digits line up
+fn value_cell<'a>(value: &Value) -> Cell<'a> {
+  let text = Text::from(value.to_string());
+  match value {
+    Value::Null => Cell::from(text).style(Style::default().dim().italic()),
+    v if v.is_numeric() => Cell::from(text.alignment(Alignment::Right)),
+    _ => Cell::from(text),
+  }
+}
+
+// keywords are styled like they are in the editor. the text is sliced out of
+// the sql where each token starts, rather than written back out from the
+// tokens, so that it's shown exactly as it was written.
+fn highlight_sql(sql: &str, driver: Driver) -> Text<'static> {
+  let dialect = get_dialect(driver);
+  let Ok(tokens) = Tokenizer::new(&*dialect, sql).tokenize_with_location() else {
+    return Text::from(sql.to_owned());
+  };
+  let line_starts = std::iter::once(0).chain(sql.match_indices('\n').map(|(i, _)| i + 1)).collect::<Vec<_>>();
+  // locations count lines and characters from 1
+  let offset = |line: u64, column: u64| {
+    let start = line_starts.get(line.saturating_sub(1) as usize).copied().unwrap_or(sql.len());
+    sql[start..].char_indices().nth(column.saturating_sub(1) as usize).map_or(sql.len(), |(i, _)| start + i)
+  };
+  let starts =
+    tokens.iter().map(|t| offset(t.span.start.line, t.span.start.column)).chain([sql.len()]).collect::<Vec<_>>();
+
+  let mut lines = vec![Line::default()];
+  for (i, token) in tokens.iter().enumerate() {
+    let style = match &token.token {
+      Token::Word(word) if word.quote_style.is_none() && word.keyword != Keyword::NoKeyword => {
+        Style::default().fg(Color::Magenta).bold()
+      },
+      Token::SingleQuotedString(_)
+      | Token::EscapedStringLiteral(_)
+      | Token::NationalStringLiteral(_)
+      | Token::DollarQuotedString(_) => Style::default().fg(Color::Green),
+      Token::Whitespace(Whitespace::SingleLineComment { .. } | Whitespace::MultiLineComment(_)) => {
+        Style::default().dim()
+      },
+      _ => Style::default(),
+    };
+    for (n, part) in sql[starts[i]..starts[i + 1].max(starts[i])].split('\n').enumerate() {
+      if n > 0 {
+        lines.push(Line::default());
+      }
+      if !part.is_empty() {
+        lines.last_mut().unwrap().push_span(Span::styled(part.to_owned(), style));
+      }
+    }
+  }
+  Text::from(lines)
+}
+
+fn row_to_string(row: &[Value]) -> String {
+  row.iter().map(ToString::to_string).collect::<Vec<_>>().join(" ")
+}
+
+// an array with one object per row. written by hand instead of through a
+// map so the keys keep the order of the columns
+fn rows_to_json(rows: &Rows) -> Result<String> {
+  let mut json = String::from("[");
+  for (i, row) in rows.rows.iter().enumerate() {
+    json.push_str(if i == 0 { "\n  {" } else { ",\n  {" });
+    for (j, (header, value)) in rows.headers.iter().zip(row).enumerate() {
+      if j > 0 {
+        json.push_str(", ");
+      }
+      json.push_str(&serde_json::to_string(&header.name)?);
+      json.push_str(": ");
+      json.push_str(&serde_json::to_string(&value.to_json())?);
+    }
+    json.push('}');
+  }
+  json.push_str("\n]\n");
+  Ok(json)
+}
+
+impl<'a> SettableDataTable<'a> for Data<'a> {
+  fn set_data_state(&mut self, data: Option<Result<Rows>>, statement_type: Option<Statement>) {
+    self.script_results = vec![];
+    self.script_index = 0;
+    self.script_labels = vec![];
+    self.show_data(data, statement_type);
+  }
+
+  fn set_script_results(&mut self, results: Vec<QueryResultsWithMetadata>) {
+    // land on the statement that stopped the script, or the last one if none failed
+    let index = results.iter().position(|r| r.results.is_err()).unwrap_or(results.len().saturating_sub(1));
+    self.script_results = results;
+    self.script_index = 0;
+    self.script_labels = vec![];
+    // the shown rows belong to the previous results
+    self.data_state = DataState::Blank;
+    if self.script_results.is_empty() {
+      self.show_data(None, None);
+    } else {
+      self.show_script_result(index);
+    }
+  }
 
+  // the rows are shown as plain results, labelled so they aren't mistaken
+  // for what's in the database. the statements of a script are numbered.
+  fn set_tx_preview(&mut self, previews: Vec<TxPreview>) {
+    let numbered = previews.len() > 1;
+    let mut results = vec![];
+    for (i, preview) in previews.into_iter().enumerate() {
+      let mut kind = statement_type_string(Some(preview.statement)).to_uppercase();
+      if numbered {
+        kind = format!("{kind} {}", i + 1);
+      }
+      let truncated = preview.truncated_at.map_or(String::new(), |row_cap| format!(", first {row_cap} rows only"));
+      results.push((format!("before {kind}, uncommitted{truncated}"), preview.before));
+      if let Some(after) = preview.after {
+        results.push((format!("after {kind}, uncommitted{truncated}"), after));
+      }
+    }
+    let (labels, results): (Vec<_>, Vec<_>) = results
+      .into_iter()
+      .map(|(label, rows)| (label, QueryResultsWithMetadata { results: Ok(rows), statement_type: None, stream: None }))
+      .unzip();
+    self.script_results = results;
+    self.script_labels = labels;
+    self.script_index = 0;
+    self.data_state = DataState::Blank;
+    self.show_script_result(0);
+  }
+
+  // dropping the previous results also drops any row stream, which
+  // releases the connection it was holding on to
   fn set_loading(&mut self) {
+    self.script_results = vec![];
+    self.script_index = 0;
+    self.script_labels = vec![];
     self.data_state = DataState::Loading;
   }
 
   fn set_cancelled(&mut self) {
+    self.script_results = vec![];
+    self.script_index = 0;
+    self.script_labels = vec![];
     self.data_state = DataState::Cancelled;
   }
+
+  fn set_ddl(&mut self, ddl: String, driver: Driver) {
+    self.script_results = vec![];
+    self.script_index = 0;
+    self.script_labels = vec![];
+    self.show_data(None, None);
+    self.explain_width = ddl.lines().map(|line| line.chars().count()).max().unwrap_or(0) as u16;
+    self.explain_height = ddl.lines().count() as u16;
+    self.explain_scroll = Some(ExplainOffsets { y_offset: 0, x_offset: 0 });
+    self.data_state = DataState::Ddl(highlight_sql(&ddl, driver), ddl);
+  }
+
+  fn set_edit_table(&mut self, schema: String, table: String, details: Result<TableDetails>, driver: Driver) {
+    let DataState::HasResults(Rows { headers, .. }) = &self.data_state else {
+      return;
+    };
+    match details.and_then(|details| EditTarget::new(schema, table, details, headers, driver)) {
+      Ok(target) => {
+        self.edit_target = Some(target);
+        if let Err(e) = self.edit_cell() {
+          self.edit_error = Some(e.to_string());
+        }
+      },
+      Err(e) => self.edit_error = Some(e.to_string()),
+    }
+  }
+
+  fn is_editing(&self) -> bool {
+    self.editing.is_some()
+  }
 }
 
 impl Component for Data<'_> {
@@ -259,6 +765,7 @@ impl Component for Data<'_> {
       },
       _ => {},
     };
+    self.fetch_rows_if_needed();
     Ok(None)
   }
 
@@ -267,7 +774,15 @@ impl Component for Data<'_> {
       return Ok(None);
     }
     let input = Input::from(key);
+    self.edit_error = None;
+    if self.editing.is_some() {
+      self.handle_editing_input(input);
+      return Ok(None);
+    }
     match input {
+      Input { key: Key::Char('U'), .. } if self.reached_row_limit(app_state).is_some() => {
+        self.command_tx.clone().unwrap().send(Action::RerunUnlimited)?;
+      },
       Input { key: Key::Char('P'), .. } => {
         if let DataState::HasResults(rows) = &self.data_state {
           self.command_tx.clone().unwrap().send(Action::RequestExportData(rows.rows.len() as i64))?;
@@ -319,6 +834,11 @@ impl Component for Data<'_> {
       Input { key: Key::Char('V'), .. } => {
         self.scrollable.transition_selection_mode(Some(SelectionMode::Row));
       },
+      Input { key: Key::Enter, .. } | Input { key: Key::Char(' '), .. }
+        if let DataState::Plan(tree) = &mut self.data_state =>
+      {
+        tree.toggle();
+      },
       Input { key: Key::Enter, .. } => {
         match self.scrollable.get_selection_mode() {
           Some(SelectionMode::Row) => {
@@ -347,12 +867,13 @@ impl Component for Data<'_> {
           let row = &rows[y];
           match self.scrollable.get_selection_mode() {
             Some(SelectionMode::Row) => {
-              let row_string = row.join(", ");
+              let driver = app_state.session().driver.unwrap_or(Driver::Postgres);
+              let row_string = row.iter().map(|value| value.to_sql_literal(driver)).collect::<Vec<_>>().join(", ");
               self.command_tx.clone().unwrap().send(Action::CopyData(row_string))?;
               self.scrollable.transition_selection_mode(Some(SelectionMode::Copied));
             },
             Some(SelectionMode::Cell) => {
-              let cell = row[x as usize].clone();
+              let cell = row[x as usize].to_string();
               self.command_tx.clone().unwrap().send(Action::CopyData(cell))?;
               self.scrollable.transition_selection_mode(Some(SelectionMode::Copied));
             },
@@ -361,33 +882,78 @@ impl Component for Data<'_> {
         } else if let DataState::Explain(text) = &self.data_state {
           self.command_tx.clone().unwrap().send(Action::CopyData(text.to_string()))?;
           self.scrollable.transition_selection_mode(Some(SelectionMode::Copied));
+        } else if let DataState::Plan(tree) = &self.data_state {
+          self.command_tx.clone().unwrap().send(Action::CopyData(tree.raw().to_owned()))?;
+          self.scrollable.transition_selection_mode(Some(SelectionMode::Copied));
+        } else if let DataState::Ddl(_, ddl) = &self.data_state {
+          self.command_tx.clone().unwrap().send(Action::CopyData(ddl.clone()))?;
+          self.scrollable.transition_selection_mode(Some(SelectionMode::Copied));
         } else if let DataState::Error(err) = &self.data_state {
           self.command_tx.clone().unwrap().send(Action::CopyData(err.to_string()))?;
           self.scrollable.transition_selection_mode(Some(SelectionMode::Copied));
         }
       },
+      Input { key: Key::Char('I'), .. } => {
+        if let DataState::Ddl(_, ddl) = &self.data_state {
+          self.command_tx.clone().unwrap().send(Action::QueryToEditor(ddl.lines().map(String::from).collect()))?;
+          self.command_tx.clone().unwrap().send(Action::FocusEditor)?;
+        }
+      },
+      Input { key: Key::Char('i'), .. } => {
+        self.edit_cell()?;
+      },
+      Input { key: Key::Char('u'), .. } => {
+        let (x, y) = self.scrollable.get_cell_offsets();
+        if self.edits.remove(&(y, x as usize)).is_some() {
+          self.refresh_table();
+        }
+      },
+      Input { key: Key::Char('X'), .. } => {
+        self.edits.clear();
+        self.refresh_table();
+      },
+      Input { key: Key::Char('A'), .. } => {
+        self.apply_edits(app_state)?;
+      },
+      Input { key: Key::Char('['), .. } => {
+        self.prev_result();
+      },
+      Input { key: Key::Char(']'), .. } => {
+        self.next_result();
+      },
       Input { key: Key::Esc, .. } => {
         self.scrollable.transition_selection_mode(None);
       },
       _ => {},
     };
+    self.fetch_rows_if_needed();
     Ok(None)
   }
 
   fn update(&mut self, action: Action, app_state: &AppState) -> Result<Option<Action>> {
     if let Action::Query(query, confirmed, bypass) = action {
       self.scrollable.reset_scroll();
+    } else if let Action::Tick = action {
+      self.poll_rows();
+      self.fetch_rows_if_needed();
     } else if let Action::ExportData(format) = action {
       let DataState::HasResults(rows) = &self.data_state else {
         return Ok(None);
       };
-      let name = format!("rainfrog_export_{}_rows_{}.csv", rows.rows.len(), chrono::Utc::now().timestamp());
-      let mut writer = Writer::from_path(get_export_dir().join(name))?;
-      writer.write_record(header_to_vec(&rows.headers))?;
-      for row in &rows.rows {
-        writer.write_record(row)?;
+      let name = format!("rainfrog_export_{}_rows_{}", rows.rows.len(), chrono::Utc::now().timestamp());
+      match format {
+        ExportFormat::CSV => {
+          let mut writer = Writer::from_path(get_export_dir().join(name).with_extension("csv"))?;
+          writer.write_record(header_to_vec(&rows.headers))?;
+          for row in &rows.rows {
+            writer.write_record(row.iter().map(Value::to_csv_field))?;
+          }
+          writer.flush()?;
+        },
+        ExportFormat::JSON => {
+          std::fs::write(get_export_dir().join(name).with_extension("json"), rows_to_json(rows)?)?;
+        },
       }
-      writer.flush()?;
       self.command_tx.clone().unwrap().send(Action::ExportDataFinished)?;
     }
     Ok(None)
@@ -413,31 +979,58 @@ impl Component for Data<'_> {
       });
     }
 
-    if let DataState::HasResults(Rows { rows, .. }) = &self.data_state {
+    let results_title = self.results_title();
+    let row_limit = self.reached_row_limit(app_state);
+    if let DataState::HasResults(Rows { headers, rows, .. }) = &self.data_state {
       let (x, y) = self.scrollable.get_cell_offsets();
       let row = &rows[y];
       let title_string = match self.scrollable.get_selection_mode() {
+        _ if let Some((y, x, _)) = self.editing => {
+          format!("{} (row {} of {}) - editing {} ", results_title, y.saturating_add(1), rows.len(), headers[x].name)
+        },
         Some(SelectionMode::Row) => {
-          format!(" 󰆼 results <alt+3> (row {} of {})", y.saturating_add(1), rows.len())
+          format!("{} (row {} of {})", results_title, y.saturating_add(1), rows.len())
         },
         Some(SelectionMode::Cell) => {
-          format!(" 󰆼 results <alt+3> (row {} of {}) - {} ", y.saturating_add(1), rows.len(), row[x as usize].clone())
+          format!("{} (row {} of {}) - {} ", results_title, y.saturating_add(1), rows.len(), row[x as usize])
         },
         Some(SelectionMode::Copied) => {
-          format!(" 󰆼 results <alt+3> ({} rows) - copied! ", rows.len())
+          format!("{} ({}) - copied! ", results_title, self.row_count(rows.len(), row_limit))
         },
-        _ => format!(" 󰆼 results <alt+3> ({} rows)", rows.len()),
+        _ => format!("{} ({})", results_title, self.row_count(rows.len(), row_limit)),
       };
-      block = block.title(title_string);
+      let status = match (&self.edit_error, self.edits.len()) {
+        _ if self.editing.is_some() => Span::default(),
+        (Some(e), _) => Span::styled(format!(" - {e} "), Style::default().fg(Color::Red)),
+        (None, 0) => Span::default(),
+        (None, 1) => Span::styled(" - 1 cell edited ", Style::default().fg(Color::Yellow)),
+        (None, n) => Span::styled(format!(" - {n} cells edited "), Style::default().fg(Color::Yellow)),
+      };
+      let title_string = match status.content.is_empty() {
+        true => title_string,
+        false => title_string.trim_end().to_owned(),
+      };
+      block = block.title(Line::from(vec![Span::raw(title_string), status]));
     } else {
+      let results_title = match &self.data_state {
+        DataState::Plan(tree) => match tree.summary() {
+          Some(summary) => format!("{results_title} (plan, {summary})"),
+          None => format!("{results_title} (plan)"),
+        },
+        DataState::Ddl(..) => format!("{results_title} (ddl)"),
+        _ => results_title,
+      };
       let title_string = match self.scrollable.get_selection_mode() {
-        Some(SelectionMode::Copied) => " 󰆼 results <alt+3> - copied! ",
-        _ => " 󰆼 results <alt+3>",
+        Some(SelectionMode::Copied) => format!("{results_title} - copied! "),
+        _ => results_title,
       };
       block = block.title(title_string);
     }
 
-    match &self.data_state {
+    match &mut self.data_state {
+      DataState::Plan(tree) => {
+        tree.draw(f, area, block, focused);
+      },
       DataState::NoResults => {
         f.render_widget(Paragraph::new("no results").wrap(Wrap { trim: false }).block(block), area);
       },
@@ -460,7 +1053,7 @@ impl Component for Data<'_> {
       DataState::Blank => {
         f.render_widget(Paragraph::new("").wrap(Wrap { trim: false }).block(block), area);
       },
-      DataState::Explain(text) => {
+      DataState::Explain(text) | DataState::Ddl(text, _) => {
         let mut paragraph = Paragraph::new(text.clone()).block(block);
         if let Some(offsets) = self.explain_scroll.clone() {
           paragraph = paragraph.scroll((offsets.y_offset, offsets.x_offset));
@@ -1,6 +1,6 @@
 use color_eyre::eyre::Result;
 use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
-use ratatui::prelude::*;
+use ratatui::{prelude::*, widgets::*};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedSender;
 use tui_textarea::{Input, Key, TextArea};
@@ -9,8 +9,10 @@ use super::{Component, Frame};
 use crate::{
   action::Action,
   app::AppState,
+  cli::Driver,
+  completion::{Completer, Completion},
   config::Config,
-  database::get_keywords,
+  database::{ColumnInfo, TableInfo, get_keywords},
   focus::Focus,
   tui::Event,
   vim::{Mode, Transition, Vim},
@@ -32,6 +34,18 @@ fn keyword_regex() -> String {
   format!("(?i)(^|[^a-zA-Z0-9\'\"`._]+)({})($|[^a-zA-Z0-9\'\"`._]+)", get_keywords().join("|"))
 }
 
+// the most suggestions the completion popup shows at once
+const COMPLETION_HEIGHT: u16 = 8;
+
+pub trait SettableCompletions {
+  fn set_completion_tables(&mut self, driver: Driver, tables: Vec<TableInfo>);
+  fn set_completion_columns(&mut self, schema: String, table: String, columns: Vec<ColumnInfo>);
+}
+
+pub trait EditorComponent: Component + SettableCompletions {}
+
+impl<T> EditorComponent for T where T: Component + SettableCompletions {}
+
 #[derive(Default)]
 pub struct Editor<'a> {
   command_tx: Option<UnboundedSender<Action>>,
@@ -40,6 +54,12 @@ pub struct Editor<'a> {
   vim_state: Vim,
   cursor_style: Style,
   last_query_duration: Option<chrono::Duration>,
+  completer: Completer,
+  completion: Option<Completion>,
+  completion_state: ListState,
+  // the first visible line, tracked the same way the textarea scrolls so
+  // that the completion popup can be drawn under the cursor
+  scroll_top: u16,
 }
 
 impl Editor<'_> {
@@ -53,13 +73,20 @@ impl Editor<'_> {
       vim_state: Vim::new(Mode::Normal),
       cursor_style: Mode::Normal.cursor_style(),
       last_query_duration: None,
+      completer: Completer::default(),
+      completion: None,
+      completion_state: ListState::default(),
+      scroll_top: 0,
     }
   }
 
   pub fn transition_vim_state(&mut self, input: Input, app_state: &AppState) -> Result<()> {
+    if self.completion.is_some() && self.vim_state.mode == Mode::Insert && self.handle_completion_input(&input) {
+      return Ok(());
+    }
     match input {
       Input { key: Key::Enter, alt: true, .. } | Input { key: Key::Enter, ctrl: true, .. } => {
-        if !app_state.query_task_running
+        if !app_state.session().query_task_running
           && let Some(sender) = &self.command_tx
         {
           sender.send(Action::Query(self.textarea.lines().to_vec(), false, false))?;
@@ -95,7 +122,7 @@ impl Editor<'_> {
       },
       _ => {
         let new_vim_state = self.vim_state.clone();
-        self.vim_state = match new_vim_state.transition(input, &mut self.textarea) {
+        self.vim_state = match new_vim_state.transition(input.clone(), &mut self.textarea) {
           Transition::Mode(mode) if new_vim_state.mode != mode => {
             self.cursor_style = mode.cursor_style();
             Vim::new(mode)
@@ -104,10 +131,117 @@ impl Editor<'_> {
           Transition::Pending(input) => new_vim_state.with_pending(input),
         };
         self.vim_state.register_action_handler(self.command_tx.clone())?;
+        self.refresh_completion(&input)?;
       },
     };
     Ok(())
   }
+
+  // returns whether the completion popup used the input
+  fn handle_completion_input(&mut self, input: &Input) -> bool {
+    let len = self.completion.as_ref().map_or(0, |c| c.suggestions.len());
+    let selected = self.completion_state.selected().unwrap_or(0);
+    match input {
+      Input { key: Key::Tab, .. } | Input { key: Key::Enter, ctrl: false, alt: false, .. } => {
+        self.accept_completion();
+        true
+      },
+      Input { key: Key::Down, .. } | Input { key: Key::Char('n'), ctrl: true, .. } => {
+        self.completion_state.select(Some((selected + 1) % len));
+        true
+      },
+      Input { key: Key::Up, .. } | Input { key: Key::Char('p'), ctrl: true, .. } => {
+        self.completion_state.select(Some((selected + len - 1) % len));
+        true
+      },
+      _ => false,
+    }
+  }
+
+  fn accept_completion(&mut self) {
+    if let Some(completion) = self.completion.take()
+      && let Some(suggestion) = completion.suggestions.get(self.completion_state.selected().unwrap_or(0))
+    {
+      for _ in 0..completion.replace_len {
+        self.textarea.delete_char();
+      }
+      self.textarea.insert_str(&suggestion.text);
+    }
+  }
+
+  // suggestions follow typing in insert mode, and go away on anything else
+  fn refresh_completion(&mut self, input: &Input) -> Result<()> {
+    let typing = self.vim_state.mode == Mode::Insert
+      && matches!(input, Input { key: Key::Char(_), ctrl: false, alt: false, .. } | Input { key: Key::Backspace, .. });
+    if !typing {
+      self.completion = None;
+      return Ok(());
+    }
+    let cursor = self.textarea.cursor();
+    for (schema, table) in self.completer.missing_columns(self.textarea.lines(), cursor) {
+      if let Some(sender) = &self.command_tx {
+        sender.send(Action::LoadCompletionColumns(schema, table))?;
+      }
+    }
+    self.completion = self.completer.complete(self.textarea.lines(), cursor);
+    self.completion_state = ListState::default().with_selected(Some(0));
+    Ok(())
+  }
+
+  fn draw_completion(&mut self, f: &mut Frame<'_>, area: Rect) {
+    let Some(completion) = &self.completion else {
+      return;
+    };
+    let (row, col) = self.textarea.cursor();
+    let gutter = self.textarea.lines().len().to_string().len() + 2;
+    let label_width = completion.suggestions.iter().map(|s| s.label.chars().count()).max().unwrap_or(0);
+    let width = ((label_width + 11) as u16).max(24).min(area.width);
+    let height = (completion.suggestions.len() as u16).min(COMPLETION_HEIGHT) + 2;
+    let cursor_y = area.y + (row as u16).saturating_sub(self.scroll_top);
+    // below the cursor if it fits, otherwise above it
+    let y =
+      if cursor_y + 1 + height <= area.bottom() { cursor_y + 1 } else { cursor_y.saturating_sub(height).max(area.y) };
+    let x =
+      (area.x + (gutter + col.saturating_sub(completion.replace_len)) as u16).min(area.right().saturating_sub(width));
+    let popup = Rect { x, y, width, height: height.min(area.bottom().saturating_sub(y)) };
+
+    let items: Vec<ListItem> = completion
+      .suggestions
+      .iter()
+      .map(|s| {
+        ListItem::new(Line::from(vec![
+          Span::raw(format!("{:<label_width$} ", s.label)),
+          Span::raw(s.kind.label()).dim().italic(),
+        ]))
+      })
+      .collect();
+    let list = List::new(items)
+      .block(
+        Block::default()
+          .borders(Borders::ALL)
+          .border_style(Style::default().fg(Color::Yellow))
+          .title_bottom(Line::from(" <tab> accept ").right_aligned()),
+      )
+      .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::REVERSED));
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut self.completion_state);
+  }
+}
+
+impl SettableCompletions for Editor<'_> {
+  fn set_completion_tables(&mut self, driver: Driver, tables: Vec<TableInfo>) {
+    self.completer.set_tables(driver, tables);
+  }
+
+  fn set_completion_columns(&mut self, schema: String, table: String, columns: Vec<ColumnInfo>) {
+    self.completer.set_columns(schema, table, columns);
+    // columns usually arrive after the popup is already open
+    if self.completion.is_some() {
+      let selected = self.completion_state.selected();
+      self.completion = self.completer.complete(self.textarea.lines(), self.textarea.cursor());
+      self.completion_state = ListState::default().with_selected(selected);
+    }
+  }
 }
 
 impl Component for Editor<'_> {
@@ -177,6 +311,7 @@ impl Component for Editor<'_> {
         }
       },
       Action::QueryToEditor(lines) => {
+        self.completion = None;
         self.textarea = TextArea::from(lines.clone());
         self.textarea.set_search_pattern(keyword_regex()).unwrap();
       },
@@ -191,8 +326,8 @@ impl Component for Editor<'_> {
   fn draw(&mut self, f: &mut Frame<'_>, area: Rect, app_state: &AppState) -> Result<()> {
     let focused = app_state.focus == Focus::Editor;
 
-    if let Some(query_start) = app_state.last_query_start {
-      self.last_query_duration = match app_state.last_query_end {
+    if let Some(query_start) = app_state.session().last_query_start {
+      self.last_query_duration = match app_state.session().last_query_end {
         Some(end) => Some(end.signed_duration_since(query_start)),
         None => Some(chrono::Utc::now().signed_duration_since(query_start)),
       };
@@ -225,6 +360,19 @@ impl Component for Editor<'_> {
     self.textarea.set_tab_length(2);
     self.textarea.set_search_style(Style::default().fg(Color::Magenta).bold());
     f.render_widget(&self.textarea, area);
+
+    let inner = area.inner(Margin { vertical: 1, horizontal: 1 });
+    let row = self.textarea.cursor().0 as u16;
+    self.scroll_top = if row < self.scroll_top {
+      row
+    } else if self.scroll_top + inner.height <= row {
+      row + 1 - inner.height
+    } else {
+      self.scroll_top
+    };
+    if focused {
+      self.draw_completion(f, inner);
+    }
     Ok(())
   }
 }
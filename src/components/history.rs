@@ -110,8 +110,8 @@ impl Component for History {
 
   fn draw(&mut self, f: &mut Frame<'_>, area: Rect, app_state: &AppState) -> Result<()> {
     let focused = app_state.focus == Focus::History;
-    if let Some(query_start) = app_state.last_query_start {
-      self.last_query_duration = match app_state.last_query_end {
+    if let Some(query_start) = app_state.session().last_query_start {
+      self.last_query_duration = match app_state.session().last_query_end {
         Some(end) => Some(end.signed_duration_since(query_start)),
         None => Some(chrono::Utc::now().signed_duration_since(query_start)),
       };
@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, HashSet};
+
 use color_eyre::eyre::Result;
 use crossterm::event::{KeyCode, KeyEvent, MouseEventKind};
 use indexmap::IndexMap;
@@ -10,7 +12,7 @@ use crate::{
   action::{Action, MenuPreview},
   app::AppState,
   config::Config,
-  database::Rows,
+  database::{ObjectKind, Rows},
   focus::Focus,
 };
 
@@ -29,11 +31,42 @@ pub trait MenuComponent<'a>: Component + SettableTableList<'a> {}
 
 impl<'a, T> MenuComponent<'a> for T where T: Component + SettableTableList<'a> {}
 
+#[derive(Debug, Clone, PartialEq)]
+enum MenuItem {
+  Kind(ObjectKind, usize, bool), // (kind, number of objects, expanded)
+  Object(ObjectKind, String),
+}
+
+// the previews of each kind of object. the first is shown with <enter>,
+// the rest with the number keys
+fn previews(kind: ObjectKind) -> Vec<(MenuPreview, &'static str)> {
+  let previews: &[(MenuPreview, &str)] = match kind {
+    ObjectKind::Table => &[
+      (MenuPreview::Rows, "rows"),
+      (MenuPreview::Columns, "columns"),
+      (MenuPreview::Constraints, "constraints"),
+      (MenuPreview::Indexes, "indexes"),
+      (MenuPreview::Policies, "rls policies"),
+    ],
+    ObjectKind::View | ObjectKind::MaterializedView => {
+      &[(MenuPreview::Rows, "rows"), (MenuPreview::Columns, "columns"), (MenuPreview::Definition, "definition")]
+    },
+    ObjectKind::Function | ObjectKind::Procedure => &[(MenuPreview::Source, "source")],
+    ObjectKind::Sequence => &[(MenuPreview::Sequence, "current value")],
+    ObjectKind::Trigger => &[(MenuPreview::Trigger, "body")],
+    ObjectKind::Enum => &[(MenuPreview::Enum, "values")],
+  };
+  [previews, &[(MenuPreview::Ddl(kind), "ddl")]].concat()
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Menu {
   command_tx: Option<UnboundedSender<Action>>,
   config: Config,
-  table_map: IndexMap<String, Vec<String>>,
+  object_map: IndexMap<String, BTreeMap<ObjectKind, Vec<String>>>,
+  // kinds whose expansion was toggled; tables start out expanded and the
+  // rest collapsed. kept across reloads.
+  toggled_kinds: HashSet<(String, ObjectKind)>,
   schema_index: usize,
   list_state: ListState,
   menu_focus: MenuFocus,
@@ -46,7 +79,8 @@ impl Menu {
     Menu {
       command_tx: None,
       config: Config::default(),
-      table_map: IndexMap::new(),
+      object_map: IndexMap::new(),
+      toggled_kinds: HashSet::new(),
       schema_index: 0,
       list_state: ListState::default(),
       menu_focus: MenuFocus::default(),
@@ -55,15 +89,70 @@ impl Menu {
     }
   }
 
+  // the rows of the tree under the selected schema
+  fn items(&self) -> Vec<MenuItem> {
+    let Some((schema, kinds)) = self.object_map.get_index(self.schema_index) else {
+      return vec![];
+    };
+    let search = self.search.as_ref().map(|s| s.to_lowercase().trim().to_owned());
+    kinds
+      .iter()
+      .flat_map(|(kind, names)| {
+        let names: Vec<&String> =
+          names.iter().filter(|n| search.as_ref().is_none_or(|s| n.to_lowercase().contains(s))).collect();
+        if names.is_empty() {
+          return vec![];
+        }
+        // every match is shown while searching, even in collapsed kinds
+        let expanded = search.is_some() || self.is_expanded(schema, *kind);
+        let mut items = vec![MenuItem::Kind(*kind, names.len(), expanded)];
+        if expanded {
+          items.extend(names.into_iter().map(|n| MenuItem::Object(*kind, n.clone())));
+        }
+        items
+      })
+      .collect()
+  }
+
+  fn is_expanded(&self, schema: &str, kind: ObjectKind) -> bool {
+    (kind == ObjectKind::Table) != self.toggled_kinds.contains(&(schema.to_owned(), kind))
+  }
+
+  fn toggle_kind(&mut self, kind: ObjectKind) {
+    if let Some(schema) = self.object_map.get_index(self.schema_index).map(|(s, _)| s.clone()) {
+      let key = (schema, kind);
+      if !self.toggled_kinds.remove(&key) {
+        self.toggled_kinds.insert(key);
+      }
+    }
+  }
+
+  fn selected_item(&self) -> Option<MenuItem> {
+    self.list_state.selected().and_then(|i| self.items().get(i).cloned())
+  }
+
+  fn send_preview(&self, kind: ObjectKind, name: String, index: usize) -> Result<()> {
+    if let Some((preview, _)) = previews(kind).get(index)
+      && let Some((schema, _)) = self.object_map.get_index(self.schema_index)
+    {
+      self.command_tx.as_ref().unwrap().send(Action::MenuPreview(preview.clone(), schema.clone(), name))?;
+    }
+    Ok(())
+  }
+
+  // selects the first object rather than the header above it
+  fn select_first(&mut self) {
+    let first = self.items().iter().position(|i| matches!(i, MenuItem::Object(..))).unwrap_or(0);
+    self.list_state = ListState::default().with_selected(Some(first));
+  }
+
   pub fn change_focus(&mut self, new_focus: MenuFocus) {
-    if self.menu_focus != new_focus && self.table_map.keys().len() > 1 {
+    if self.menu_focus != new_focus && self.object_map.keys().len() > 1 {
       match new_focus {
         MenuFocus::Schema => {
           self.list_state = ListState::default();
         },
-        MenuFocus::Tables => {
-          self.list_state = ListState::default().with_selected(Some(0));
-        },
+        MenuFocus::Tables => self.select_first(),
       }
       self.menu_focus = new_focus;
     }
@@ -73,23 +162,13 @@ impl Menu {
     match self.menu_focus {
       MenuFocus::Tables => {
         if let Some(i) = self.list_state.selected() {
-          let tables = self.table_map.get_index(self.schema_index).unwrap().1.to_owned();
-          let filtered_tables: Vec<String> = tables
-            .into_iter()
-            .filter(|t| {
-              if let Some(search) = self.search.as_ref() {
-                t.to_lowercase().contains(search.to_lowercase().trim())
-              } else {
-                true
-              }
-            })
-            .collect();
-          self.list_state = ListState::default()
-            .with_selected(Some(i.saturating_add(1).clamp(0, filtered_tables.len().saturating_sub(1))));
+          let len = self.items().len();
+          self.list_state =
+            ListState::default().with_selected(Some(i.saturating_add(1).clamp(0, len.saturating_sub(1))));
         }
       },
       MenuFocus::Schema => {
-        self.schema_index = self.schema_index.saturating_add(1).clamp(0, self.table_map.keys().len().saturating_sub(1))
+        self.schema_index = self.schema_index.saturating_add(1).clamp(0, self.object_map.keys().len().saturating_sub(1))
       },
     }
   }
@@ -108,23 +187,12 @@ impl Menu {
   pub fn scroll_bottom(&mut self) {
     match self.menu_focus {
       MenuFocus::Tables => {
-        if let Some(i) = self.list_state.selected() {
-          let tables = self.table_map.get_index(self.schema_index).unwrap().1.to_owned();
-          let filtered_tables: Vec<String> = tables
-            .into_iter()
-            .filter(|t| {
-              if let Some(search) = self.search.as_ref() {
-                t.to_lowercase().contains(search.to_lowercase().trim())
-              } else {
-                true
-              }
-            })
-            .collect();
-          self.list_state = ListState::default().with_selected(Some(filtered_tables.len().saturating_sub(1)));
+        if self.list_state.selected().is_some() {
+          self.list_state = ListState::default().with_selected(Some(self.items().len().saturating_sub(1)));
         }
       },
       MenuFocus::Schema => {
-        self.schema_index = self.table_map.keys().len().saturating_sub(1);
+        self.schema_index = self.object_map.keys().len().saturating_sub(1);
       },
     }
   }
@@ -132,7 +200,7 @@ impl Menu {
   pub fn scroll_top(&mut self) {
     match self.menu_focus {
       MenuFocus::Tables => {
-        if let Some(i) = self.list_state.selected() {
+        if self.list_state.selected().is_some() {
           self.list_state = ListState::default().with_selected(Some(0));
         }
       },
@@ -143,27 +211,25 @@ impl Menu {
   pub fn reset_search(&mut self) {
     self.search = None;
     self.search_focused = false;
-    self.list_state = ListState::default().with_selected(Some(0));
+    self.select_first();
   }
 }
 
 impl SettableTableList<'_> for Menu {
   fn set_table_list(&mut self, data: Option<Result<Rows>>) {
     log::info!("setting menu table list");
-    self.table_map = IndexMap::new();
+    self.object_map = IndexMap::new();
     match data {
       Some(Ok(rows)) => {
         rows.rows.iter().for_each(|row| {
-          let schema = row[0].clone();
-          let table = row[1].clone();
-          if !self.table_map.contains_key(&schema) {
-            self.table_map.insert(schema.clone(), vec![]);
-          }
-          self.table_map.get_mut(&schema).unwrap().push(table.clone());
+          let schema = row[0].to_string();
+          let name = row[1].to_string();
+          let kind = row.get(2).and_then(|k| k.to_string().parse().ok()).unwrap_or(ObjectKind::Table);
+          self.object_map.entry(schema).or_default().entry(kind).or_default().push(name);
         });
-        if self.table_map.keys().len() == 1 {
+        if self.object_map.keys().len() == 1 {
           self.menu_focus = MenuFocus::Tables;
-          self.list_state = ListState::default().with_selected(Some(0));
+          self.select_first();
         } else {
           self.menu_focus = MenuFocus::Schema;
           self.list_state = ListState::default();
@@ -217,7 +283,7 @@ impl Component for Menu {
         if self.search.is_some() && self.search_focused {
           if let Some(search) = self.search.as_mut() {
             search.push(c);
-            self.list_state = ListState::default().with_selected(Some(0));
+            self.select_first();
           }
         } else {
           match key.code {
@@ -234,32 +300,9 @@ impl Component for Menu {
             KeyCode::Char('g') => self.scroll_top(),
             KeyCode::Char('G') => self.scroll_bottom(),
             KeyCode::Char('R') => self.command_tx.as_ref().unwrap().send(Action::LoadMenu)?,
-            KeyCode::Char('1') | KeyCode::Char('2') | KeyCode::Char('3') | KeyCode::Char('4') => {
-              if let Some(selected) = self.list_state.selected()
-                && let Some((schema, tables)) = self.table_map.get_index(self.schema_index)
-              {
-                let filtered_tables: Vec<String> = tables
-                  .iter()
-                  .filter(|t| {
-                    if let Some(search) = self.search.as_ref() {
-                      t.to_lowercase().contains(search.to_lowercase().trim())
-                    } else {
-                      true
-                    }
-                  })
-                  .cloned()
-                  .collect();
-                self.command_tx.as_ref().unwrap().send(Action::MenuPreview(
-                  match key.code {
-                    KeyCode::Char('1') => MenuPreview::Columns,
-                    KeyCode::Char('2') => MenuPreview::Constraints,
-                    KeyCode::Char('3') => MenuPreview::Indexes,
-                    KeyCode::Char('4') => MenuPreview::Policies,
-                    _ => MenuPreview::Rows,
-                  },
-                  schema.clone(),
-                  filtered_tables[selected].clone(),
-                ))?;
+            KeyCode::Char(c @ '1'..='9') => {
+              if let Some(MenuItem::Object(kind, name)) = self.selected_item() {
+                self.send_preview(kind, name, c as usize - '0' as usize)?;
               }
             },
             _ => {},
@@ -271,24 +314,12 @@ impl Component for Menu {
           self.search_focused = false;
         } else if self.menu_focus == MenuFocus::Schema {
           self.change_focus(MenuFocus::Tables);
-        } else if let Some(selected) = self.list_state.selected() {
-          let (schema, tables) = self.table_map.get_index(self.schema_index).unwrap();
-          let filtered_tables: Vec<String> = tables
-            .iter()
-            .filter(|t| {
-              if let Some(search) = self.search.as_ref() {
-                t.to_lowercase().contains(search.to_lowercase().trim())
-              } else {
-                true
-              }
-            })
-            .cloned()
-            .collect();
-          self.command_tx.as_ref().unwrap().send(Action::MenuPreview(
-            MenuPreview::Rows,
-            schema.clone(),
-            filtered_tables[selected].clone(),
-          ))?;
+        } else {
+          match self.selected_item() {
+            Some(MenuItem::Kind(kind, ..)) => self.toggle_kind(kind),
+            Some(MenuItem::Object(kind, name)) => self.send_preview(kind, name, 0)?,
+            None => {},
+          }
         }
       },
       KeyCode::Esc => self.reset_search(),
@@ -297,7 +328,7 @@ impl Component for Menu {
           if let Some(search) = self.search.as_mut() {
             if !search.is_empty() {
               search.pop();
-              self.list_state = ListState::default().with_selected(Some(0));
+              self.select_first();
             } else {
               self.reset_search();
             }
@@ -314,7 +345,8 @@ impl Component for Menu {
   fn draw(&mut self, f: &mut Frame<'_>, area: Rect, app_state: &AppState) -> Result<()> {
     let focused = app_state.focus == Focus::Menu;
     let parent_block = Block::default();
-    let stable_keys = self.table_map.keys().enumerate();
+    let items = self.items();
+    let stable_keys = self.object_map.keys().enumerate();
     let mut constraints: Vec<Constraint> = stable_keys
       .clone()
       .map(|(i, k)| match i {
@@ -358,44 +390,39 @@ impl Component for Menu {
             })
             .padding(Padding { left: 0, right: 1, top: 0, bottom: 0 });
           let block_margin = layout[layout_index].inner(Margin { vertical: 1, horizontal: 0 });
-          let tables = self.table_map.get_key_value(k).unwrap().1.clone();
-          let filtered_tables: Vec<String> = tables
-            .into_iter()
-            .filter(|t| {
-              if let Some(search) = self.search.as_ref() {
-                t.to_lowercase().contains(search.to_lowercase().trim())
-              } else {
-                true
-              }
-            })
-            .collect();
-          let table_length = filtered_tables.len();
+          let items = items.clone();
+          let item_count = items.len();
           let available_height = block.inner(parent_block.inner(area)).height as usize;
-          let selected_table_index = self.list_state.selected();
-          let filtered_tables_items: Vec<ListItem> = filtered_tables
+          let selected_index = self.list_state.selected();
+          let list_items: Vec<ListItem> = items
             .into_iter()
             .enumerate()
-            .map(|(i, t)| {
-              let is_selected = selected_table_index == Some(i);
-              if is_selected && focused && !self.search_focused {
-                ListItem::new(Text::from(vec![
-                  Line::from(t),
-                  Line::from(if app_state.query_task_running { "├[...] rows" } else { "├[<enter>] rows" }),
-                  Line::from(if app_state.query_task_running { "├[...] columns" } else { "├[1] columns" }),
-                  Line::from(if app_state.query_task_running { "├[...] constraints" } else { "├[2] constraints" }),
-                  Line::from(if app_state.query_task_running { "├[...] indexes" } else { "├[3] indexes" }),
-                  Line::from(if app_state.query_task_running {
-                    "└[...] rls policies"
-                  } else {
-                    "└[4] rls policies"
-                  }),
-                ]))
-              } else {
-                ListItem::new(t)
-              }
+            .map(|(i, item)| match item {
+              MenuItem::Kind(kind, count, expanded) => {
+                ListItem::new(Line::from(format!("{} {} ({count})", if expanded { "▾" } else { "▸" }, kind.plural())))
+              },
+              MenuItem::Object(kind, name) => {
+                let is_selected = selected_index == Some(i);
+                if is_selected && focused && !self.search_focused {
+                  let previews = previews(kind);
+                  let mut lines = vec![Line::from(format!("  {name}"))];
+                  lines.extend(previews.iter().enumerate().map(|(n, (_, label))| {
+                    let branch = if n == previews.len() - 1 { "└" } else { "├" };
+                    let key = match n {
+                      _ if app_state.session().query_task_running => "...".to_owned(),
+                      0 => "<enter>".to_owned(),
+                      n => n.to_string(),
+                    };
+                    Line::from(format!("  {branch}[{key}] {label}"))
+                  }));
+                  ListItem::new(Text::from(lines))
+                } else {
+                  ListItem::new(format!("  {name}"))
+                }
+              },
             })
             .collect();
-          let list = List::default().items(filtered_tables_items).block(block).highlight_style(
+          let list = List::default().items(list_items).block(block).highlight_style(
             Style::default()
               .fg(if focused && !self.search_focused && self.menu_focus == MenuFocus::Tables {
                 Color::Green
@@ -413,10 +440,10 @@ impl Component for Menu {
               Style::default()
             });
           let mut vertical_scrollbar_state =
-            ScrollbarState::new(table_length.saturating_sub(available_height)).position(self.list_state.offset());
+            ScrollbarState::new(item_count.saturating_sub(available_height)).position(self.list_state.offset());
           f.render_stateful_widget(vertical_scrollbar, block_margin, &mut vertical_scrollbar_state);
         },
-        x if x == self.table_map.keys().len().saturating_sub(1) => {
+        x if x == self.object_map.keys().len().saturating_sub(1) => {
           f.render_widget(
             Text::styled(
               "└ ".to_owned() + k.to_owned().as_str(),
@@ -18,13 +18,18 @@ pub enum ComponentImpls {
   History,
   Data,
   Favorites,
+  Notifications,
+  Activity,
 }
 
+pub mod activity;
 pub mod data;
 pub mod editor;
 pub mod favorites;
 pub mod history;
 pub mod menu;
+pub mod notifications;
+pub mod plan_tree;
 pub mod scroll_table;
 pub trait Component {
   /// Register an action handler that can send actions for processing if necessary.
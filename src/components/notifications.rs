@@ -0,0 +1,202 @@
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, symbols::scrollbar, widgets::*};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{Component, Frame};
+use crate::{action::Action, app::AppState, config::Config, focus::Focus};
+
+#[derive(Default)]
+pub struct Notifications {
+  command_tx: Option<UnboundedSender<Action>>,
+  config: Config,
+  list_state: ListState,
+  copied: bool,
+  pretty: bool,
+}
+
+impl Notifications {
+  pub fn new() -> Self {
+    Notifications {
+      command_tx: None,
+      config: Config::default(),
+      list_state: ListState::default(),
+      copied: false,
+      pretty: false,
+    }
+  }
+
+  pub fn scroll_up(&mut self) {
+    let current_selected = self.list_state.selected();
+    if let Some(i) = current_selected {
+      self.list_state.select(Some(i.saturating_sub(1)));
+    }
+  }
+
+  pub fn scroll_down(&mut self, item_count: usize) {
+    let current_selected = self.list_state.selected();
+    if let Some(i) = current_selected {
+      self.list_state.select(Some(std::cmp::min(i.saturating_add(1), item_count.saturating_sub(1))));
+    }
+  }
+
+  // payloads that aren't JSON are shown as they are
+  fn format_payload(&self, payload: &str) -> String {
+    if !self.pretty {
+      return payload.to_string();
+    }
+    serde_json::from_str::<serde_json::Value>(payload)
+      .ok()
+      .and_then(|json| serde_json::to_string_pretty(&json).ok())
+      .unwrap_or_else(|| payload.to_string())
+  }
+}
+
+impl Component for Notifications {
+  fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+    self.command_tx = Some(tx);
+    Ok(())
+  }
+
+  fn register_config_handler(&mut self, config: Config) -> Result<()> {
+    self.config = config;
+    Ok(())
+  }
+
+  fn handle_mouse_events(&mut self, mouse: MouseEvent, app_state: &AppState) -> Result<Option<Action>> {
+    if app_state.focus != Focus::Notifications {
+      return Ok(None);
+    }
+    self.copied = false;
+    match mouse.kind {
+      MouseEventKind::ScrollDown => {
+        self.scroll_down(app_state.session().notifications.len());
+      },
+      MouseEventKind::ScrollUp => {
+        self.scroll_up();
+      },
+      _ => {},
+    };
+    Ok(None)
+  }
+
+  fn handle_key_events(&mut self, key: KeyEvent, app_state: &AppState) -> Result<Option<Action>> {
+    if app_state.focus != Focus::Notifications {
+      return Ok(None);
+    }
+    self.copied = false;
+    let notifications = &app_state.session().notifications;
+    match key.code {
+      KeyCode::Down | KeyCode::Char('j') => {
+        self.scroll_down(notifications.len());
+      },
+      KeyCode::Up | KeyCode::Char('k') => {
+        self.scroll_up();
+      },
+      KeyCode::Char('g') => {
+        self.list_state.select(Some(0));
+      },
+      KeyCode::Char('G') => self.list_state.select(Some(notifications.len().saturating_sub(1))),
+      KeyCode::Char('y') => {
+        if let Some(notification) = self.list_state.selected().and_then(|i| notifications.get(i)) {
+          let payload = self.format_payload(&notification.payload);
+          self.command_tx.as_ref().unwrap().send(Action::CopyData(payload))?;
+          self.copied = true;
+        }
+      },
+      KeyCode::Char('p') => {
+        self.pretty = !self.pretty;
+      },
+      KeyCode::Char('L') => {
+        self.command_tx.as_ref().unwrap().send(Action::RequestListen)?;
+      },
+      KeyCode::Char('U') => {
+        self.command_tx.as_ref().unwrap().send(Action::UnlistenAll)?;
+      },
+      KeyCode::Char('D') => {
+        self.command_tx.as_ref().unwrap().send(Action::ClearNotifications)?;
+      },
+      _ => {},
+    };
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect, app_state: &AppState) -> Result<()> {
+    let focused = app_state.focus == Focus::Notifications;
+    let session = app_state.session();
+    let title = match session.listening.is_empty() {
+      true => " not listening on any channels ".to_string(),
+      false => format!(" listening on {} ", session.listening.join(", ")),
+    };
+    let block = Block::default()
+      .borders(Borders::ALL)
+      .border_style(if focused { Style::new().green() } else { Style::new().dim() })
+      .title(Line::from(title).right_aligned());
+    let scrollbar_margin = area.inner(Margin { vertical: 1, horizontal: 0 });
+
+    let items = session
+      .notifications
+      .iter()
+      .enumerate()
+      .map(|(i, n)| {
+        let selected = self.list_state.selected() == Some(i);
+        let color = if selected && focused { Color::Blue } else { Color::default() };
+        let max_lines = 1_usize.max(area.height.saturating_sub(6) as usize);
+        let payload = self.format_payload(&n.payload);
+        let payload_lines = payload.lines().collect::<Vec<&str>>();
+        let mut lines = payload_lines[0..max_lines.min(payload_lines.len())]
+          .iter()
+          .map(|s| Line::from(s.to_string()).style(Style::default().fg(color)))
+          .collect::<Vec<Line>>();
+        if payload_lines.len() > max_lines {
+          lines.push(
+            Line::from(format!("... and {} more lines", payload_lines.len().saturating_sub(max_lines)))
+              .style(Style::default().fg(color)),
+          );
+        }
+        lines.insert(
+          0,
+          Line::from(format!(
+            "{}{} {} (pid {})",
+            if self.copied && selected { " copied! - " } else { "" },
+            n.received_at.format("%H:%M:%S%.3f"),
+            n.channel,
+            n.process_id
+          ))
+          .style(if focused { Color::Yellow } else { Color::default() }),
+        );
+        lines.push(
+          Line::from("----------------------------------------------------------------------------------------------------------------------------------------------------------------")
+            .style(Style::default().fg(color)),
+        );
+        ListItem::new(Text::from_iter(lines))
+      })
+      .collect::<Vec<ListItem>>();
+
+    match self.list_state.selected() {
+      Some(x) if x > items.len().saturating_sub(1) => {
+        self.list_state.select(Some(0));
+      },
+      None => {
+        self.list_state.select(Some(0));
+      },
+      _ => {},
+    };
+
+    let list = List::default()
+      .items(items)
+      .block(block)
+      .highlight_style(Style::default().bold())
+      .highlight_symbol(if self.copied { "  " } else { " > " })
+      .highlight_spacing(HighlightSpacing::Always);
+
+    f.render_stateful_widget(list, area, &mut self.list_state);
+    let vertical_scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+      .symbols(scrollbar::VERTICAL)
+      .style(if focused { Style::default().fg(Color::Green) } else { Style::default() });
+    let mut vertical_scrollbar_state = ScrollbarState::new(session.notifications.len().saturating_sub(1))
+      .position(self.list_state.selected().map_or(0, |x| x));
+    f.render_stateful_widget(vertical_scrollbar, scrollbar_margin, &mut vertical_scrollbar_state);
+    Ok(())
+  }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashSet;
+
+use ratatui::{
+  prelude::*,
+  symbols::scrollbar,
+  widgets::{Block, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
+};
+
+use crate::database::{Plan, PlanNode};
+
+// nodes taking at least these shares of the plan are highlighted
+const EXPENSIVE_SHARE: f64 = 0.5;
+const NOTABLE_SHARE: f64 = 0.2;
+
+/// An EXPLAIN plan shown as a tree whose nodes can be collapsed.
+#[derive(Debug, Clone, Default)]
+pub struct PlanTree {
+  plan: Plan,
+  /// What EXPLAIN returned, for copying.
+  raw: String,
+  // nodes are identified by the index of each child on the way down to them
+  collapsed: HashSet<Vec<usize>>,
+  selected: usize,
+  y_offset: usize,
+  x_offset: u16,
+  max_x_offset: u16,
+}
+
+impl PlanTree {
+  pub fn new(plan: Plan, raw: String) -> Self {
+    Self { plan, raw, ..Default::default() }
+  }
+
+  pub fn raw(&self) -> &str {
+    &self.raw
+  }
+
+  /// Planning and execution times, when the database reported them.
+  pub fn summary(&self) -> Option<String> {
+    let times = [("planning", self.plan.planning_time), ("execution", self.plan.execution_time)]
+      .into_iter()
+      .filter_map(|(name, time)| time.map(|time| format!("{name} {}ms", number(time))))
+      .collect::<Vec<_>>();
+    (!times.is_empty()).then(|| times.join(", "))
+  }
+
+  // the nodes that aren't inside a collapsed one, in the order they're shown
+  fn visible(&self) -> Vec<(Vec<usize>, &PlanNode)> {
+    fn walk<'a>(
+      node: &'a PlanNode,
+      path: Vec<usize>,
+      collapsed: &HashSet<Vec<usize>>,
+      visible: &mut Vec<(Vec<usize>, &'a PlanNode)>,
+    ) {
+      visible.push((path.clone(), node));
+      if !collapsed.contains(&path) {
+        for (i, child) in node.children.iter().enumerate() {
+          let mut child_path = path.clone();
+          child_path.push(i);
+          walk(child, child_path, collapsed, visible);
+        }
+      }
+    }
+    let mut visible = vec![];
+    walk(&self.plan.root, vec![], &self.collapsed, &mut visible);
+    visible
+  }
+
+  pub fn up(&mut self) {
+    self.selected = self.selected.saturating_sub(1);
+  }
+
+  pub fn down(&mut self) {
+    self.selected = self.selected.saturating_add(1).min(self.visible().len().saturating_sub(1));
+  }
+
+  pub fn top(&mut self) {
+    self.selected = 0;
+  }
+
+  pub fn bottom(&mut self) {
+    self.selected = self.visible().len().saturating_sub(1);
+  }
+
+  pub fn scroll_left(&mut self) {
+    self.x_offset = self.x_offset.saturating_sub(2);
+  }
+
+  pub fn scroll_right(&mut self) {
+    self.x_offset = self.x_offset.saturating_add(2).min(self.max_x_offset);
+  }
+
+  pub fn first_column(&mut self) {
+    self.x_offset = 0;
+  }
+
+  pub fn last_column(&mut self) {
+    self.x_offset = self.max_x_offset;
+  }
+
+  /// Collapses the selected node, or expands it if it's collapsed.
+  pub fn toggle(&mut self) {
+    let Some((path, node)) = self.visible().into_iter().nth(self.selected) else {
+      return;
+    };
+    if node.children.is_empty() {
+      return;
+    }
+    if !self.collapsed.remove(&path) {
+      self.collapsed.insert(path);
+    }
+  }
+
+  fn line(&self, path: &[usize], node: &PlanNode) -> Line<'static> {
+    let marker = match (node.children.is_empty(), self.collapsed.contains(path)) {
+      (true, _) => "  ",
+      (false, true) => "▸ ",
+      (false, false) => "▾ ",
+    };
+    let share = self.plan.share(node);
+    let label_style = match share {
+      Some(share) if share >= EXPENSIVE_SHARE => Style::default().fg(Color::Red).bold(),
+      Some(share) if share >= NOTABLE_SHARE => Style::default().fg(Color::Yellow),
+      _ => Style::default(),
+    };
+    let mut spans =
+      vec![Span::raw(format!("{}{marker}", "  ".repeat(path.len()))), Span::styled(node.label.clone(), label_style)];
+
+    let mut metrics = vec![];
+    match (node.startup_cost, node.total_cost) {
+      (Some(startup), Some(total)) => metrics.push(format!("cost={}..{}", number(startup), number(total))),
+      (None, Some(total)) => metrics.push(format!("cost={}", number(total))),
+      _ => {},
+    }
+    match (node.plan_rows, node.actual_rows) {
+      (Some(estimated), Some(actual)) => {
+        metrics.push(format!("rows={} (estimated {})", number(actual), number(estimated)))
+      },
+      (Some(estimated), None) => metrics.push(format!("rows={}", number(estimated))),
+      _ => {},
+    }
+    if let Some(loops) = node.loops {
+      metrics.push(format!("loops={}", number(loops)));
+    }
+    if let Some(time) = node.actual_time {
+      metrics.push(format!("time={}ms", number(time)));
+    }
+    if let Some(share) = share {
+      metrics.push(format!("{:.0}%", share * 100.0));
+    }
+    if !metrics.is_empty() {
+      spans.push(Span::raw(format!("  {}", metrics.join(" "))).dim());
+    }
+    if node.loops == Some(0.0) {
+      spans.push(Span::raw("  never executed").dim().italic());
+    }
+    if let Some(factor) = node.misestimate() {
+      let flag = match factor >= 1.0 {
+        true => format!("  ⚠ {factor:.0}x more rows than estimated"),
+        false => format!("  ⚠ {:.0}x fewer rows than estimated", 1.0 / factor),
+      };
+      spans.push(Span::styled(flag, Style::default().fg(Color::Magenta)));
+    }
+    if !node.details.is_empty() {
+      spans.push(Span::raw(format!("  [{}]", node.details.join("; "))).dim());
+    }
+    Line::from(spans)
+  }
+
+  pub fn draw(&mut self, f: &mut Frame<'_>, area: Rect, block: Block<'_>, focused: bool) {
+    let inner_area = block.inner(area);
+    self.selected = self.selected.min(self.visible().len().saturating_sub(1));
+    let height = inner_area.height as usize;
+    if self.selected < self.y_offset {
+      self.y_offset = self.selected;
+    } else if height > 0 && self.selected >= self.y_offset + height {
+      self.y_offset = self.selected + 1 - height;
+    }
+
+    let lines = self
+      .visible()
+      .iter()
+      .enumerate()
+      .map(|(i, (path, node))| {
+        let line = self.line(path, node);
+        match i == self.selected && focused {
+          true => line.style(Style::default().reversed()),
+          false => line,
+        }
+      })
+      .collect::<Vec<_>>();
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+    self.max_x_offset = width.saturating_sub(inner_area.width);
+    self.x_offset = self.x_offset.min(self.max_x_offset);
+
+    let line_count = lines.len();
+    f.render_widget(Paragraph::new(lines).block(block).scroll((self.y_offset as u16, self.x_offset)), area);
+    if line_count > height {
+      let vertical_scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight).symbols(scrollbar::VERTICAL);
+      let mut vertical_scrollbar_state = ScrollbarState::new(line_count.saturating_sub(1)).position(self.selected);
+      f.render_stateful_widget(
+        vertical_scrollbar,
+        area.inner(Margin { vertical: 1, horizontal: 0 }),
+        &mut vertical_scrollbar_state,
+      );
+    }
+  }
+}
+
+// whole numbers are written without decimals
+fn number(n: f64) -> String {
+  match n.fract() == 0.0 {
+    true => format!("{n:.0}"),
+    false => format!("{n:.2}"),
+  }
+}
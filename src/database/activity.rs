@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use super::{Rows, Value, schema::text};
+
+/// A session connected to the server, as the activity monitor lists it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerSession {
+  /// The process or connection id, which is what cancelling and
+  /// terminating go by.
+  pub id: String,
+  pub user: Option<String>,
+  pub database: Option<String>,
+  pub client: Option<String>,
+  /// `active`, `idle in transaction` and so on for postgres, and the
+  /// command for mysql.
+  pub state: Option<String>,
+  /// What the session is waiting on, if anything.
+  pub wait_event: Option<String>,
+  /// Seconds since the running query started, or since the session became
+  /// idle.
+  pub duration: Option<f64>,
+  pub query: Option<String>,
+}
+
+/// Expects rows of (id, user, database, client, state, wait event, duration
+/// in seconds, query).
+pub fn sessions_from_rows(rows: Rows) -> Vec<ServerSession> {
+  rows
+    .rows
+    .iter()
+    .map(|row| ServerSession {
+      id: text(&row[0]).unwrap_or_default(),
+      user: filled(&row[1]),
+      database: filled(&row[2]),
+      client: filled(&row[3]),
+      state: filled(&row[4]),
+      wait_event: filled(&row[5]),
+      duration: number(&row[6]),
+      query: filled(&row[7]),
+    })
+    .collect()
+}
+
+/// A session taking part in a lock wait, either waiting on a lock or holding
+/// one that another session waits on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LockWait {
+  pub id: String,
+  /// The sessions it waits on. Empty for sessions that only block others.
+  pub blocked_by: Vec<String>,
+  pub state: Option<String>,
+  pub query: Option<String>,
+  /// The mode of the lock it waits on, or for sessions that only block
+  /// others, of a lock it holds that's waited on.
+  pub lock_mode: Option<String>,
+  /// The table the lock is on, or the kind of lock when it isn't on one.
+  pub relation: Option<String>,
+  /// Seconds the session has been waiting, or for sessions that only block
+  /// others, how long their transaction has been open.
+  pub age: Option<f64>,
+}
+
+/// Who blocks whom, starting from a session that isn't waiting on anyone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockingTree<'a> {
+  pub session: &'a LockWait,
+  /// The sessions waiting on this one.
+  pub children: Vec<BlockingTree<'a>>,
+}
+
+impl<'a> BlockingTree<'a> {
+  fn collect_ids(&self, ids: &mut HashSet<&'a str>) {
+    ids.insert(&self.session.id);
+    self.children.iter().for_each(|child| child.collect_ids(ids));
+  }
+}
+
+/// Expects rows of (id, ids of the sessions blocking it separated by commas,
+/// state, query, lock mode, relation, age in seconds). Only the first row of
+/// each session is kept.
+pub fn lock_waits_from_rows(rows: Rows) -> Vec<LockWait> {
+  let mut waits: Vec<LockWait> = vec![];
+  for row in rows.rows.iter() {
+    let id = text(&row[0]).unwrap_or_default();
+    if waits.iter().any(|wait| wait.id == id) {
+      continue;
+    }
+    waits.push(LockWait {
+      id,
+      blocked_by: filled(&row[1])
+        .map(|ids| ids.split(',').map(|id| id.trim().to_owned()).filter(|id| !id.is_empty()).collect())
+        .unwrap_or_default(),
+      state: filled(&row[2]),
+      query: filled(&row[3]),
+      lock_mode: filled(&row[4]),
+      relation: filled(&row[5]),
+      age: number(&row[6]),
+    });
+  }
+  waits
+}
+
+/// Builds a tree for each session that blocks others without waiting itself.
+/// A session blocked by several others shows up under each of them. Sessions
+/// waiting on each other in a cycle have no such root, so the first of them
+/// starts a tree of its own.
+pub fn blocking_trees(waits: &[LockWait]) -> Vec<BlockingTree<'_>> {
+  let mut trees = waits
+    .iter()
+    .filter(|wait| wait.blocked_by.is_empty())
+    .map(|wait| blocking_tree(wait, waits, &mut vec![]))
+    .collect::<Vec<_>>();
+  let mut seen = HashSet::new();
+  trees.iter().for_each(|tree| tree.collect_ids(&mut seen));
+  for wait in waits {
+    if !seen.contains(wait.id.as_str()) {
+      let tree = blocking_tree(wait, waits, &mut vec![]);
+      tree.collect_ids(&mut seen);
+      trees.push(tree);
+    }
+  }
+  trees
+}
+
+// the path keeps cycles from going round forever
+fn blocking_tree<'a>(session: &'a LockWait, waits: &'a [LockWait], path: &mut Vec<&'a str>) -> BlockingTree<'a> {
+  path.push(&session.id);
+  let mut children = vec![];
+  for wait in waits {
+    if wait.blocked_by.contains(&session.id) && !path.contains(&wait.id.as_str()) {
+      children.push(blocking_tree(wait, waits, path));
+    }
+  }
+  path.pop();
+  BlockingTree { session, children }
+}
+
+// the catalogs use empty strings and NULLs interchangeably
+fn filled(value: &Value) -> Option<String> {
+  text(value).filter(|s| !s.is_empty())
+}
+
+fn number(value: &Value) -> Option<f64> {
+  match value {
+    Value::Int(i) => Some(*i as f64),
+    Value::Float(f) => Some(*f),
+    value => text(value)?.parse().ok(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sessions_from_rows() {
+    let rows = Rows {
+      headers: vec![],
+      rows: vec![
+        vec![
+          Value::Text("42".to_owned()),
+          Value::Text("root".to_owned()),
+          Value::Text("postgres".to_owned()),
+          Value::Text("psql (127.0.0.1:5432)".to_owned()),
+          Value::Text("active".to_owned()),
+          Value::Text("Lock: relation".to_owned()),
+          Value::Float(1.5),
+          Value::Text("select 1".to_owned()),
+        ],
+        vec![
+          Value::Int(7),
+          Value::Bytes(b"app".to_vec()),
+          Value::Null,
+          Value::Text("localhost:50312".to_owned()),
+          Value::Text("Sleep".to_owned()),
+          Value::Text("".to_owned()),
+          Value::Decimal("30".to_owned()),
+          Value::Null,
+        ],
+      ],
+      rows_affected: None,
+    };
+    assert_eq!(
+      sessions_from_rows(rows),
+      vec![
+        ServerSession {
+          id: "42".to_owned(),
+          user: Some("root".to_owned()),
+          database: Some("postgres".to_owned()),
+          client: Some("psql (127.0.0.1:5432)".to_owned()),
+          state: Some("active".to_owned()),
+          wait_event: Some("Lock: relation".to_owned()),
+          duration: Some(1.5),
+          query: Some("select 1".to_owned()),
+        },
+        ServerSession {
+          id: "7".to_owned(),
+          user: Some("app".to_owned()),
+          database: None,
+          client: Some("localhost:50312".to_owned()),
+          state: Some("Sleep".to_owned()),
+          wait_event: None,
+          duration: Some(30.0),
+          query: None,
+        },
+      ]
+    );
+  }
+
+  fn wait(id: &str, blocked_by: &[&str]) -> LockWait {
+    LockWait {
+      id: id.to_owned(),
+      blocked_by: blocked_by.iter().map(|id| id.to_string()).collect(),
+      ..Default::default()
+    }
+  }
+
+  // (id, depth) in the order the trees are shown
+  fn flatten(trees: &[BlockingTree]) -> Vec<(String, usize)> {
+    fn walk(tree: &BlockingTree, depth: usize, out: &mut Vec<(String, usize)>) {
+      out.push((tree.session.id.clone(), depth));
+      tree.children.iter().for_each(|child| walk(child, depth + 1, out));
+    }
+    let mut out = vec![];
+    trees.iter().for_each(|tree| walk(tree, 0, &mut out));
+    out
+  }
+
+  #[test]
+  fn test_lock_waits_from_rows() {
+    let t = |s: &str| Value::Text(s.to_owned());
+    let rows = Rows {
+      headers: vec![],
+      rows: vec![
+        vec![
+          t("1"),
+          t(""),
+          t("idle in transaction"),
+          t("update t set n = 1"),
+          t("RowExclusiveLock"),
+          t("t"),
+          Value::Float(12.5),
+        ],
+        vec![t("2"), t("1"), t("active"), t("lock table t"), t("AccessExclusiveLock"), t("t"), Value::Float(3.0)],
+        vec![t("3"), Value::Bytes(b"2,1".to_vec()), t("Query"), Value::Null, Value::Null, Value::Null, Value::Int(1)],
+        vec![t("3"), t("1"), t("Query"), Value::Null, Value::Null, Value::Null, Value::Int(1)],
+      ],
+      rows_affected: None,
+    };
+    let waits = lock_waits_from_rows(rows);
+    assert_eq!(waits.len(), 3);
+    assert_eq!(
+      waits[0],
+      LockWait {
+        id: "1".to_owned(),
+        blocked_by: vec![],
+        state: Some("idle in transaction".to_owned()),
+        query: Some("update t set n = 1".to_owned()),
+        lock_mode: Some("RowExclusiveLock".to_owned()),
+        relation: Some("t".to_owned()),
+        age: Some(12.5),
+      }
+    );
+    assert_eq!(waits[1].blocked_by, vec!["1"]);
+    assert_eq!(waits[2].blocked_by, vec!["2", "1"]);
+    assert_eq!(waits[2].age, Some(1.0));
+  }
+
+  #[test]
+  fn test_blocking_trees() {
+    let waits = vec![wait("3", &["2", "1"]), wait("2", &["1"]), wait("1", &[]), wait("4", &["1"])];
+    assert_eq!(
+      flatten(&blocking_trees(&waits)),
+      vec![("1".to_owned(), 0), ("3".to_owned(), 1), ("2".to_owned(), 1), ("3".to_owned(), 2), ("4".to_owned(), 1),]
+    );
+
+    let cycle = vec![wait("1", &["2"]), wait("2", &["1"]), wait("3", &["2"])];
+    assert_eq!(flatten(&blocking_trees(&cycle)), vec![("1".to_owned(), 0), ("2".to_owned(), 1), ("3".to_owned(), 2)]);
+  }
+}
@@ -0,0 +1,51 @@
+/// Ends each statement with a semicolon and puts a blank line between them,
+/// since the catalogs aren't consistent about either.
+pub fn script(statements: impl IntoIterator<Item = String>) -> String {
+  statements
+    .into_iter()
+    .map(|statement| statement.trim().trim_end_matches(';').trim_end().to_owned())
+    .filter(|statement| !statement.is_empty())
+    .map(|statement| format!("{statement};"))
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+/// Writes out a CREATE TABLE with one column or constraint per line, followed
+/// by the statements that go with it, like its indexes and comments. The name
+/// is expected to be quoted already.
+pub fn create_table(name: &str, elements: &[String], statements: &[String]) -> String {
+  let create =
+    format!("CREATE TABLE {name} (\n{}\n)", elements.iter().map(|e| format!("  {e}")).collect::<Vec<_>>().join(",\n"));
+  script(std::iter::once(create).chain(statements.iter().cloned()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_script() {
+    assert_eq!(
+      script(vec!["create table t (id int)".to_owned(), "  ".to_owned(), "create index on t (id);\n".to_owned()]),
+      "create table t (id int);\n\ncreate index on t (id);"
+    );
+    assert_eq!(script(vec![]), "");
+  }
+
+  #[test]
+  fn test_create_table() {
+    assert_eq!(
+      create_table(
+        "public.\"Users\"",
+        &["id integer NOT NULL".to_owned(), "CONSTRAINT \"Users_pkey\" PRIMARY KEY (id)".to_owned()],
+        &["COMMENT ON TABLE public.\"Users\" IS 'people';".to_owned()],
+      ),
+      "CREATE TABLE public.\"Users\" (
+  id integer NOT NULL,
+  CONSTRAINT \"Users_pkey\" PRIMARY KEY (id)
+);
+
+COMMENT ON TABLE public.\"Users\" IS 'people';"
+    );
+  }
+}
@@ -0,0 +1,401 @@
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::{self, Result};
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{Expr, GroupByExpr, SelectItem, SetExpr, Statement, TableFactor};
+
+use super::{ColumnInfo, Header, ObjectKind, TableDetails, TableInfo, Value};
+use crate::cli::Driver;
+
+/// A statement whose parameters are bound separately. The parameters are all
+/// passed as text, or NULL, and cast by the statement where it needs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParameterizedStatement {
+  pub sql: String,
+  pub params: Vec<Option<String>>,
+}
+
+/// A column of a row being updated, with its value as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnValue {
+  pub column: String,
+  /// The type as the database would write it, which postgres casts to.
+  pub type_name: String,
+  pub value: Option<String>,
+}
+
+/// The changes to one row of a table, which is found by its primary key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowUpdate {
+  /// Empty for databases without schemas, like sqlite.
+  pub schema: String,
+  pub table: String,
+  pub key: Vec<ColumnValue>,
+  pub changes: Vec<ColumnValue>,
+}
+
+/// The schema, if it's named, and the table a query selects from, when its
+/// rows map one to one onto the rows of that table. That's only the case for
+/// a plain SELECT from one table, without joins, grouping or DISTINCT, that
+/// selects columns as they are rather than expressions or aliases of them.
+pub fn editable_table(statement: &Statement) -> Option<(Option<String>, String)> {
+  let Statement::Query(query) = statement else {
+    return None;
+  };
+  let SetExpr::Select(select) = query.body.as_ref() else {
+    return None;
+  };
+  let grouped = match &select.group_by {
+    GroupByExpr::Expressions(expressions, _) => !expressions.is_empty(),
+    GroupByExpr::All(_) => true,
+  };
+  let plain_columns = select.projection.iter().all(|item| match item {
+    SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..) => true,
+    SelectItem::UnnamedExpr(expr) => matches!(expr, Expr::Identifier(_) | Expr::CompoundIdentifier(_)),
+    SelectItem::ExprWithAlias { .. } => false,
+  });
+  if query.with.is_some() || select.distinct.is_some() || select.having.is_some() || grouped || !plain_columns {
+    return None;
+  }
+  let [from] = select.from.as_slice() else {
+    return None;
+  };
+  let TableFactor::Table { name, args: None, .. } = &from.relation else {
+    return None;
+  };
+  if !from.joins.is_empty() {
+    return None;
+  }
+  let parts = name.0.iter().map(|part| part.as_ident().map(|ident| ident.value.clone())).collect::<Option<Vec<_>>>()?;
+  match parts.as_slice() {
+    [table] => Some((None, table.clone())),
+    [.., schema, table] => Some((Some(schema.clone()), table.clone())),
+    [] => None,
+  }
+}
+
+/// Finds the table that `editable_table()` named among the database's tables.
+/// Names that don't match exactly are matched without regard to case, since
+/// they could have been written unquoted. A table named without its schema
+/// is looked for in `default_schema` when there's more than one by that name.
+pub fn find_table<'a>(
+  tables: &'a [TableInfo],
+  schema: Option<&str>,
+  table: &str,
+  default_schema: Option<&str>,
+) -> Result<&'a TableInfo> {
+  let matches = |exact: bool| {
+    let eq = |a: &str, b: &str| if exact { a == b } else { a.eq_ignore_ascii_case(b) };
+    tables
+      .iter()
+      .filter(|info| info.kind == ObjectKind::Table && eq(&info.name, table))
+      // databases without schemas still let them be named, like sqlite's main
+      .filter(|info| schema.is_none_or(|schema| info.schema.is_empty() || eq(&info.schema, schema)))
+      .collect::<Vec<_>>()
+  };
+  let found = Some(matches(true)).filter(|found| !found.is_empty()).unwrap_or_else(|| matches(false));
+  match found.as_slice() {
+    [] => Err(eyre::Report::msg(format!("Could not find the table {table}"))),
+    [info] => Ok(info),
+    found => found.iter().find(|info| default_schema == Some(info.schema.as_str())).copied().ok_or_else(|| {
+      eyre::Report::msg(format!("There's a table named {table} in more than one schema, so the query has to name one"))
+    }),
+  }
+}
+
+/// The table that results were selected from, and which of their columns are
+/// its columns, so that edits to them can be written as UPDATEs.
+#[derive(Debug, Clone)]
+pub struct EditTarget {
+  /// Empty for databases without schemas, like sqlite.
+  pub schema: String,
+  pub table: String,
+  driver: Driver,
+  // the position of each primary key column among the results' columns
+  key: Vec<usize>,
+  // the table's column that each of the results' columns shows, if any
+  columns: Vec<Option<ColumnInfo>>,
+}
+
+impl EditTarget {
+  /// Fails if the table has no primary key, or the results leave part of it
+  /// out, since there'd be no telling which row an edit belongs to.
+  pub fn new(schema: String, table: String, details: TableDetails, headers: &[Header], driver: Driver) -> Result<Self> {
+    if details.primary_key.is_empty() {
+      return Err(eyre::Report::msg(format!("{table} has no primary key, so its rows can't be edited")));
+    }
+    // a column selected twice can't be told apart from its copy
+    let position = |name: &str| match headers.iter().filter(|header| header.name == name).count() {
+      1 => headers.iter().position(|header| header.name == name),
+      _ => None,
+    };
+    let key =
+      details.primary_key.iter().map(|column| position(column)).collect::<Option<Vec<_>>>().ok_or_else(|| {
+        eyre::Report::msg(format!(
+          "The results need to include the primary key ({}) for their rows to be edited",
+          details.primary_key.join(", ")
+        ))
+      })?;
+    let columns = headers
+      .iter()
+      .map(|header| match position(&header.name) {
+        Some(_) => details.columns.iter().find(|column| column.name == header.name).cloned(),
+        None => None,
+      })
+      .collect();
+    Ok(Self { schema, table, driver, key, columns })
+  }
+
+  /// Whether the results' column at this position is one of the table's.
+  pub fn editable(&self, column: usize) -> bool {
+    self.columns.get(column).is_some_and(Option::is_some)
+  }
+
+  /// Writes an UPDATE for each row that has edits, keyed by the row and
+  /// column positions in the results, with `None` setting a column to NULL.
+  pub fn updates(
+    &self,
+    rows: &[Vec<Value>],
+    edits: &BTreeMap<(usize, usize), Option<String>>,
+  ) -> Result<Vec<ParameterizedStatement>> {
+    let mut updates: BTreeMap<usize, Vec<ColumnValue>> = BTreeMap::new();
+    for (&(row, column), value) in edits {
+      let info = self.columns.get(column).and_then(Option::as_ref).ok_or_else(|| {
+        eyre::Report::msg(format!("Column {} of the results isn't a column of {}", column + 1, self.table))
+      })?;
+      updates.entry(row).or_default().push(ColumnValue {
+        column: info.name.clone(),
+        type_name: info.type_name.clone(),
+        value: value.clone(),
+      });
+    }
+    updates
+      .into_iter()
+      .map(|(row, changes)| {
+        let key = self
+          .key
+          .iter()
+          .map(|&column| {
+            let info = self.columns[column].as_ref()?;
+            let value = key_text(rows.get(row)?.get(column)?)?;
+            Some(ColumnValue { column: info.name.clone(), type_name: info.type_name.clone(), value: Some(value) })
+          })
+          .collect::<Option<Vec<_>>>()
+          .ok_or_else(|| eyre::Report::msg(format!("The primary key of row {} can't be matched", row + 1)))?;
+        let update = RowUpdate { schema: self.schema.clone(), table: self.table.clone(), key, changes };
+        Ok(update_statement(&update, self.driver))
+      })
+      .collect()
+  }
+}
+
+// the primary key as text that the database can cast back to the value it
+// came from. bytes are shown as hex and floats may have been rounded, so
+// neither can be relied on to match.
+fn key_text(value: &Value) -> Option<String> {
+  match value {
+    Value::Null | Value::Bytes(_) | Value::Float(_) => None,
+    Value::Unknown(s) if s == "_ERROR_" => None,
+    value => Some(value.to_string()),
+  }
+}
+
+/// Each UPDATE goes by a primary key, so changing anything other than one
+/// row means the row was changed or deleted since it was loaded.
+pub fn expect_one_row(statement: &ParameterizedStatement, rows_affected: u64) -> Result<()> {
+  match rows_affected {
+    1 => Ok(()),
+    n => Err(eyre::Report::msg(format!(
+      "Expected to update one row but updated {n}, so nothing was changed. Run the query again to see the rows as \
+       they are now.\n\n{}",
+      statement.sql
+    ))),
+  }
+}
+
+/// Writes an UPDATE of one row, with a parameter for each value.
+pub fn update_statement(update: &RowUpdate, driver: Driver) -> ParameterizedStatement {
+  let quote = |name: &str| match driver {
+    Driver::MySql => format!("`{}`", name.replace('`', "``")),
+    _ => format!("\"{}\"", name.replace('"', "\"\"")),
+  };
+  let mut params = vec![];
+  let mut assign = |column: &ColumnValue| {
+    params.push(column.value.clone());
+    let placeholder = match driver {
+      Driver::Postgres => format!("CAST(${} AS {})", params.len(), column.type_name),
+      Driver::Oracle => format!(":{}", params.len()),
+      Driver::MySql | Driver::Sqlite => "?".to_owned(),
+    };
+    format!("{} = {placeholder}", quote(&column.column))
+  };
+  let set = update.changes.iter().map(&mut assign).collect::<Vec<_>>().join(", ");
+  let filter = update.key.iter().map(&mut assign).collect::<Vec<_>>().join(" AND ");
+  let table = match update.schema.is_empty() {
+    true => quote(&update.table),
+    false => format!("{}.{}", quote(&update.schema), quote(&update.table)),
+  };
+  ParameterizedStatement { sql: format!("UPDATE {table} SET {set} WHERE {filter}"), params }
+}
+
+#[cfg(test)]
+mod tests {
+  use sqlparser::{dialect::PostgreSqlDialect, parser::Parser};
+
+  use super::*;
+
+  fn parse(query: &str) -> Statement {
+    Parser::parse_sql(&PostgreSqlDialect {}, query).unwrap().remove(0)
+  }
+
+  #[test]
+  fn test_editable_table() {
+    let table = |schema: Option<&str>, name: &str| Some((schema.map(str::to_owned), name.to_owned()));
+    assert_eq!(editable_table(&parse("select * from users")), table(None, "users"));
+    assert_eq!(
+      editable_table(&parse("select id, name from public.users u where id > 1")),
+      table(Some("public"), "users")
+    );
+    assert_eq!(editable_table(&parse("select * from \"My Table\" limit 5")), table(None, "My Table"));
+    assert_eq!(editable_table(&parse("select * from db.public.users")), table(Some("public"), "users"));
+
+    for query in [
+      "select * from users join posts on posts.user_id = users.id",
+      "select * from users, posts",
+      "select distinct name from users",
+      "select name, count(*) from users group by name",
+      "with u as (select * from users) select * from u",
+      "select * from users union select * from admins",
+      "select * from (select * from users) u",
+      "select * from generate_series(1, 3)",
+      "select id, upper(name) from users",
+      "select id, name as title from users",
+      "select 1",
+      "update users set name = 'x'",
+    ] {
+      assert_eq!(editable_table(&parse(query)), None, "{query}");
+    }
+  }
+
+  fn header(name: &str) -> Header {
+    Header { name: name.to_owned(), type_name: "".to_owned() }
+  }
+
+  fn details(primary_key: &[&str]) -> TableDetails {
+    let column = |name: &str, type_name: &str| ColumnInfo {
+      name: name.to_owned(),
+      type_name: type_name.to_owned(),
+      nullable: true,
+      default: None,
+      comment: None,
+    };
+    TableDetails {
+      columns: vec![column("id", "integer"), column("name", "text"), column("age", "integer")],
+      primary_key: primary_key.iter().map(|column| column.to_string()).collect(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_edit_target() {
+    let headers = vec![header("name"), header("id"), header("age"), header("age"), header("note")];
+    let target =
+      EditTarget::new("public".to_owned(), "users".to_owned(), details(&["id"]), &headers, Driver::MySql).unwrap();
+    assert_eq!((0..5).map(|column| target.editable(column)).collect::<Vec<_>>(), vec![true, true, false, false, false]);
+
+    let rows = vec![
+      vec![Value::Text("ann".to_owned()), Value::Int(1), Value::Int(30), Value::Int(30), Value::Null],
+      vec![Value::Text("bob".to_owned()), Value::Int(2), Value::Int(40), Value::Int(40), Value::Null],
+      vec![Value::Text("cy".to_owned()), Value::Null, Value::Int(50), Value::Int(50), Value::Null],
+    ];
+    let edits = BTreeMap::from([((1, 0), None), ((0, 0), Some("al".to_owned())), ((1, 1), Some("3".to_owned()))]);
+    assert_eq!(
+      target.updates(&rows, &edits).unwrap(),
+      vec![
+        ParameterizedStatement {
+          sql: "UPDATE `public`.`users` SET `name` = ? WHERE `id` = ?".to_owned(),
+          params: vec![Some("al".to_owned()), Some("1".to_owned())],
+        },
+        ParameterizedStatement {
+          sql: "UPDATE `public`.`users` SET `name` = ?, `id` = ? WHERE `id` = ?".to_owned(),
+          params: vec![None, Some("3".to_owned()), Some("2".to_owned())],
+        },
+      ]
+    );
+    assert!(target.updates(&rows, &BTreeMap::from([((2, 0), None)])).is_err());
+    assert!(target.updates(&rows, &BTreeMap::from([((0, 2), None)])).is_err());
+
+    let target = |primary_key: &[&str], headers: &[Header]| {
+      EditTarget::new("".to_owned(), "users".to_owned(), details(primary_key), headers, Driver::Sqlite)
+    };
+    assert!(target(&[], &headers).is_err());
+    assert!(target(&["id", "age"], &headers).is_err());
+    assert!(target(&["id"], &[header("name")]).is_err());
+  }
+
+  #[test]
+  fn test_find_table() {
+    let info = |schema: &str, name: &str, kind: ObjectKind| TableInfo {
+      schema: schema.to_owned(),
+      name: name.to_owned(),
+      kind,
+      comment: None,
+    };
+    let tables = vec![
+      info("public", "users", ObjectKind::Table),
+      info("audit", "users", ObjectKind::Table),
+      info("public", "Posts", ObjectKind::Table),
+      info("public", "posts", ObjectKind::View),
+      info("audit", "events", ObjectKind::Table),
+    ];
+    let found = |schema: Option<&str>, table: &str, default_schema: Option<&str>| {
+      find_table(&tables, schema, table, default_schema).map(|info| (info.schema.as_str(), info.name.as_str())).ok()
+    };
+    assert_eq!(found(Some("audit"), "users", None), Some(("audit", "users")));
+    assert_eq!(found(None, "users", Some("public")), Some(("public", "users")));
+    assert_eq!(found(None, "users", None), None);
+    assert_eq!(found(None, "posts", None), Some(("public", "Posts")));
+    assert_eq!(found(None, "EVENTS", Some("public")), Some(("audit", "events")));
+    assert_eq!(found(Some("public"), "events", None), None);
+
+    let sqlite = vec![info("", "books", ObjectKind::Table)];
+    assert!(find_table(&sqlite, Some("main"), "books", None).is_ok());
+  }
+
+  #[test]
+  fn test_update_statement() {
+    let column = |column: &str, type_name: &str, value: Option<&str>| ColumnValue {
+      column: column.to_owned(),
+      type_name: type_name.to_owned(),
+      value: value.map(str::to_owned),
+    };
+    let update = RowUpdate {
+      schema: "public".to_owned(),
+      table: "user \"accounts\"".to_owned(),
+      key: vec![column("id", "integer", Some("7")), column("region", "text", Some("eu"))],
+      changes: vec![column("name", "character varying(20)", Some("ann")), column("age", "integer", None)],
+    };
+    let params = vec![Some("ann".to_owned()), None, Some("7".to_owned()), Some("eu".to_owned())];
+    assert_eq!(
+      update_statement(&update, Driver::Postgres),
+      ParameterizedStatement {
+        sql: "UPDATE \"public\".\"user \"\"accounts\"\"\" SET \"name\" = CAST($1 AS character varying(20)), \"age\" = \
+              CAST($2 AS integer) WHERE \"id\" = CAST($3 AS integer) AND \"region\" = CAST($4 AS text)"
+          .to_owned(),
+        params: params.clone(),
+      }
+    );
+    assert_eq!(
+      update_statement(&update, Driver::MySql),
+      ParameterizedStatement {
+        sql: "UPDATE `public`.`user \"accounts\"` SET `name` = ?, `age` = ? WHERE `id` = ? AND `region` = ?".to_owned(),
+        params,
+      }
+    );
+    let update = RowUpdate { schema: "".to_owned(), ..update };
+    assert_eq!(
+      update_statement(&update, Driver::Sqlite).sql,
+      "UPDATE \"user \"\"accounts\"\"\" SET \"name\" = ?, \"age\" = ? WHERE \"id\" = ? AND \"region\" = ?"
+    );
+  }
+}
@@ -1,7 +1,12 @@
 use async_trait::async_trait;
 use color_eyre::eyre::{self, Result};
+use serde::Deserialize;
 use sqlparser::{
-  ast::Statement,
+  ast::{
+    Expr, Fetch, FromTable, LimitClause, ObjectName, Query, SelectItem, SelectItemQualifiedWildcardKind, SetExpr,
+    Statement, TableFactor, TableWithJoins, TransactionAccessMode, TransactionMode, UpdateTableFromKind,
+    WildcardAdditionalOptions,
+  },
   dialect::{Dialect, GenericDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect},
   keywords,
   parser::{Parser, ParserError},
@@ -10,15 +15,30 @@ use tokio::task::JoinHandle;
 
 use crate::cli::{Cli, Driver};
 
+mod activity;
+mod ddl;
+mod edit;
 mod mysql;
 mod oracle;
+mod plan;
+mod policy;
 mod postgresql;
+mod schema;
 mod sqlite;
+mod stream;
+mod value;
 
+pub use activity::{BlockingTree, LockWait, ServerSession, blocking_trees};
+pub use edit::{EditTarget, ParameterizedStatement, editable_table, find_table};
 pub use mysql::MySqlDriver;
 pub use oracle::OracleDriver;
+pub use plan::{Plan, PlanNode, parse_plan};
+pub use policy::{ExecutionPolicy, StatementKind};
 pub use postgresql::PostgresDriver;
+pub use schema::{ColumnInfo, ForeignKey, IndexInfo, ObjectKind, TableDetails, TableInfo};
 pub use sqlite::SqliteDriver;
+pub use stream::{RowStream, StreamState};
+pub use value::Value;
 
 #[derive(Debug, Clone)]
 pub struct Header {
@@ -27,16 +47,10 @@ pub struct Header {
 }
 pub type Headers = Vec<Header>;
 
-pub struct Value {
-  pub parse_error: bool,
-  pub is_null: bool,
-  pub string: String,
-}
-
 #[derive(Debug, Clone)]
 pub struct Rows {
   pub headers: Headers,
-  pub rows: Vec<Vec<String>>,
+  pub rows: Vec<Vec<Value>>,
   pub rows_affected: Option<u64>,
 }
 
@@ -44,13 +58,60 @@ pub struct Rows {
 pub struct QueryResultsWithMetadata {
   pub results: Result<Rows>,
   pub statement_type: Option<Statement>,
+  /// Set when `results` only holds the first page of rows.
+  pub stream: Option<RowStream>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// A message sent with `NOTIFY` to a channel the connection listens on.
+#[derive(Debug, Clone)]
+pub struct Notification {
+  pub channel: String,
+  pub payload: String,
+  /// The backend that sent the notification.
+  pub process_id: u32,
+  pub received_at: chrono::DateTime<chrono::Local>,
+}
+
+/// What to do with the remaining statements of a multi-statement
+/// script when one of them fails.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ScriptErrorPolicy {
+  #[default]
+  #[serde(alias = "stop", alias = "STOP")]
+  Stop,
+  #[serde(alias = "continue", alias = "CONTINUE")]
+  Continue,
+}
+
+/// Options that apply to every statement started with `start_query()`.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryOptions {
+  pub on_error: ScriptErrorPolicy,
+  /// Number of rows fetched at a time when a result is streamed.
+  pub page_size: usize,
+  /// Streamed results stop fetching once they reach this many rows.
+  pub row_cap: usize,
+  /// Injected as a limit into queries that don't limit their rows themselves.
+  pub max_rows: Option<usize>,
+}
+
+impl Default for QueryOptions {
+  fn default() -> Self {
+    Self { on_error: ScriptErrorPolicy::default(), page_size: 500, row_cap: 100_000, max_rows: None }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub enum ExecutionType {
+  #[serde(alias = "confirm", alias = "CONFIRM")]
   Confirm,
+  #[serde(alias = "transaction", alias = "TRANSACTION")]
   Transaction,
+  #[serde(alias = "normal", alias = "NORMAL")]
   Normal,
+  /// The statement is refused instead of being run.
+  #[serde(alias = "forbid", alias = "FORBID")]
+  Forbid,
 }
 
 #[derive(Debug)]
@@ -66,11 +127,62 @@ impl std::fmt::Display for ParseError {
 }
 impl std::error::Error for ParseError {}
 
-pub type QueryTask = JoinHandle<QueryResultsWithMetadata>;
+pub type QueryTask = JoinHandle<Vec<QueryResultsWithMetadata>>;
+
+/// The rows changed by an UPDATE or DELETE whose transaction is waiting to
+/// be committed, so that they can be looked over first.
+#[derive(Debug, Clone)]
+pub struct TxPreview {
+  pub statement: Statement,
+  /// The rows as they were before the statement ran.
+  pub before: Rows,
+  /// The rows as they'll be once the transaction is committed. Not set for
+  /// DELETE, since there won't be anything left.
+  pub after: Option<Rows>,
+  /// Set to the number of rows kept when the statement changed more.
+  pub truncated_at: Option<usize>,
+}
+
+impl TxPreview {
+  // `selected` holds the rows an UPDATE matched before it ran, and
+  // `returned` the rows the rewritten statement returned. either can hold
+  // one row past the cap, which tells that there were more.
+  fn new(statement: Statement, selected: Option<Rows>, returned: Rows, row_cap: usize) -> Self {
+    let (mut before, mut after) = match selected {
+      Some(before) => (before, Some(returned)),
+      None => (returned, None),
+    };
+    let truncated = before.rows.len() > row_cap || after.as_ref().is_some_and(|after| after.rows.len() > row_cap);
+    before.rows.truncate(row_cap);
+    if let Some(after) = &mut after {
+      after.rows.truncate(row_cap);
+    }
+    Self { statement, before, after, truncated_at: truncated.then_some(row_cap) }
+  }
+}
+
+// the queries a driver runs in a transaction to fill in a `TxPreview`
+#[derive(Debug, Clone, PartialEq)]
+struct TxPreviewQueries {
+  /// Selects the rows an UPDATE matches, before it runs.
+  select: Option<String>,
+  /// The statement, rewritten to return the rows it changes.
+  statement: String,
+}
+
+impl TxPreviewQueries {
+  // one row past the cap is selected, to tell whether there were more
+  fn capped_select(&self, row_cap: usize) -> Option<String> {
+    self.select.as_ref().map(|select| format!("{select} LIMIT {}", row_cap.saturating_add(1)))
+  }
+}
 
+#[allow(clippy::large_enum_variant)]
 pub enum DbTaskResult {
-  Finished(QueryResultsWithMetadata),
-  ConfirmTx(Option<u64>, Option<Statement>),
+  Finished(Vec<QueryResultsWithMetadata>),
+  /// The rows affected, the statement if the transaction ran only one, and
+  /// the rows each statement changed for drivers that can return them.
+  ConfirmTx(Option<u64>, Option<Statement>, Vec<TxPreview>),
   Pending,
   NoTask,
 }
@@ -83,9 +195,14 @@ pub trait Database {
   /// calling `new()` does not connect).
   async fn init(&mut self, args: Cli) -> Result<()>;
 
-  /// Spawns a tokio task that runs the query. The task should
+  /// Spawns a tokio task that runs the query. If the query contains
+  /// multiple statements, they are run in order on the same connection,
+  /// producing one result per statement; `options.on_error` decides
+  /// whether the remaining statements still run after one fails. Drivers
+  /// that support it only fetch the first page of rows of the last
+  /// statement, leaving the rest to a `RowStream`. The task should
   /// expect to be polled via the `get_query_results()` method.
-  async fn start_query(&mut self, query: String, bypass_parser: bool) -> Result<()>;
+  async fn start_query(&mut self, query: String, bypass_parser: bool, options: QueryOptions) -> Result<()>;
 
   /// Aborts the tokio task running the active query or transaction.
   /// Some drivers also kill the process that was running the query,
@@ -98,23 +215,91 @@ pub trait Database {
   /// `DbTaskResult::Pending` if the task is still running.
   async fn get_query_results(&mut self) -> Result<DbTaskResult>;
 
-  /// Spawns a tokio task that runs the query in a transaction.
+  /// Spawns a tokio task that runs the query's statements in a transaction,
+  /// stopping at the first one that fails, in which case the transaction is
+  /// rolled back and the results are returned as finished.
   /// The task should also expect to be polled via the `get_query_results()`
-  /// method.
-  async fn start_tx(&mut self, query: String) -> Result<()>;
-
-  /// Commits the pending transaction and returns the results.
-  /// Should do nothing or fail gracefully if no transaction is pending.
-  async fn commit_tx(&mut self) -> Result<Option<QueryResultsWithMetadata>>;
+  /// method. Previews of the rows each statement changes hold at most
+  /// `options.row_cap` rows.
+  async fn start_tx(&mut self, query: String, options: QueryOptions) -> Result<()>;
+
+  /// Spawns a tokio task that runs the statements in one transaction, with
+  /// their parameters bound as text or NULL. Like `start_tx()`, the
+  /// transaction then waits to be committed or rolled back. Each statement
+  /// is expected to change exactly one row, and the transaction fails if it
+  /// doesn't.
+  async fn start_tx_with_params(&mut self, statements: Vec<ParameterizedStatement>) -> Result<()>;
+
+  /// Commits the pending transaction and returns the results of its
+  /// statements. Should do nothing or fail gracefully if no transaction is
+  /// pending.
+  async fn commit_tx(&mut self) -> Result<Vec<QueryResultsWithMetadata>>;
 
   /// Rolls back the pending transaction. Should do nothing or fail gracefully
   /// if no transaction is pending.
   async fn rollback_tx(&mut self) -> Result<()>;
 
+  /// Starts a transaction that every following query runs in, until it is
+  /// ended with `commit_session_tx()` or `rollback_session_tx()`. The driver
+  /// pins a connection for the length of the transaction and runs queries on
+  /// it instead of one from the pool.
+  async fn begin_session_tx(&mut self) -> Result<()>;
+
+  /// Commits the transaction started by `begin_session_tx()` and releases
+  /// its connection. Should do nothing if no transaction was started.
+  async fn commit_session_tx(&mut self) -> Result<()>;
+
+  /// Rolls back the transaction started by `begin_session_tx()` and releases
+  /// its connection. Should do nothing if no transaction was started.
+  async fn rollback_session_tx(&mut self) -> Result<()>;
+
+  /// Rolls back the pending and session transactions, aborts the active
+  /// query, and closes the connection pool. The driver can't be used again until
+  /// `init()` is called.
+  async fn close(&mut self) -> Result<()>;
+
+  /// Starts listening on a channel, along with any it already listens on.
+  /// Notifications arrive on a connection of their own, so they keep coming
+  /// in while queries and transactions run. Fails for drivers without
+  /// LISTEN/NOTIFY.
+  async fn listen(&mut self, channel: &str) -> Result<()>;
+
+  /// Stops listening on every channel and drops the listening connection.
+  async fn unlisten_all(&mut self) -> Result<()>;
+
+  /// Returns the notifications received since the last call, oldest first.
+  fn take_notifications(&mut self) -> Vec<Notification>;
+
+  /// Lists the sessions connected to the server, leaving out the one that
+  /// asks. Fails for drivers without a way to see them.
+  async fn load_activity(&self) -> Result<Vec<ServerSession>>;
+
+  /// Cancels the query a session is running, leaving it connected.
+  async fn cancel_session(&self, id: &str) -> Result<()>;
+
+  /// Disconnects a session, rolling back whatever it had in progress.
+  async fn terminate_session(&self, id: &str) -> Result<()>;
+
+  /// Lists the sessions waiting on locks, along with the sessions they wait
+  /// on. Fails for drivers without a way to see them.
+  async fn load_lock_waits(&self) -> Result<Vec<LockWait>>;
+
   /// Returns rows representing the database menu. The menu component
-  /// expects each row to be combination of schema and table name.
+  /// expects each row to be a combination of schema, object name and
+  /// object kind, where the kind is an `ObjectKind` written in lowercase.
   async fn load_menu(&self) -> Result<Rows>;
 
+  /// Returns the tables and views in the database, skipping system schemas.
+  async fn load_tables(&self) -> Result<Vec<TableInfo>>;
+
+  /// Returns the columns, primary key, foreign keys and indexes of a table
+  /// or view.
+  async fn describe_table(&self, schema: &str, table: &str) -> Result<TableDetails>;
+
+  /// Returns the statements that would create an object as it is now, e.g.
+  /// a table followed by its indexes.
+  async fn load_ddl(&self, kind: ObjectKind, schema: &str, name: &str) -> Result<String>;
+
   /// Returns a query that can be used to preview the rows in a table.
   fn preview_rows_query(&self, schema: &str, table: &str) -> String;
 
@@ -129,6 +314,23 @@ pub trait Database {
 
   /// Returns a query that can be used to preview the policies in a table.
   fn preview_policies_query(&self, schema: &str, table: &str) -> String;
+
+  /// Returns a query that can be used to preview the definition of a view
+  /// or materialized view.
+  fn preview_definition_query(&self, schema: &str, view: &str) -> String;
+
+  /// Returns a query that can be used to preview the source of a function
+  /// or procedure.
+  fn preview_source_query(&self, schema: &str, function: &str) -> String;
+
+  /// Returns a query that can be used to preview the current value of a sequence.
+  fn preview_sequence_query(&self, schema: &str, sequence: &str) -> String;
+
+  /// Returns a query that can be used to preview the body of a trigger.
+  fn preview_trigger_query(&self, schema: &str, trigger: &str) -> String;
+
+  /// Returns a query that can be used to preview the values of an enum.
+  fn preview_enum_query(&self, schema: &str, name: &str) -> String;
 }
 
 fn get_first_query(query: String, driver: Driver) -> Result<(String, Statement), ParseError> {
@@ -146,53 +348,274 @@ fn get_first_query(query: String, driver: Driver) -> Result<(String, Statement),
   }
 }
 
+// only for drivers that support RETURNING. UPDATE ... FROM and DELETE ...
+// USING return the columns of every table they join, so the rows returned
+// are limited to the table being changed. sqlite only returns the changed
+// table's columns, and doesn't accept a qualified wildcard.
+fn get_tx_preview_queries(statement: &Statement, driver: Driver) -> Option<TxPreviewQueries> {
+  let qualify = |joined: bool| joined && !matches!(driver, Driver::Sqlite);
+  match statement {
+    Statement::Update { table, from, selection, returning, .. } => {
+      let target = table_qualifier(table)?;
+      let from = match from {
+        Some(UpdateTableFromKind::BeforeSet(from) | UpdateTableFromKind::AfterSet(from)) => from.as_slice(),
+        None => &[],
+      };
+      let tables = std::iter::once(table).chain(from).map(|t| t.to_string()).collect::<Vec<_>>().join(", ");
+      let mut select = format!("SELECT {target}.* FROM {tables}");
+      if let Some(selection) = selection {
+        select.push_str(&format!(" WHERE {selection}"));
+      }
+      let mut statement = statement.clone();
+      if returning.is_none()
+        && let Statement::Update { returning, .. } = &mut statement
+      {
+        *returning = Some(vec![returning_item(target, qualify(!from.is_empty() || !table.joins.is_empty()))]);
+      }
+      Some(TxPreviewQueries { select: Some(select), statement: statement.to_string() })
+    },
+    // mysql's multi-table deletes name their targets separately
+    Statement::Delete(delete) if delete.tables.is_empty() => {
+      let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &delete.from;
+      let table = from.first()?;
+      let target = table_qualifier(table)?;
+      let mut delete = delete.clone();
+      if delete.returning.is_none() {
+        let joined = from.len() > 1 || !table.joins.is_empty() || delete.using.is_some();
+        delete.returning = Some(vec![returning_item(target, qualify(joined))]);
+      }
+      Some(TxPreviewQueries { select: None, statement: Statement::Delete(delete).to_string() })
+    },
+    _ => None,
+  }
+}
+
+// the alias of the table, or its name if it doesn't have one
+fn table_qualifier(table: &TableWithJoins) -> Option<ObjectName> {
+  match &table.relation {
+    TableFactor::Table { alias: Some(alias), .. } => Some(ObjectName::from(vec![alias.name.clone()])),
+    TableFactor::Table { name, .. } => Some(name.clone()),
+    _ => None,
+  }
+}
+
+fn returning_item(target: ObjectName, qualified: bool) -> SelectItem {
+  match qualified {
+    true => SelectItem::QualifiedWildcard(
+      SelectItemQualifiedWildcardKind::ObjectName(target),
+      WildcardAdditionalOptions::default(),
+    ),
+    false => SelectItem::Wildcard(WildcardAdditionalOptions::default()),
+  }
+}
+
+// what a transaction whose statements all went through asks to have
+// confirmed: the rows they affected, and the statement if there was only one
+fn confirm_tx(results: &[QueryResultsWithMetadata], previews: Vec<TxPreview>) -> DbTaskResult {
+  let rows_affected = results
+    .iter()
+    .filter(|result| !matches!(result.statement_type, Some(Statement::Query(_))))
+    .filter_map(|result| result.results.as_ref().ok()?.rows_affected)
+    .reduce(|a, b| a + b);
+  let statement = match results {
+    [result] => result.statement_type.clone(),
+    _ => None,
+  };
+  DbTaskResult::ConfirmTx(rows_affected, statement, previews)
+}
+
+fn get_queries(query: String, driver: Driver) -> Result<Vec<(String, Statement)>, ParseError> {
+  let ast = Parser::parse_sql(&*get_dialect(driver), &query);
+  match ast {
+    Ok(ast) if ast.is_empty() => Err(ParseError::EmptyQuery("Parsed query is empty".to_owned())),
+    Ok(ast) => Ok(ast.into_iter().map(|statement| (statement.to_string(), statement)).collect()),
+    Err(e) => Err(ParseError::SqlParserError(e)),
+  }
+}
+
+// splits the query into the statements that a driver should run. when
+// bypassing the parser, the raw query is handed to the database as-is.
+// queries are limited to `max_rows`, but keep their original statement, so
+// the results can tell that the limit was injected. EXPLAINs are asked for
+// plans in a format that can be shown as a tree.
+fn get_script(
+  query: String,
+  bypass_parser: bool,
+  driver: Driver,
+  max_rows: Option<usize>,
+) -> Result<Vec<(String, Option<Statement>)>> {
+  match bypass_parser {
+    true => Ok(vec![(query, None)]),
+    false => Ok(
+      get_queries(query, driver)?
+        .into_iter()
+        .map(|(query, statement)| match max_rows {
+          Some(max_rows) if is_unbounded_query(&statement) => {
+            (limit_rows(statement.clone(), max_rows, driver).to_string(), Some(statement))
+          },
+          _ => (plan::structured_explain(&statement, driver).unwrap_or(query), Some(statement)),
+        })
+        .collect(),
+    ),
+  }
+}
+
+/// Whether the statement is a query that doesn't limit its own rows with
+/// LIMIT, FETCH or TOP.
+pub fn is_unbounded_query(statement: &Statement) -> bool {
+  match statement {
+    Statement::Query(query) => {
+      query.limit_clause.is_none()
+        && query.fetch.is_none()
+        && !matches!(query.body.as_ref(), SetExpr::Select(select) if select.top.is_some())
+    },
+    _ => false,
+  }
+}
+
+/// Whether the statement's rows can be read a page at a time. Rows that are
+/// left unread keep the statement running, so queries that lock the rows they
+/// read (FOR UPDATE, FOR SHARE) would hold those locks for as long as the
+/// results are on screen.
+pub fn is_streamable_query(statement: &Statement) -> bool {
+  matches!(statement, Statement::Query(query) if query.locks.is_empty())
+}
+
+// oracle doesn't have LIMIT, but supports the standard FETCH FIRST
+fn limit_rows(mut statement: Statement, max_rows: usize, driver: Driver) -> Statement {
+  if let Statement::Query(query) = &mut statement {
+    let quantity = Expr::value(sqlparser::ast::Value::Number(max_rows.to_string(), false));
+    match driver {
+      Driver::Oracle => query.fetch = Some(Fetch { with_ties: false, percent: false, quantity: Some(quantity) }),
+      _ => {
+        query.limit_clause = Some(LimitClause::LimitOffset { limit: Some(quantity), offset: None, limit_by: vec![] })
+      },
+    }
+  }
+  statement
+}
+
+/// On read-only connections, anything that isn't a query is refused, and
+/// statements the policy forbids are refused even once confirmed.
 pub fn get_execution_type(
   query: String,
   confirmed: bool,
   driver: Driver,
+  read_only: bool,
+  policy: &ExecutionPolicy,
 ) -> Result<(ExecutionType, Option<Statement>)> {
-  let first_query = get_first_query(query, driver);
+  let queries = get_queries(query, driver);
+  if read_only
+    && let Ok(queries) = &queries
+    && let Some((_, statement)) = queries.iter().find(|(_, statement)| !is_read_only_statement(statement))
+  {
+    return Err(eyre::Report::msg(format!(
+      "{} statements can't be run on a read-only connection",
+      statement_type_string(Some(statement.clone()))
+    )));
+  }
+  if let Ok(queries) = &queries
+    && let Some(kind) = queries
+      .iter()
+      .filter_map(|(_, statement)| policy::statement_kind(statement))
+      .find(|kind| policy.execution_type(*kind) == ExecutionType::Forbid)
+  {
+    return Err(eyre::Report::msg(format!("{kind} is forbidden by the execution policy")));
+  }
 
-  match first_query {
-    Ok((_, statement)) => Ok((get_default_execution_type(statement.clone(), confirmed), Some(statement.clone()))),
+  match queries {
+    Ok(queries) if queries.len() == 1 => {
+      let statement = queries[0].1.clone();
+      Ok((get_default_execution_type(&statement, confirmed, policy), Some(statement)))
+    },
+    // a script with statements that need a transaction runs in one as a
+    // whole, unless it starts or ends transactions itself. then, like a
+    // script with statements that need confirming, it's confirmed up front.
+    Ok(queries) => {
+      let manages_tx = queries.iter().any(|(_, statement)| is_transaction_control(statement));
+      let mut transaction = None;
+      for (_, statement) in queries {
+        match get_default_execution_type(&statement, confirmed, policy) {
+          ExecutionType::Confirm => return Ok((ExecutionType::Confirm, Some(statement))),
+          ExecutionType::Transaction if transaction.is_none() => transaction = Some(statement),
+          _ => {},
+        }
+      }
+      Ok(match transaction {
+        Some(statement) if manages_tx => (ExecutionType::Confirm, Some(statement)),
+        Some(statement) => (ExecutionType::Transaction, Some(statement)),
+        None => (ExecutionType::Normal, None),
+      })
+    },
     Err(e) => Err(eyre::Report::new(e)),
   }
 }
 
-fn get_default_execution_type(statement: Statement, confirmed: bool) -> ExecutionType {
-  if confirmed {
-    return ExecutionType::Normal;
-  }
+fn is_transaction_control(statement: &Statement) -> bool {
+  matches!(
+    statement,
+    Statement::StartTransaction { .. }
+      | Statement::Commit { .. }
+      | Statement::Rollback { .. }
+      | Statement::Savepoint { .. }
+      | Statement::ReleaseSavepoint { .. }
+  )
+}
+
+fn is_read_only_statement(statement: &Statement) -> bool {
   match statement {
-    Statement::AlterIndex { .. }
-    | Statement::AlterView { .. }
-    | Statement::AlterRole { .. }
-    | Statement::AlterTable { .. }
-    | Statement::Drop { .. }
-    | Statement::Truncate { .. } => ExecutionType::Confirm,
-    Statement::Delete(_) | Statement::Update { .. } => ExecutionType::Transaction,
-    Statement::Explain { statement, analyze, .. }
-      if analyze
-        && matches!(
-          statement.as_ref(),
-          Statement::AlterIndex { .. }
-            | Statement::AlterView { .. }
-            | Statement::AlterRole { .. }
-            | Statement::AlterTable { .. }
-            | Statement::Drop { .. }
-            | Statement::Truncate { .. },
-        ) =>
-    {
-      ExecutionType::Confirm
+    Statement::Query(query) => is_read_only_query(query),
+    Statement::ExplainTable { .. }
+    | Statement::ShowFunctions { .. }
+    | Statement::ShowVariable { .. }
+    | Statement::ShowStatus { .. }
+    | Statement::ShowVariables { .. }
+    | Statement::ShowCreate { .. }
+    | Statement::ShowColumns { .. }
+    | Statement::ShowDatabases { .. }
+    | Statement::ShowSchemas { .. }
+    | Statement::ShowObjects(_)
+    | Statement::ShowTables { .. }
+    | Statement::ShowViews { .. }
+    | Statement::ShowCollation { .. } => true,
+    // transactions can't change anything on their own, but one that's
+    // started READ WRITE would let the statements in it
+    Statement::StartTransaction { modes, .. } => {
+      !modes.contains(&TransactionMode::AccessMode(TransactionAccessMode::ReadWrite))
     },
-    Statement::Explain { statement, analyze, .. }
-      if analyze && matches!(statement.as_ref(), Statement::Delete(_) | Statement::Update { .. }) =>
-    {
-      ExecutionType::Transaction
+    Statement::Commit { .. }
+    | Statement::Rollback { .. }
+    | Statement::Savepoint { .. }
+    | Statement::ReleaseSavepoint { .. } => true,
+    // explain analyze actually runs the statement
+    Statement::Explain { statement: explained, .. } => {
+      !policy::explain_analyzes(statement) || is_read_only_statement(explained)
     },
-    Statement::Explain { .. } => ExecutionType::Normal,
-    _ => ExecutionType::Normal,
+    _ => false,
+  }
+}
+
+// a query can still write with a data-modifying CTE or SELECT INTO
+fn is_read_only_query(query: &Query) -> bool {
+  query.with.as_ref().is_none_or(|with| with.cte_tables.iter().all(|cte| is_read_only_query(&cte.query)))
+    && is_read_only_set_expr(&query.body)
+}
+
+fn is_read_only_set_expr(body: &SetExpr) -> bool {
+  match body {
+    SetExpr::Select(select) => select.into.is_none(),
+    SetExpr::Query(query) => is_read_only_query(query),
+    SetExpr::SetOperation { left, right, .. } => is_read_only_set_expr(left) && is_read_only_set_expr(right),
+    SetExpr::Insert(_) | SetExpr::Update(_) | SetExpr::Delete(_) => false,
+    SetExpr::Values(_) | SetExpr::Table(_) => true,
+  }
+}
+
+fn get_default_execution_type(statement: &Statement, confirmed: bool, policy: &ExecutionPolicy) -> ExecutionType {
+  if confirmed {
+    return ExecutionType::Normal;
   }
+  policy::statement_kind(statement).map_or(ExecutionType::Normal, |kind| policy.execution_type(kind))
 }
 
 pub fn statement_type_string(statement: Option<Statement>) -> String {
@@ -1,5 +1,4 @@
 use std::{
-  fmt::Write,
   io::{self, Write as _},
   str::FromStr,
   string::String,
@@ -11,20 +10,29 @@ use color_eyre::eyre::{self, Result};
 use futures::stream::StreamExt;
 use sqlparser::ast::Statement;
 use sqlx::{
-  Column, Either, MySqlConnection, Row, ValueRef,
-  mysql::{MySql, MySqlConnectOptions, MySqlPoolOptions},
+  Column, Either, Executor, MySqlConnection, Row, ValueRef,
+  mysql::{MySql, MySqlConnectOptions, MySqlPoolOptions, MySqlRow, MySqlSslMode},
   pool::PoolConnection,
 };
-use tokio::{sync::Mutex, task::JoinHandle};
+use tokio::{
+  sync::{Mutex, OwnedMutexGuard},
+  task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
 
-use super::{Database, DbTaskResult, Driver, Header, Headers, QueryResultsWithMetadata, QueryTask, Rows, Value};
+use super::{
+  Database, DbTaskResult, Driver, Header, Headers, LockWait, Notification, ObjectKind, ParameterizedStatement,
+  QueryOptions, QueryResultsWithMetadata, QueryTask, Rows, ScriptErrorPolicy, ServerSession, TableDetails, TableInfo,
+  Value, activity, ddl, edit, schema, stream::PageSender,
+};
+use crate::cli::{SslMode, TlsOptions};
 
 type MySqlTransaction<'a> = sqlx::Transaction<'a, MySql>;
-type TransactionTask<'a> = JoinHandle<(QueryResultsWithMetadata, MySqlTransaction<'a>)>;
+type TransactionTask<'a> = JoinHandle<(Vec<QueryResultsWithMetadata>, MySqlTransaction<'a>)>;
 enum MySqlTask<'a> {
   Query(QueryTask),
   TxStart(TransactionTask<'a>),
-  TxPending(Box<(MySqlTransaction<'a>, QueryResultsWithMetadata)>),
+  TxPending(Box<(MySqlTransaction<'a>, Vec<QueryResultsWithMetadata>)>),
 }
 
 #[derive(Default)]
@@ -33,45 +41,81 @@ pub struct MySqlDriver<'a> {
   task: Option<MySqlTask<'a>>,
   querying_conn: Option<Arc<Mutex<PoolConnection<MySql>>>>,
   querying_pid: Option<String>,
+  // pinned by `begin_session_tx()` for every query until the transaction ends
+  session_conn: Option<Arc<Mutex<PoolConnection<MySql>>>>,
+  // cancelled when the transaction ends, so that results still streaming
+  // from `session_conn` hand it back
+  session_streams: CancellationToken,
 }
 
 #[async_trait(?Send)]
 impl Database for MySqlDriver<'_> {
   async fn init(&mut self, args: crate::cli::Cli) -> Result<()> {
+    let read_only = args.read_only;
     let opts = super::mysql::MySqlDriver::<'_>::build_connection_opts(args)?;
-    let pool = Arc::new(MySqlPoolOptions::new().max_connections(3).connect_with(opts).await?);
+    let pool = Arc::new(
+      MySqlPoolOptions::new()
+        .max_connections(3)
+        .after_connect(move |conn, _| {
+          Box::pin(async move {
+            if read_only {
+              conn.execute(sqlx::raw_sql("SET SESSION TRANSACTION READ ONLY")).await?;
+            }
+            Ok(())
+          })
+        })
+        .connect_with(opts)
+        .await?,
+    );
     self.pool = Some(pool);
     Ok(())
   }
 
-  // since it's possible for raw_sql to execute multiple queries in a single string,
-  // we only execute the first one and then drop the rest.
-  async fn start_query(&mut self, query: String, bypass_parser: bool) -> Result<()> {
-    let (first_query, statement_type) = match bypass_parser {
-      true => (query, None),
-      false => {
-        let (first, stmt) = super::get_first_query(query, Driver::MySql)?;
-        (first, Some(stmt))
-      },
-    };
+  // raw_sql would happily run every statement in a single string, but we
+  // run them one at a time so that each one gets its own results. rows of
+  // the last statement are streamed a page at a time, unless it locks them.
+  async fn start_query(&mut self, query: String, bypass_parser: bool, options: QueryOptions) -> Result<()> {
+    let mut queries = super::get_script(query, bypass_parser, Driver::MySql, options.max_rows)?;
     let pool = self.pool.clone().unwrap();
-    self.querying_conn = Some(Arc::new(Mutex::new(pool.acquire().await?)));
+    let session_streams = self.session_conn.is_some().then(|| self.session_streams.clone());
+    self.querying_conn = Some(match self.session_conn.clone() {
+      Some(conn) => conn,
+      None => Arc::new(Mutex::new(pool.acquire().await?)),
+    });
     let conn = self.querying_conn.clone().unwrap();
     let conn_for_task = conn.clone();
     let pid = sqlx::raw_sql("SELECT CONNECTION_ID()").fetch_one(conn.lock().await.as_mut()).await?.get::<u64, _>(0);
     log::info!("Starting query with PID {}", pid.clone());
     self.querying_pid = Some(pid.to_string());
     self.task = Some(MySqlTask::Query(tokio::spawn(async move {
-      let results = query_with_conn(conn_for_task.lock().await.as_mut(), first_query.clone()).await;
-      match results {
-        Ok(ref rows) => {
-          log::info!("{:?} rows, {:?} affected", rows.rows.len(), rows.rows_affected);
-        },
-        Err(ref e) => {
-          log::error!("{e:?}");
-        },
-      };
-      QueryResultsWithMetadata { results, statement_type: statement_type.clone() }
+      let mut conn = conn_for_task.lock_owned().await;
+      let mut results = vec![];
+      let last = queries.pop();
+      for (query, statement_type) in queries {
+        let rows = query_with_conn(conn.as_mut(), query).await;
+        log_rows(&rows);
+        let failed = rows.is_err();
+        results.push(QueryResultsWithMetadata { results: rows, statement_type, stream: None });
+        if failed && options.on_error == ScriptErrorPolicy::Stop {
+          return results;
+        }
+      }
+      if let Some((query, statement_type)) = last {
+        let (rows, stream) = if statement_type.as_ref().is_some_and(super::is_streamable_query) {
+          let (stream, pages) = super::stream::row_stream(options.page_size, options.row_cap);
+          let pages = match session_streams {
+            Some(token) => pages.cancel_on(token),
+            None => pages,
+          };
+          tokio::spawn(stream_query(conn, query, pages));
+          stream.first_page().await
+        } else {
+          (query_with_conn(conn.as_mut(), query).await, None)
+        };
+        log_rows(&rows);
+        results.push(QueryResultsWithMetadata { results: rows, statement_type, stream });
+      }
+      results
     })));
     Ok(())
   }
@@ -85,7 +129,10 @@ impl Database for MySqlDriver<'_> {
           _ => {},
         };
         if let Some(pid) = self.querying_pid.take() {
-          let result = sqlx::raw_sql(&format!("KILL {pid}")).execute(&*self.pool.clone().unwrap()).await;
+          // killing the transaction's connection would roll it back behind
+          // the session's back, so only its statement is stopped
+          let statement = if self.session_conn.is_some() { "KILL QUERY" } else { "KILL" };
+          let result = kill(&self.pool.clone().unwrap(), statement, &pid).await;
           let msg = match result {
             Ok(_) => "Successfully killed".to_string(),
             Err(e) => format!("Failed to kill: {e:?}"),
@@ -120,23 +167,17 @@ impl Database for MySqlDriver<'_> {
         if !handle.is_finished() {
           (DbTaskResult::Pending, Some(MySqlTask::TxStart(handle)))
         } else {
-          let (result, tx) = handle.await?;
-          let rows_affected = match &result.results {
-            Ok(rows) => rows.rows_affected,
-            _ => None,
-          };
-          match result {
-            // if tx failed to start, return the error immediately
-            QueryResultsWithMetadata { results: Err(e), statement_type } => {
-              log::error!("Transaction didn't start: {e:?}");
-              self.querying_conn = None;
-              self.querying_pid = None;
-              (DbTaskResult::Finished(QueryResultsWithMetadata { results: Err(e), statement_type }), None)
-            },
-            _ => (
-              DbTaskResult::ConfirmTx(rows_affected, result.statement_type.clone()),
-              Some(MySqlTask::TxPending(Box::new((tx, result)))),
-            ),
+          let (results, tx) = handle.await?;
+          // if a statement failed, the transaction is rolled back as it's
+          // dropped, and the results are returned immediately
+          if results.iter().any(|result| result.results.is_err()) {
+            log::error!("Transaction failed, rolling back");
+            self.querying_conn = None;
+            self.querying_pid = None;
+            (DbTaskResult::Finished(results), None)
+          } else {
+            // mysql can't return the rows a statement changes, so there's no preview
+            (super::confirm_tx(&results, vec![]), Some(MySqlTask::TxPending(Box::new((tx, results)))))
           }
         }
       },
@@ -146,50 +187,62 @@ impl Database for MySqlDriver<'_> {
     Ok(task_result)
   }
 
-  async fn start_tx(&mut self, query: String) -> Result<()> {
-    let (first_query, statement_type) = super::get_first_query(query, Driver::MySql)?;
+  async fn start_tx(&mut self, query: String, options: QueryOptions) -> Result<()> {
+    let queries = super::get_queries(query, Driver::MySql)?;
+    let mut tx = self.pool.clone().unwrap().begin().await?;
+    let pid = sqlx::raw_sql("SELECT CONNECTION_ID()").fetch_one(&mut *tx).await?.get::<u64, _>(0);
+    log::info!("Starting transaction with PID {}", pid.clone());
+    self.querying_pid = Some(pid.to_string());
+    self.task = Some(MySqlTask::TxStart(tokio::spawn(async move {
+      let mut results = vec![];
+      for (query, statement_type) in queries {
+        let (result, next_tx) = tx_statement(tx, query, statement_type).await;
+        tx = next_tx;
+        let failed = result.results.is_err();
+        results.push(result);
+        if failed {
+          break;
+        }
+      }
+      (results, tx)
+    })));
+    Ok(())
+  }
+
+  async fn start_tx_with_params(&mut self, statements: Vec<ParameterizedStatement>) -> Result<()> {
+    let statement_type = statements
+      .first()
+      .and_then(|statement| super::get_first_query(statement.sql.clone(), Driver::MySql).ok())
+      .map(|(_, statement_type)| statement_type);
     let mut tx = self.pool.clone().unwrap().begin().await?;
     let pid = sqlx::raw_sql("SELECT CONNECTION_ID()").fetch_one(&mut *tx).await?.get::<u64, _>(0);
     log::info!("Starting transaction with PID {}", pid.clone());
     self.querying_pid = Some(pid.to_string());
     self.task = Some(MySqlTask::TxStart(tokio::spawn(async move {
-      let (results, tx) = query_with_tx(tx, &first_query).await;
+      let (results, tx) = execute_with_params(tx, &statements).await;
       match results {
-        Ok(Either::Left(rows_affected)) => {
-          log::info!("{rows_affected:?} rows affected");
-          (
-            QueryResultsWithMetadata {
-              results: Ok(Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) }),
-              statement_type: Some(statement_type),
-            },
-            tx,
-          )
-        },
-        Ok(Either::Right(rows)) => {
-          log::info!("{:?} rows affected", rows.rows_affected);
-          (QueryResultsWithMetadata { results: Ok(rows), statement_type: Some(statement_type) }, tx)
-        },
-        Err(e) => {
-          log::error!("{e:?}");
-          (QueryResultsWithMetadata { results: Err(e), statement_type: Some(statement_type) }, tx)
-        },
+        Ok(rows_affected) => log::info!("{rows_affected:?} rows affected"),
+        Err(ref e) => log::error!("{e:?}"),
       }
+      let results =
+        results.map(|rows_affected| Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) });
+      (vec![QueryResultsWithMetadata { results, statement_type, stream: None }], tx)
     })));
     Ok(())
   }
 
-  async fn commit_tx(&mut self) -> Result<Option<QueryResultsWithMetadata>> {
+  async fn commit_tx(&mut self) -> Result<Vec<QueryResultsWithMetadata>> {
     if !matches!(self.task, Some(MySqlTask::TxPending(_))) {
-      Ok(None)
+      Ok(vec![])
     } else {
       match self.task.take() {
         Some(MySqlTask::TxPending(b)) => {
           b.0.commit().await?;
           self.querying_conn = None;
           self.querying_pid = None;
-          Ok(Some(b.1))
+          Ok(b.1)
         },
-        _ => Ok(None),
+        _ => Ok(vec![]),
       }
     }
   }
@@ -203,18 +256,193 @@ impl Database for MySqlDriver<'_> {
     Ok(())
   }
 
+  async fn begin_session_tx(&mut self) -> Result<()> {
+    if self.session_conn.is_some() {
+      return Err(eyre::Report::msg("A transaction is already in progress"));
+    }
+    let mut conn = self.pool.clone().unwrap().acquire().await?;
+    conn.as_mut().execute(sqlx::raw_sql("BEGIN")).await?;
+    self.session_conn = Some(Arc::new(Mutex::new(conn)));
+    self.session_streams = CancellationToken::new();
+    Ok(())
+  }
+
+  async fn commit_session_tx(&mut self) -> Result<()> {
+    self.end_session_tx("COMMIT").await
+  }
+
+  async fn rollback_session_tx(&mut self) -> Result<()> {
+    self.end_session_tx("ROLLBACK").await
+  }
+
+  async fn close(&mut self) -> Result<()> {
+    self.rollback_tx().await?;
+    self.abort_query().await?;
+    self.rollback_session_tx().await?;
+    if let Some(pool) = self.pool.take() {
+      pool.close().await;
+    }
+    Ok(())
+  }
+
+  async fn listen(&mut self, channel: &str) -> Result<()> {
+    Err(eyre::Report::msg("LISTEN/NOTIFY is not supported for MySQL"))
+  }
+
+  async fn unlisten_all(&mut self) -> Result<()> {
+    Ok(())
+  }
+
+  fn take_notifications(&mut self) -> Vec<Notification> {
+    vec![]
+  }
+
+  async fn load_activity(&self) -> Result<Vec<ServerSession>> {
+    let rows = query_with_pool(
+      self.pool.clone().unwrap(),
+      "select cast(id as char), user, db, host, command, state, time, info
+      from information_schema.processlist
+      where id <> connection_id()
+      order by command = 'Sleep', time desc"
+        .to_owned(),
+    )
+    .await?;
+    Ok(activity::sessions_from_rows(rows))
+  }
+
+  async fn cancel_session(&self, id: &str) -> Result<()> {
+    kill(&self.pool.clone().unwrap(), "KILL QUERY", id).await
+  }
+
+  async fn terminate_session(&self, id: &str) -> Result<()> {
+    kill(&self.pool.clone().unwrap(), "KILL", id).await
+  }
+
+  // needs mysql 8, where innodb's lock waits moved to performance_schema.
+  // sessions that only block others get a row for each lock that's waited on
+  async fn load_lock_waits(&self) -> Result<Vec<LockWait>> {
+    let rows = query_with_pool(
+      self.pool.clone().unwrap(),
+      "with waits as (
+        select rt.processlist_id as waiting, bt.processlist_id as blocking,
+          rl.lock_mode as waiting_mode, bl.lock_mode as blocking_mode,
+          concat(rl.object_schema, '.', rl.object_name) as relation
+        from performance_schema.data_lock_waits w
+        join performance_schema.data_locks rl on rl.engine_lock_id = w.requesting_engine_lock_id
+        join performance_schema.data_locks bl on bl.engine_lock_id = w.blocking_engine_lock_id
+        join performance_schema.threads rt on rt.thread_id = w.requesting_thread_id
+        join performance_schema.threads bt on bt.thread_id = w.blocking_thread_id
+      ), sessions as (
+        select waiting as id, group_concat(distinct blocking) as blocked_by,
+          min(waiting_mode) as lock_mode, min(relation) as relation
+        from waits group by waiting
+        union all
+        select distinct blocking, '', blocking_mode, relation
+        from waits where blocking not in (select waiting from waits)
+      )
+      select cast(s.id as char), s.blocked_by, p.command, p.info, s.lock_mode, s.relation, p.time
+      from sessions s
+      left join information_schema.processlist p on p.id = s.id
+      order by s.id"
+        .to_owned(),
+    )
+    .await?;
+    Ok(activity::lock_waits_from_rows(rows))
+  }
+
   async fn load_menu(&self) -> Result<Rows> {
     query_with_pool(
       self.pool.clone().unwrap(),
-      "select table_schema as table_schema, table_name as table_name
+      "select table_schema, table_name, case table_type when 'VIEW' then 'view' else 'table' end
       from information_schema.tables
       where table_schema not in ('mysql', 'information_schema', 'performance_schema', 'sys')
-      order by table_schema, table_name asc"
+      union all
+      select routine_schema, routine_name, lower(routine_type)
+      from information_schema.routines
+      where routine_schema not in ('mysql', 'information_schema', 'performance_schema', 'sys')
+      union all
+      select trigger_schema, trigger_name, 'trigger'
+      from information_schema.triggers
+      where trigger_schema not in ('mysql', 'information_schema', 'performance_schema', 'sys')
+      order by 1, 2"
         .to_owned(),
     )
     .await
   }
 
+  async fn load_tables(&self) -> Result<Vec<TableInfo>> {
+    let rows = query_with_pool(
+      self.pool.clone().unwrap(),
+      "select table_schema, table_name,
+        case table_type when 'VIEW' then 'view' else 'table' end,
+        nullif(table_comment, '')
+      from information_schema.tables
+      where table_schema not in ('mysql', 'information_schema', 'performance_schema', 'sys')
+      order by table_schema, table_name"
+        .to_owned(),
+    )
+    .await?;
+    Ok(schema::tables_from_rows(rows))
+  }
+
+  async fn describe_table(&self, schema: &str, table: &str) -> Result<TableDetails> {
+    let pool = self.pool.clone().unwrap();
+    let relation = format!("table_schema = {} and table_name = {}", schema::literal(schema), schema::literal(table));
+    let columns = query_with_pool(
+      pool.clone(),
+      format!(
+        "select column_name, column_type, is_nullable = 'YES', column_default, nullif(column_comment, '')
+        from information_schema.columns
+        where {relation}
+        order by ordinal_position"
+      ),
+    )
+    .await?;
+    let foreign_keys = query_with_pool(
+      pool.clone(),
+      format!(
+        "select constraint_name, column_name, referenced_table_schema, referenced_table_name, referenced_column_name
+        from information_schema.key_column_usage
+        where {relation} and referenced_table_name is not null
+        order by constraint_name, ordinal_position"
+      ),
+    )
+    .await?;
+    let indexes = query_with_pool(
+      pool,
+      format!(
+        "select index_name, coalesce(column_name, expression), non_unique = 0, index_name = 'PRIMARY'
+        from information_schema.statistics
+        where {relation}
+        order by index_name, seq_in_index"
+      ),
+    )
+    .await?;
+    Ok(TableDetails::from_indexes(
+      schema::columns_from_rows(columns),
+      schema::foreign_keys_from_rows(foreign_keys),
+      schema::indexes_from_rows(indexes),
+    ))
+  }
+
+  // the statement is in the second column for tables and views, and in the
+  // third for routines and triggers
+  async fn load_ddl(&self, kind: ObjectKind, schema: &str, name: &str) -> Result<String> {
+    let (statement, column) = match kind {
+      ObjectKind::Table => ("TABLE", 1),
+      ObjectKind::View => ("VIEW", 1),
+      ObjectKind::Function => ("FUNCTION", 2),
+      ObjectKind::Procedure => ("PROCEDURE", 2),
+      ObjectKind::Trigger => ("TRIGGER", 2),
+      kind => return Err(eyre::Report::msg(format!("MySQL does not support {}", kind.plural()))),
+    };
+    let quote = |s: &str| format!("`{}`", s.replace('`', "``"));
+    let rows =
+      query_with_pool(self.pool.clone().unwrap(), format!("SHOW CREATE {statement} {}.{}", quote(schema), quote(name)))
+        .await?;
+    Ok(ddl::script(rows.rows.iter().filter_map(|row| row.get(column).and_then(schema::text))))
+  }
+
   fn preview_rows_query(&self, schema: &str, table: &str) -> String {
     format!("select * from `{schema}`.`{table}` limit 100")
   }
@@ -252,18 +480,79 @@ impl Database for MySqlDriver<'_> {
   fn preview_policies_query(&self, schema: &str, table: &str) -> String {
     "select 'MySQL does not support row-level security policies' as message".to_owned()
   }
+
+  fn preview_definition_query(&self, schema: &str, view: &str) -> String {
+    format!(
+      "select view_definition, check_option, is_updatable
+        from information_schema.views
+        where table_schema = '{schema}' and table_name = '{view}'"
+    )
+  }
+
+  fn preview_source_query(&self, schema: &str, function: &str) -> String {
+    format!(
+      "select routine_type, dtd_identifier as returns, routine_definition
+        from information_schema.routines
+        where routine_schema = '{schema}' and routine_name = '{function}'"
+    )
+  }
+
+  fn preview_sequence_query(&self, schema: &str, sequence: &str) -> String {
+    "select 'MySQL does not support sequences' as message".to_owned()
+  }
+
+  fn preview_trigger_query(&self, schema: &str, trigger: &str) -> String {
+    format!(
+      "select event_object_table as table_name, action_timing, event_manipulation, action_statement
+        from information_schema.triggers
+        where trigger_schema = '{schema}' and trigger_name = '{trigger}'"
+    )
+  }
+
+  fn preview_enum_query(&self, schema: &str, name: &str) -> String {
+    "select 'MySQL does not support enum types' as message".to_owned()
+  }
 }
 
 impl MySqlDriver<'_> {
   pub fn new() -> Self {
-    Self { pool: None, task: None, querying_conn: None, querying_pid: None }
+    Self {
+      pool: None,
+      task: None,
+      querying_conn: None,
+      querying_pid: None,
+      session_conn: None,
+      session_streams: CancellationToken::new(),
+    }
+  }
+
+  // if the transaction can't be ended, the connection is closed instead of
+  // going back to the pool with the transaction still open
+  async fn end_session_tx(&mut self, statement: &str) -> Result<()> {
+    let Some(conn) = self.session_conn.take() else {
+      return Ok(());
+    };
+    self.session_streams.cancel();
+    let mut conn = conn.lock_owned().await;
+    let result = conn.as_mut().execute(sqlx::raw_sql(statement)).await;
+    if result.is_err() {
+      conn.close_on_drop();
+    }
+    result?;
+    Ok(())
   }
 
   fn build_connection_opts(
     args: crate::cli::Cli,
   ) -> Result<<<sqlx::MySql as sqlx::Database>::Connection as sqlx::Connection>::Options> {
     match args.connection_url {
-      Some(url) => Ok(MySqlConnectOptions::from_str(&url)?),
+      Some(url) => {
+        let mut opts = MySqlConnectOptions::from_str(&url)?;
+        if let Some(socket) = &args.socket {
+          opts = opts.socket(socket);
+        }
+        Ok(with_tls(opts, &args.tls))
+      },
       None => {
         let mut opts = MySqlConnectOptions::new();
 
@@ -292,8 +581,10 @@ impl MySqlDriver<'_> {
           }
         }
 
-        // Host
-        if let Some(host) = args.host {
+        // Host, unless connecting through a socket
+        if let Some(socket) = &args.socket {
+          opts = opts.socket(socket);
+        } else if let Some(host) = args.host {
           opts = opts.host(&host);
         } else {
           let mut host = String::new();
@@ -309,7 +600,7 @@ impl MySqlDriver<'_> {
         // Port
         if let Some(port) = args.port {
           opts = opts.port(port);
-        } else {
+        } else if args.socket.is_none() {
           let mut port = String::new();
           print!("port (ex. 3306): ");
           io::stdout().flush()?;
@@ -334,16 +625,64 @@ impl MySqlDriver<'_> {
           }
         }
 
-        Ok(opts)
+        Ok(with_tls(opts, &args.tls))
       },
     }
   }
 }
 
+// settings given explicitly win over any in the connection url
+fn with_tls(mut opts: MySqlConnectOptions, tls: &TlsOptions) -> MySqlConnectOptions {
+  if let Some(mode) = tls.ssl_mode {
+    opts = opts.ssl_mode(match mode {
+      SslMode::Disable => MySqlSslMode::Disabled,
+      SslMode::Prefer => MySqlSslMode::Preferred,
+      SslMode::Require => MySqlSslMode::Required,
+      SslMode::VerifyCa => MySqlSslMode::VerifyCa,
+      SslMode::VerifyFull => MySqlSslMode::VerifyIdentity,
+    });
+  }
+  if let Some(root_cert) = &tls.ssl_root_cert {
+    opts = opts.ssl_ca(root_cert);
+  }
+  if let Some(cert) = &tls.ssl_cert {
+    opts = opts.ssl_client_cert(cert);
+  }
+  if let Some(key) = &tls.ssl_key {
+    opts = opts.ssl_client_key(key);
+  }
+  opts
+}
+
+// KILL can't take a parameter, so the id is checked to be a number first
+async fn kill(pool: &sqlx::Pool<MySql>, statement: &str, id: &str) -> Result<()> {
+  let id = id.parse::<u64>()?;
+  sqlx::raw_sql(&format!("{statement} {id}")).execute(pool).await?;
+  Ok(())
+}
+
 async fn query_with_pool(pool: Arc<sqlx::Pool<MySql>>, query: String) -> Result<Rows> {
   query_with_stream(&*pool.clone(), &query).await
 }
 
+fn log_rows(rows: &Result<Rows>) {
+  match rows {
+    Ok(rows) => {
+      log::info!("{:?} rows, {:?} affected", rows.rows.len(), rows.rows_affected);
+    },
+    Err(e) => {
+      log::error!("{e:?}");
+    },
+  };
+}
+
+// mysql only has cursors inside stored programs, so rows are read straight
+// off the socket instead; the ones that haven't been read yet stay on the
+// server until they're asked for.
+async fn stream_query(mut conn: OwnedMutexGuard<PoolConnection<MySql>>, query: String, pages: PageSender) {
+  pages.serve(sqlx::raw_sql(&query).fetch(conn.as_mut()), row_to_vec, get_headers).await;
+}
+
 async fn query_with_conn(conn: &mut MySqlConnection, query: String) -> Result<Rows> {
   query_with_stream(conn, &query).await
 }
@@ -375,6 +714,57 @@ where
   Ok(Rows { rows_affected: query_rows_affected, headers, rows: query_rows })
 }
 
+// runs each statement with its parameters bound as text, adding up the rows
+// they change
+async fn execute_with_params(
+  mut tx: MySqlTransaction<'static>,
+  statements: &[ParameterizedStatement],
+) -> (Result<u64>, MySqlTransaction<'static>) {
+  let mut rows_affected = 0;
+  for statement in statements {
+    let query = statement.params.iter().fold(sqlx::query(&statement.sql), |query, param| query.bind(param.clone()));
+    let result = match query.execute(&mut *tx).await {
+      Ok(result) => edit::expect_one_row(statement, result.rows_affected()),
+      Err(e) => Err(e.into()),
+    };
+    match result {
+      Ok(()) => rows_affected += 1,
+      Err(e) => return (Err(e), tx),
+    }
+  }
+  (Ok(rows_affected), tx)
+}
+
+// runs one statement of a transaction
+async fn tx_statement(
+  tx: MySqlTransaction<'static>,
+  query: String,
+  statement_type: Statement,
+) -> (QueryResultsWithMetadata, MySqlTransaction<'static>) {
+  let (results, tx) = query_with_tx(tx, &query).await;
+  match results {
+    Ok(Either::Left(rows_affected)) => {
+      log::info!("{rows_affected:?} rows affected");
+      (
+        QueryResultsWithMetadata {
+          results: Ok(Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) }),
+          statement_type: Some(statement_type),
+          stream: None,
+        },
+        tx,
+      )
+    },
+    Ok(Either::Right(rows)) => {
+      log::info!("{:?} rows affected", rows.rows_affected);
+      (QueryResultsWithMetadata { results: Ok(rows), statement_type: Some(statement_type), stream: None }, tx)
+    },
+    Err(e) => {
+      log::error!("{e:?}");
+      (QueryResultsWithMetadata { results: Err(e), statement_type: Some(statement_type), stream: None }, tx)
+    },
+  }
+}
+
 async fn query_with_tx<'a>(
   mut tx: MySqlTransaction<'static>,
   query: &str,
@@ -386,8 +776,10 @@ where
   let first_query = super::get_first_query(query.to_string(), Driver::MySql);
   match first_query {
     Ok((first_query, statement_type)) => match statement_type {
-      Statement::Explain { .. } => {
-        let result = query_with_stream(&mut *tx, &first_query).await;
+      // queries in a script that runs in a transaction keep their rows
+      Statement::Explain { .. } | Statement::Query(_) => {
+        let query = super::plan::structured_explain(&statement_type, Driver::MySql).unwrap_or(first_query);
+        let result = query_with_stream(&mut *tx, &query).await;
         match result {
           Ok(result) => (Ok(Either::Right(result)), tx),
           Err(e) => (Err(e), tx),
@@ -413,129 +805,79 @@ fn get_headers(row: &<sqlx::MySql as sqlx::Database>::Row) -> Headers {
     .collect()
 }
 
-fn row_to_vec(row: &<sqlx::MySql as sqlx::Database>::Row) -> Vec<String> {
-  row.columns().iter().map(|col| parse_value(row, col).unwrap().string).collect()
+fn row_to_vec(row: &<sqlx::MySql as sqlx::Database>::Row) -> Vec<Value> {
+  row.columns().iter().map(|col| parse_value(row, col)).collect()
+}
+
+fn decode<'r, T>(row: &'r MySqlRow, col: &<MySql as sqlx::Database>::Column, to_value: impl FnOnce(T) -> Value) -> Value
+where
+  T: sqlx::Decode<'r, MySql> + sqlx::Type<MySql>,
+{
+  row.try_get::<T, usize>(col.ordinal()).map_or(Value::Unknown("_ERROR_".to_string()), to_value)
 }
 
 // parsed based on https://docs.rs/sqlx/latest/sqlx/mysql/types/index.html
-fn parse_value(row: &<MySql as sqlx::Database>::Row, col: &<MySql as sqlx::Database>::Column) -> Option<Value> {
+fn parse_value(row: &<MySql as sqlx::Database>::Row, col: &<MySql as sqlx::Database>::Column) -> Value {
   let col_type = col.type_info().to_string();
   if row.try_get_raw(col.ordinal()).is_ok_and(|v| v.is_null()) {
-    return Some(Value { parse_error: false, string: "NULL".to_string(), is_null: true });
+    return Value::Null;
   }
   match col_type.to_uppercase().as_str() {
-    "TINYINT(1)" | "BOOLEAN" | "BOOL" => Some(row.try_get::<bool, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "TINYINT" => Some(row.try_get::<i8, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "SMALLINT" => Some(row.try_get::<i16, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "INT" => Some(row.try_get::<i32, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "BIGINT" => Some(row.try_get::<i64, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "TINYINT UNSIGNED" => Some(row.try_get::<u8, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "SMALLINT UNSIGNED" => Some(row.try_get::<u16, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "INT UNSIGNED" => Some(row.try_get::<u32, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "BIGINT UNSIGNED" => Some(row.try_get::<u64, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "FLOAT" => Some(row.try_get::<f32, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "DOUBLE" => Some(row.try_get::<f64, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "VARCHAR" | "CHAR" | "TEXT" | "BINARY" => Some(row.try_get::<String, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "VARBINARY" | "BLOB" => Some(row.try_get::<Vec<u8>, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| {
-        if let Ok(s) = String::from_utf8(received.clone()) {
-          Value { parse_error: false, string: s, is_null: false }
-        } else {
-          Value {
-            parse_error: false,
-            string: received.iter().fold(String::new(), |mut output, b| {
-              let _ = write!(output, "{b:02X}");
-              output
-            }),
-            is_null: false,
-          }
-        }
-      },
-    )),
-    "INET4" | "INET6" => Some(row.try_get::<std::net::IpAddr, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "TIME" => Some(row.try_get::<chrono::NaiveTime, usize>(col.ordinal()).map_or(
-      row.try_get::<chrono::TimeDelta, usize>(col.ordinal()).map_or(
-        Value { parse_error: true, string: "_ERROR_".to_string(), is_null: true },
-        |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-      ),
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "DATE" => Some(row.try_get::<chrono::NaiveDate, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "DATETIME" => Some(row.try_get::<chrono::NaiveDateTime, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "TIMESTAMP" => Some(row.try_get::<chrono::DateTime<chrono::Utc>, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "JSON" => Some(row.try_get::<serde_json::Value, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
+    "TINYINT(1)" | "BOOLEAN" | "BOOL" => decode(row, col, Value::Bool),
+    "TINYINT" => decode(row, col, |v: i8| Value::Int(v.into())),
+    "SMALLINT" => decode(row, col, |v: i16| Value::Int(v.into())),
+    "INT" => decode(row, col, |v: i32| Value::Int(v.into())),
+    "BIGINT" => decode(row, col, |v: i64| Value::Int(v.into())),
+    "TINYINT UNSIGNED" => decode(row, col, |v: u8| Value::Int(v.into())),
+    "SMALLINT UNSIGNED" => decode(row, col, |v: u16| Value::Int(v.into())),
+    "INT UNSIGNED" => decode(row, col, |v: u32| Value::Int(v.into())),
+    "BIGINT UNSIGNED" => decode(row, col, |v: u64| Value::Int(v.into())),
+    // go through the f32's own formatting so it doesn't pick up digits it never had
+    "FLOAT" => decode(row, col, |v: f32| Value::Float(v.to_string().parse().unwrap_or(v.into()))),
+    "DOUBLE" => decode(row, col, Value::Float),
+    "DECIMAL" => row
+      .try_get_unchecked::<String, usize>(col.ordinal())
+      .map_or(Value::Unknown("_ERROR_".to_string()), Value::Decimal),
+    "VARCHAR" | "CHAR" | "TEXT" | "BINARY" => decode(row, col, Value::Text),
+    "VARBINARY" | "BLOB" => decode(row, col, |received: Vec<u8>| match String::from_utf8(received) {
+      Ok(s) => Value::Text(s),
+      Err(e) => Value::Bytes(e.into_bytes()),
+    }),
+    "INET4" | "INET6" => decode(row, col, |v: std::net::IpAddr| Value::Text(v.to_string())),
+    "TIME" => row.try_get::<chrono::NaiveTime, usize>(col.ordinal()).map_or_else(
+      |_| decode(row, col, |v: chrono::TimeDelta| Value::Temporal(v.to_string())),
+      |v| Value::Temporal(v.to_string()),
+    ),
+    "DATE" => decode(row, col, |v: chrono::NaiveDate| Value::Temporal(v.to_string())),
+    "DATETIME" => decode(row, col, |v: chrono::NaiveDateTime| Value::Temporal(v.to_string())),
+    "TIMESTAMP" => decode(row, col, |v: chrono::DateTime<chrono::Utc>| Value::Temporal(v.to_string())),
+    "JSON" => decode(row, col, Value::Json),
     "GEOMETRY" => {
       // TODO: would have to resort to geozero to parse WKB
-      Some(Value { parse_error: true, string: "_TODO_".to_owned(), is_null: false })
+      Value::Unknown("_TODO_".to_owned())
     },
     _ => {
       // Try to cast custom or other types to strings
-      Some(row.try_get_unchecked::<String, usize>(col.ordinal()).map_or(
-        Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-        |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-      ))
+      row
+        .try_get_unchecked::<String, usize>(col.ordinal())
+        .map_or(Value::Unknown("_ERROR_".to_string()), Value::Unknown)
     },
   }
 }
 
 #[cfg(test)]
 mod tests {
+  use std::{collections::HashMap, path::PathBuf};
+
+  use clap::Parser;
   use sqlparser::{ast::Statement, dialect::MySqlDialect, parser::ParserError};
+  use sqlx::ConnectOptions;
 
   use super::*;
-  use crate::database::{ExecutionType, ParseError, get_execution_type, get_first_query};
+  use crate::cli::Cli;
+  use crate::database::{
+    ExecutionPolicy, ExecutionType, ParseError, get_execution_type, get_first_query, is_streamable_query,
+  };
 
   #[test]
   fn test_get_first_query() {
@@ -617,6 +959,14 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_is_streamable_query() {
+    let parse = |query: &str| get_first_query(query.to_owned(), Driver::MySql).unwrap().1;
+    assert!(!is_streamable_query(&parse("select * from users for update")));
+    assert!(!is_streamable_query(&parse("select * from users where id = 1 for share")));
+    assert!(is_streamable_query(&parse("select * from users")));
+  }
+
   #[test]
   fn test_execution_type_mysql() {
     let test_cases = vec![
@@ -629,14 +979,66 @@ mod tests {
       ("EXPLAIN ANALYZE DROP TABLE users", ExecutionType::Confirm),
       ("EXPLAIN SELECT * FROM users", ExecutionType::Normal),
       ("EXPLAIN ANALYZE SELECT * FROM users WHERE id = 1", ExecutionType::Normal),
+      ("SELECT 1; SELECT 2", ExecutionType::Normal),
+      ("INSERT INTO users (name) VALUES ('John'); SELECT * FROM users", ExecutionType::Normal),
+      ("SELECT 1; DELETE FROM users WHERE id = 1", ExecutionType::Transaction),
+      ("UPDATE users SET name = 'a' WHERE id = 1; DELETE FROM users WHERE id = 2", ExecutionType::Transaction),
+      ("BEGIN; DELETE FROM users WHERE id = 1; COMMIT", ExecutionType::Confirm),
+      ("DELETE FROM users WHERE id = 1; DROP TABLE users", ExecutionType::Confirm),
+      ("DROP TABLE users; SELECT 1", ExecutionType::Confirm),
     ];
 
     for (query, expected) in test_cases {
       assert_eq!(
-        get_execution_type(query.to_string(), false, Driver::MySql).unwrap().0,
+        get_execution_type(query.to_string(), false, Driver::MySql, false, &ExecutionPolicy::default()).unwrap().0,
         expected,
         "Failed for query: {query}"
       );
     }
   }
+
+  #[test]
+  fn test_build_connection_opts_tls() {
+    let args = Cli::parse_from([
+      "rainfrog",
+      "--url",
+      "mysql://root@localhost:3306/shop?ssl-mode=DISABLED",
+      "--ssl-mode",
+      "verify-full",
+      "--ssl-root-cert",
+      "/etc/ca.pem",
+    ]);
+    let opts = MySqlDriver::build_connection_opts(args).unwrap();
+    assert!(matches!(opts.get_ssl_mode(), MySqlSslMode::VerifyIdentity));
+    let url = opts.to_url_lossy();
+    let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    assert_eq!(params["ssl-ca"], "file: /etc/ca.pem");
+    assert!(!params.contains_key("ssl-cert"));
+  }
+
+  #[test]
+  fn test_build_connection_opts_socket() {
+    // nothing is prompted for, since the host and port aren't needed
+    let args = Cli::parse_from([
+      "rainfrog",
+      "--driver",
+      "mysql",
+      "--username",
+      "app",
+      "--password",
+      "secret",
+      "--database",
+      "app",
+      "--socket",
+      "/var/run/mysqld/mysqld.sock",
+    ]);
+    let opts = MySqlDriver::build_connection_opts(args).unwrap();
+    assert_eq!(opts.get_socket(), Some(&PathBuf::from("/var/run/mysqld/mysqld.sock")));
+    assert_eq!(opts.get_database(), Some("app"));
+
+    let args =
+      Cli::parse_from(["rainfrog", "--url", "mysql://app@localhost/app", "--socket", "/var/run/mysqld/mysqld.sock"]);
+    let opts = MySqlDriver::build_connection_opts(args).unwrap();
+    assert_eq!(opts.get_socket(), Some(&PathBuf::from("/var/run/mysqld/mysqld.sock")));
+  }
 }
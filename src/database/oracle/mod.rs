@@ -1,17 +1,21 @@
 mod connect_options;
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{self, Result};
 use connect_options::OracleConnectOptions;
-use oracle::{Connection, pool::Pool};
+use oracle::{Connection, SqlValue, pool::Pool, sql_type::OracleType};
 use sqlparser::ast::Statement;
 use tokio::task::JoinHandle;
 
 use crate::cli::Driver;
 
-use super::{Database, DbTaskResult, Header, QueryResultsWithMetadata, QueryTask, Rows};
+use super::{
+  Database, DbTaskResult, Header, LockWait, Notification, ObjectKind, ParameterizedStatement, QueryOptions,
+  QueryResultsWithMetadata, QueryTask, Rows, ScriptErrorPolicy, ServerSession, TableDetails, TableInfo, Value, ddl,
+  schema,
+};
 
 struct ConnectionWrapper {
   conn: Connection,
@@ -42,16 +46,20 @@ enum OracleTask {
 pub struct OracleDriver {
   pool: Option<Arc<oracle::pool::Pool>>,
   task: Option<OracleTask>,
+  // pinned by `begin_session_tx()` for every query until the transaction ends
+  session_conn: Option<Arc<Mutex<Connection>>>,
 }
 
 impl OracleDriver {
   pub fn new() -> Self {
-    OracleDriver { pool: None, task: None }
+    OracleDriver { pool: None, task: None, session_conn: None }
   }
 }
 
 #[async_trait(?Send)]
 impl Database for OracleDriver {
+  // oracle can only make single transactions read-only, not a whole session,
+  // so read-only connections are left to the parser
   async fn init(&mut self, args: crate::cli::Cli) -> Result<()> {
     let connection_opts = OracleConnectOptions::build_connection_opts(args)?;
 
@@ -63,33 +71,73 @@ impl Database for OracleDriver {
     Ok(())
   }
 
-  async fn start_query(&mut self, query: String, bypass_parser: bool) -> Result<()> {
-    let (first_query, statement_type) = if bypass_parser {
-      (query, None)
-    } else {
-      let (first, stmt) = super::get_first_query(query, Driver::Oracle)?;
-      (first, Some(stmt))
-    };
+  async fn start_query(&mut self, query: String, bypass_parser: bool, options: QueryOptions) -> Result<()> {
+    let queries = super::get_script(query, bypass_parser, Driver::Oracle, options.max_rows)?;
     let pool = self.pool.clone().unwrap();
 
-    let task = match statement_type {
-      Some(Statement::Query(_)) => OracleTask::Query(tokio::spawn(async move {
-        let results = query_with_pool(&pool, &first_query);
-        QueryResultsWithMetadata { results, statement_type }
-      })),
-      _ => OracleTask::TxStart(tokio::spawn(async move {
+    let task = if let Some(conn) = self.session_conn.clone() {
+      // nothing run in the session's transaction is committed until the
+      // session commits it, so there's nothing to confirm statement by statement
+      OracleTask::Query(tokio::spawn(async move {
+        let conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+        let mut results = vec![];
+        for (query, statement_type) in queries {
+          let rows = match statement_type {
+            Some(Statement::Query(_)) => query_with_conn(&conn, &query, options.row_cap),
+            _ => execute_with_conn(&conn, &query),
+          };
+          let failed = rows.is_err();
+          results.push(QueryResultsWithMetadata { results: rows, statement_type, stream: None });
+          if failed && options.on_error == ScriptErrorPolicy::Stop {
+            break;
+          }
+        }
+        results
+      }))
+    } else if queries.iter().all(|(_, statement_type)| matches!(statement_type, Some(Statement::Query(_)))) {
+      OracleTask::Query(tokio::spawn(async move {
+        let mut results = vec![];
+        for (query, statement_type) in queries {
+          let rows = query_with_pool(&pool, &query, options.row_cap);
+          let failed = rows.is_err();
+          results.push(QueryResultsWithMetadata { results: rows, statement_type, stream: None });
+          if failed && options.on_error == ScriptErrorPolicy::Stop {
+            break;
+          }
+        }
+        results
+      }))
+    } else {
+      // anything other than a select has to be committed explicitly, so the
+      // whole script runs on one connection and is confirmed as one transaction
+      OracleTask::TxStart(tokio::spawn(async move {
         let conn = pool.get()?;
-        let results = execute_with_conn(&conn, &first_query);
-        match results {
-          Ok(ref rows) => {
-            log::info!("{:?} rows, {:?} affected", rows.rows.len(), rows.rows_affected);
-          },
-          Err(ref e) => {
-            log::error!("{e:?}");
-          },
+        let statement_type = if queries.len() == 1 { queries[0].1.clone() } else { None };
+        let mut rows_affected: Option<u64> = None;
+        let mut error = None;
+        for (query, _) in queries {
+          match execute_with_conn(&conn, &query) {
+            Ok(rows) => {
+              log::info!("{:?} rows, {:?} affected", rows.rows.len(), rows.rows_affected);
+              if let Some(n) = rows.rows_affected {
+                rows_affected = Some(rows_affected.unwrap_or_default() + n);
+              }
+            },
+            Err(e) => {
+              log::error!("{e:?}");
+              error.get_or_insert(e);
+              if options.on_error == ScriptErrorPolicy::Stop {
+                break;
+              }
+            },
+          }
+        }
+        let results = match error {
+          Some(e) => Err(e),
+          None => Ok(Rows { headers: Vec::new(), rows: Vec::new(), rows_affected }),
         };
-        Ok((QueryResultsWithMetadata { results, statement_type }, ConnectionWrapper::new(conn)))
-      })),
+        Ok((QueryResultsWithMetadata { results, statement_type, stream: None }, ConnectionWrapper::new(conn)))
+      }))
     };
 
     self.task = Some(task);
@@ -130,7 +178,7 @@ impl Database for OracleDriver {
             _ => None,
           };
           (
-            DbTaskResult::ConfirmTx(rows_affected, result.statement_type.clone()),
+            DbTaskResult::ConfirmTx(rows_affected, result.statement_type.clone(), vec![]),
             Some(OracleTask::TxPending(Box::new((tx, result)))),
           )
         }
@@ -141,11 +189,15 @@ impl Database for OracleDriver {
     Ok(task_result)
   }
 
-  async fn start_tx(&mut self, query: String) -> Result<()> {
-    Self::start_query(self, query, false).await
+  async fn start_tx(&mut self, query: String, options: QueryOptions) -> Result<()> {
+    Self::start_query(self, query, false, options).await
+  }
+
+  async fn start_tx_with_params(&mut self, statements: Vec<ParameterizedStatement>) -> Result<()> {
+    Err(eyre::Report::msg("Editing results is not supported for Oracle yet"))
   }
 
-  async fn commit_tx(&mut self) -> Result<Option<QueryResultsWithMetadata>> {
+  async fn commit_tx(&mut self) -> Result<Vec<QueryResultsWithMetadata>> {
     if let Some(OracleTask::TxPending(b)) = self.task.take() {
       let mut conn = b.0;
       tokio::task::spawn_blocking(move || {
@@ -156,9 +208,9 @@ impl Database for OracleDriver {
         result
       })
       .await??;
-      Ok(Some(b.1))
+      Ok(vec![b.1])
     } else {
-      Ok(None)
+      Ok(vec![])
     }
   }
 
@@ -171,13 +223,110 @@ impl Database for OracleDriver {
     }
   }
 
+  // oracle starts a transaction on its own with the first statement that
+  // changes anything, so there is no BEGIN to run
+  async fn begin_session_tx(&mut self) -> Result<()> {
+    if self.session_conn.is_some() {
+      return Err(eyre::Report::msg("A transaction is already in progress"));
+    }
+    let pool = self.pool.clone().unwrap();
+    let conn = tokio::task::spawn_blocking(move || pool.get()).await??;
+    self.session_conn = Some(Arc::new(Mutex::new(conn)));
+    Ok(())
+  }
+
+  async fn commit_session_tx(&mut self) -> Result<()> {
+    if let Some(conn) = self.session_conn.take() {
+      tokio::task::spawn_blocking(move || conn.lock().unwrap_or_else(|e| e.into_inner()).commit()).await??;
+    }
+    Ok(())
+  }
+
+  async fn rollback_session_tx(&mut self) -> Result<()> {
+    if let Some(conn) = self.session_conn.take() {
+      tokio::task::spawn_blocking(move || conn.lock().unwrap_or_else(|e| e.into_inner()).rollback()).await??;
+    }
+    Ok(())
+  }
+
+  async fn close(&mut self) -> Result<()> {
+    self.rollback_tx().await?;
+    self.abort_query().await?;
+    self.rollback_session_tx().await?;
+    // the pool closes once the last handle to it is dropped
+    self.pool = None;
+    Ok(())
+  }
+
+  async fn listen(&mut self, channel: &str) -> Result<()> {
+    Err(eyre::Report::msg("LISTEN/NOTIFY is not supported for Oracle"))
+  }
+
+  async fn unlisten_all(&mut self) -> Result<()> {
+    Ok(())
+  }
+
+  fn take_notifications(&mut self) -> Vec<Notification> {
+    vec![]
+  }
+
+  async fn load_activity(&self) -> Result<Vec<ServerSession>> {
+    Err(eyre::Report::msg("The activity monitor is not supported for Oracle"))
+  }
+
+  async fn cancel_session(&self, id: &str) -> Result<()> {
+    Err(eyre::Report::msg("Cancelling sessions is not supported for Oracle"))
+  }
+
+  async fn terminate_session(&self, id: &str) -> Result<()> {
+    Err(eyre::Report::msg("Terminating sessions is not supported for Oracle"))
+  }
+
+  async fn load_lock_waits(&self) -> Result<Vec<LockWait>> {
+    Err(eyre::Report::msg("The lock inspector is not supported for Oracle"))
+  }
+
   async fn load_menu(&self) -> Result<Rows> {
     query_with_pool(
       self.pool.as_ref().unwrap(),
-      "select user, table_name from user_tables where tablespace_name is not null order by user, table_name",
+      "select user, table_name, 'table' from user_tables where tablespace_name is not null
+      union all
+      select user, object_name, lower(object_type) from user_objects
+      where object_type in ('VIEW', 'MATERIALIZED VIEW', 'FUNCTION', 'PROCEDURE', 'SEQUENCE', 'TRIGGER')
+      order by 1, 2",
+      usize::MAX,
     )
   }
 
+  async fn load_tables(&self) -> Result<Vec<TableInfo>> {
+    Err(eyre::Report::msg("Loading table metadata is not supported for Oracle yet"))
+  }
+
+  async fn describe_table(&self, schema: &str, table: &str) -> Result<TableDetails> {
+    Err(eyre::Report::msg("Describing tables is not supported for Oracle yet"))
+  }
+
+  async fn load_ddl(&self, kind: ObjectKind, schema: &str, name: &str) -> Result<String> {
+    let object_type = match kind {
+      ObjectKind::Enum => return Err(eyre::Report::msg("Oracle does not support enum types")),
+      // dbms_metadata writes object types with underscores
+      kind => kind.to_string().to_uppercase().replace(' ', "_"),
+    };
+    let rows = query_with_pool(
+      self.pool.as_ref().unwrap(),
+      &format!(
+        "select dbms_metadata.get_ddl('{object_type}', {}, {}) from dual",
+        schema::literal(name),
+        schema::literal(schema)
+      ),
+      usize::MAX,
+    )?;
+    Ok(ddl::script(rows.rows.iter().filter_map(|row| match &row[0] {
+      Value::Null => None,
+      value => Some(value.to_string()),
+    })))
+  }
+
   fn preview_rows_query(&self, schema: &str, table: &str) -> String {
     format!("select * from \"{}\".\"{}\" where rownum <= 100", schema, table)
   }
@@ -197,15 +346,53 @@ impl Database for OracleDriver {
   fn preview_policies_query(&self, schema: &str, table: &str) -> String {
     format!("select * from user_policies where object_name = '{}' and user = '{}'", table, schema)
   }
+
+  fn preview_definition_query(&self, schema: &str, view: &str) -> String {
+    format!(
+      "select dbms_metadata.get_ddl(replace(object_type, ' ', '_'), object_name) as definition from user_objects where object_name = '{}' and object_type in ('VIEW', 'MATERIALIZED VIEW') and user = '{}'",
+      view, schema
+    )
+  }
+
+  fn preview_source_query(&self, schema: &str, function: &str) -> String {
+    format!(
+      "select line, text from user_source where name = '{}' and user = '{}' order by type, line",
+      function, schema
+    )
+  }
+
+  fn preview_sequence_query(&self, schema: &str, sequence: &str) -> String {
+    format!(
+      "select s.last_number, s.* from user_sequences s where sequence_name = '{}' and user = '{}'",
+      sequence, schema
+    )
+  }
+
+  fn preview_trigger_query(&self, schema: &str, trigger: &str) -> String {
+    format!(
+      "select table_name, trigger_type, triggering_event, trigger_body from user_triggers where trigger_name = '{}' and user = '{}'",
+      trigger, schema
+    )
+  }
+
+  fn preview_enum_query(&self, schema: &str, name: &str) -> String {
+    "select 'Oracle does not support enum types' as message from dual".to_owned()
+  }
 }
 
-fn query_with_pool(pool: &Pool, query: &str) -> Result<Rows> {
+fn query_with_pool(pool: &Pool, query: &str, row_cap: usize) -> Result<Rows> {
+  query_with_conn(&pool.get()?, query, row_cap)
+}
+
+// the oracle driver doesn't stream rows in pages, but it still stops
+// reading them once the row cap is reached
+fn query_with_conn(conn: &Connection, query: &str, row_cap: usize) -> Result<Rows> {
   let mut headers = Vec::new();
-  let rows = pool
-    .get()?
+  let rows = conn
     .query(query, &[])
     .map_err(|e| color_eyre::eyre::eyre!("Error executing query: {}", e))?
     .filter_map(|row| row.ok())
+    .take(row_cap)
     .map(|row| {
       if headers.is_empty() {
         headers = get_headers(&row);
@@ -231,8 +418,44 @@ fn get_headers(row: &oracle::Row) -> Vec<Header> {
     .collect()
 }
 
-fn row_to_vec(row: &oracle::Row) -> Vec<String> {
-  row.sql_values().iter().map(|v| v.to_string()).collect()
+fn row_to_vec(row: &oracle::Row) -> Vec<Value> {
+  row.sql_values().iter().map(parse_value).collect()
+}
+
+fn parse_value(value: &SqlValue) -> Value {
+  if value.is_null().unwrap_or(false) {
+    return Value::Null;
+  }
+  let Ok(oracle_type) = value.oracle_type() else {
+    return Value::Unknown(value.to_string());
+  };
+  match oracle_type {
+    OracleType::Varchar2(_) | OracleType::NVarchar2(_) | OracleType::Char(_) | OracleType::NChar(_) => {
+      value.get::<String>().map_or(Value::Unknown("_ERROR_".to_string()), Value::Text)
+    },
+    OracleType::CLOB | OracleType::NCLOB | OracleType::Long => {
+      value.get::<String>().map_or(Value::Unknown("_ERROR_".to_string()), Value::Text)
+    },
+    OracleType::Number(_, 0) | OracleType::Int64 => {
+      value.get::<i64>().map_or_else(|_| Value::Decimal(value.to_string()), |v| Value::Int(v.into()))
+    },
+    OracleType::UInt64 => value.get::<u64>().map_or(Value::Unknown("_ERROR_".to_string()), |v| Value::Int(v.into())),
+    OracleType::Number(..) | OracleType::Float(_) => Value::Decimal(value.to_string()),
+    OracleType::BinaryFloat | OracleType::BinaryDouble => {
+      value.get::<f64>().map_or(Value::Unknown("_ERROR_".to_string()), Value::Float)
+    },
+    OracleType::Boolean => value.get::<bool>().map_or(Value::Unknown("_ERROR_".to_string()), Value::Bool),
+    OracleType::Raw(_) | OracleType::BLOB | OracleType::LongRaw => {
+      value.get::<Vec<u8>>().map_or(Value::Unknown("_ERROR_".to_string()), Value::Bytes)
+    },
+    OracleType::Date
+    | OracleType::Timestamp(_)
+    | OracleType::TimestampTZ(_)
+    | OracleType::TimestampLTZ(_)
+    | OracleType::IntervalDS(..)
+    | OracleType::IntervalYM(_) => Value::Temporal(value.to_string()),
+    _ => Value::Unknown(value.to_string()),
+  }
 }
 
 #[cfg(test)]
@@ -240,7 +463,7 @@ mod tests {
   use sqlparser::{ast::Statement, parser::ParserError};
 
   use super::*;
-  use crate::database::{ExecutionType, ParseError, get_execution_type, get_first_query};
+  use crate::database::{ExecutionPolicy, ExecutionType, ParseError, get_execution_type, get_first_query, get_script};
 
   #[test]
   fn test_get_first_query() {
@@ -336,11 +559,20 @@ mod tests {
 
     for (query, expected) in test_cases {
       assert_eq!(
-        get_execution_type(query.to_string(), false, Driver::Oracle).unwrap().0,
+        get_execution_type(query.to_string(), false, Driver::Oracle, false, &ExecutionPolicy::default()).unwrap().0,
         expected,
         "Failed for query: {}",
         query
       );
     }
   }
+
+  #[test]
+  fn test_get_script_max_rows() {
+    let script = get_script("SELECT * FROM users ORDER BY id".to_owned(), false, Driver::Oracle, Some(100)).unwrap();
+    assert_eq!(script[0].0, "SELECT * FROM users ORDER BY id FETCH FIRST 100 ROWS ONLY");
+    let script =
+      get_script("SELECT * FROM users FETCH FIRST 5 ROWS ONLY".to_owned(), false, Driver::Oracle, Some(100)).unwrap();
+    assert_eq!(script[0].0, "SELECT * FROM users FETCH FIRST 5 ROWS ONLY");
+  }
 }
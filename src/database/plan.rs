@@ -0,0 +1,500 @@
+use serde_json::{Map, Value as Json};
+use sqlparser::ast::Statement;
+
+use super::{Rows, Value};
+use crate::cli::Driver;
+
+// estimates that are this many times too high or too low are flagged
+const MISESTIMATE_FACTOR: f64 = 10.0;
+
+type Object = Map<String, Json>;
+
+/// A query plan read from what EXPLAIN returned.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+  pub root: PlanNode,
+  /// Set when the query was run, so actual rows and times are known.
+  pub analyzed: bool,
+  pub planning_time: Option<f64>,
+  pub execution_time: Option<f64>,
+}
+
+/// A step of a plan. Costs are in the database's own units and include the
+/// cost of the children. Rows are per loop, while the time covers every loop,
+/// in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanNode {
+  pub label: String,
+  /// Conditions, keys and the like.
+  pub details: Vec<String>,
+  pub startup_cost: Option<f64>,
+  pub total_cost: Option<f64>,
+  pub plan_rows: Option<f64>,
+  pub actual_rows: Option<f64>,
+  pub loops: Option<f64>,
+  pub actual_time: Option<f64>,
+  pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+  /// The cost of the node, leaving out the cost of its children.
+  pub fn self_cost(&self) -> Option<f64> {
+    let children = self.children.iter().filter_map(|child| child.total_cost).sum::<f64>();
+    Some((self.total_cost? - children).max(0.0))
+  }
+
+  /// The time spent in the node, leaving out the time spent in its children.
+  pub fn self_time(&self) -> Option<f64> {
+    let children = self.children.iter().filter_map(|child| child.actual_time).sum::<f64>();
+    Some((self.actual_time? - children).max(0.0))
+  }
+
+  /// How many times more rows the node returned than were estimated, when
+  /// the estimate is off by enough to matter. Below 1 when there were fewer.
+  pub fn misestimate(&self) -> Option<f64> {
+    if self.loops == Some(0.0) {
+      return None;
+    }
+    let factor = self.actual_rows?.max(1.0) / self.plan_rows?.max(1.0);
+    (factor >= MISESTIMATE_FACTOR || factor <= 1.0 / MISESTIMATE_FACTOR).then_some(factor)
+  }
+}
+
+impl Plan {
+  /// The share of the whole plan spent in the node itself, by time when the
+  /// query was run and by cost otherwise.
+  pub fn share(&self, node: &PlanNode) -> Option<f64> {
+    let (own, total) = match self.analyzed {
+      true => (node.self_time()?, self.root.actual_time?),
+      false => (node.self_cost()?, self.root.total_cost?),
+    };
+    (total > 0.0).then(|| own / total)
+  }
+}
+
+/// Rewrites an EXPLAIN that doesn't pick a format of its own so that it
+/// returns a plan `parse_plan()` can read. sqlite is left alone, since its
+/// EXPLAIN QUERY PLAN can already be read and plain EXPLAIN lists bytecode.
+pub fn structured_explain(statement: &Statement, driver: Driver) -> Option<String> {
+  let Statement::Explain {
+    analyze, verbose, query_plan: false, estimate: false, statement, format: None, options, ..
+  } = statement
+  else {
+    return None;
+  };
+  match driver {
+    Driver::Postgres => {
+      let options = options.clone().unwrap_or_default();
+      if options.iter().any(|option| option.name.value.eq_ignore_ascii_case("format")) {
+        return None;
+      }
+      let mut all = vec![];
+      if *analyze {
+        all.push("ANALYZE".to_owned());
+      }
+      if *verbose {
+        all.push("VERBOSE".to_owned());
+      }
+      all.extend(options.iter().map(ToString::to_string));
+      all.push("FORMAT JSON".to_owned());
+      Some(format!("EXPLAIN ({}) {statement}", all.join(", ")))
+    },
+    // EXPLAIN ANALYZE only has a tree format
+    Driver::MySql if !analyze && options.is_none() => Some(format!("EXPLAIN FORMAT=JSON {statement}")),
+    _ => None,
+  }
+}
+
+/// Reads the plan out of the rows EXPLAIN returned: postgres and MySQL plans
+/// in JSON, or the rows of sqlite's EXPLAIN QUERY PLAN. Anything else is
+/// `None`, and is better shown as it is.
+pub fn parse_plan(rows: &Rows) -> Option<Plan> {
+  let names = rows.headers.iter().map(|header| header.name.as_str()).collect::<Vec<_>>();
+  if names == ["id", "parent", "notused", "detail"] {
+    return sqlite_plan(rows);
+  }
+  let [row] = rows.rows.as_slice() else {
+    return None;
+  };
+  let json = match row.as_slice() {
+    [Value::Json(json)] => json.clone(),
+    [Value::Text(text)] => serde_json::from_str(text).ok()?,
+    _ => return None,
+  };
+  match &json {
+    Json::Array(explained) => postgres_plan(explained.first()?.as_object()?),
+    Json::Object(explained) => mysql_plan(explained),
+    _ => None,
+  }
+}
+
+// mysql writes its numbers as strings
+fn number(value: Option<&Json>) -> Option<f64> {
+  match value? {
+    Json::Number(n) => n.as_f64(),
+    Json::String(s) => s.parse().ok(),
+    _ => None,
+  }
+}
+
+fn text<'a>(object: &'a Object, key: &str) -> Option<&'a str> {
+  object.get(key).and_then(Json::as_str)
+}
+
+fn postgres_plan(explained: &Object) -> Option<Plan> {
+  let root = postgres_node(explained.get("Plan")?.as_object()?);
+  Some(Plan {
+    analyzed: root.actual_time.is_some(),
+    planning_time: number(explained.get("Planning Time")),
+    execution_time: number(explained.get("Execution Time")),
+    root,
+  })
+}
+
+// labels follow what EXPLAIN prints in its text format
+fn postgres_node(node: &Object) -> PlanNode {
+  let node_type = text(node, "Node Type").unwrap_or("Unknown");
+  let mut label = match (node_type, text(node, "Strategy")) {
+    ("Aggregate", Some("Hashed")) => "HashAggregate".to_owned(),
+    ("Aggregate", Some("Sorted")) => "GroupAggregate".to_owned(),
+    ("Aggregate", Some("Mixed")) => "MixedAggregate".to_owned(),
+    _ => node_type.to_owned(),
+  };
+  if let Some(join_type) = text(node, "Join Type").filter(|join_type| *join_type != "Inner") {
+    label = format!("{} {join_type} Join", label.trim_end_matches(" Join"));
+  }
+  if let Some(index) = text(node, "Index Name") {
+    label.push_str(&format!(" using {index}"));
+  }
+  if let Some(relation) = text(node, "Relation Name").or(text(node, "CTE Name")).or(text(node, "Function Name")) {
+    label.push_str(&format!(" on {relation}"));
+    if let Some(alias) = text(node, "Alias").filter(|alias| *alias != relation) {
+      label.push_str(&format!(" {alias}"));
+    }
+  }
+  if let Some(subplan) = text(node, "Subplan Name") {
+    label = format!("{label} ({subplan})");
+  }
+
+  let mut details = vec![];
+  for key in ["Index Cond", "Recheck Cond", "Hash Cond", "Merge Cond", "Join Filter", "Filter"] {
+    if let Some(condition) = text(node, key) {
+      details.push(format!("{key}: {condition}"));
+    }
+  }
+  for key in ["Sort Key", "Group Key"] {
+    if let Some(Json::Array(keys)) = node.get(key) {
+      let keys = keys.iter().filter_map(Json::as_str).collect::<Vec<_>>();
+      details.push(format!("{key}: {}", keys.join(", ")));
+    }
+  }
+  if let Some(removed) = number(node.get("Rows Removed by Filter")) {
+    details.push(format!("Rows Removed by Filter: {removed}"));
+  }
+
+  let loops = number(node.get("Actual Loops"));
+  PlanNode {
+    label,
+    details,
+    startup_cost: number(node.get("Startup Cost")),
+    total_cost: number(node.get("Total Cost")),
+    plan_rows: number(node.get("Plan Rows")),
+    actual_rows: number(node.get("Actual Rows")),
+    loops,
+    // reported per loop
+    actual_time: number(node.get("Actual Total Time")).map(|time| time * loops.unwrap_or(1.0)),
+    children: match node.get("Plans") {
+      Some(Json::Array(plans)) => plans.iter().filter_map(Json::as_object).map(postgres_node).collect(),
+      _ => vec![],
+    },
+  }
+}
+
+fn mysql_plan(explained: &Object) -> Option<Plan> {
+  let root = mysql_query_block(explained.get("query_block")?.as_object()?);
+  Some(Plan { root, analyzed: false, planning_time: None, execution_time: None })
+}
+
+fn mysql_cost(object: &Object, key: &str) -> Option<f64> {
+  number(object.get("cost_info")?.get(key))
+}
+
+// steps without a cost of their own cost as much as their children
+fn with_children_cost(mut node: PlanNode) -> PlanNode {
+  let children = node.children.iter().filter_map(|child| child.total_cost).collect::<Vec<_>>();
+  if !children.is_empty() {
+    node.total_cost = Some(node.total_cost.unwrap_or(0.0) + children.iter().sum::<f64>());
+  }
+  node
+}
+
+fn mysql_query_block(block: &Object) -> PlanNode {
+  let label = match block.get("select_id") {
+    Some(id) => format!("select #{id}"),
+    None => "select".to_owned(),
+  };
+  let children = mysql_children(block);
+  match mysql_cost(block, "query_cost") {
+    Some(cost) => PlanNode {
+      label,
+      details: text(block, "message").map(str::to_owned).into_iter().collect(),
+      total_cost: Some(cost),
+      children,
+      ..Default::default()
+    },
+    None => with_children_cost(PlanNode { label, children, ..Default::default() }),
+  }
+}
+
+fn mysql_children(object: &Object) -> Vec<PlanNode> {
+  let mut children = vec![];
+  for (key, value) in object {
+    match (key.as_str(), value) {
+      ("table", Json::Object(table)) => children.push(mysql_table(table)),
+      ("query_block", Json::Object(block)) => children.push(mysql_query_block(block)),
+      ("nested_loop", Json::Array(tables)) => children.push(with_children_cost(PlanNode {
+        label: "nested loop".to_owned(),
+        children: tables.iter().filter_map(Json::as_object).flat_map(mysql_children).collect(),
+        ..Default::default()
+      })),
+      ("union_result", Json::Object(union)) => children.push(with_children_cost(PlanNode {
+        label: "union".to_owned(),
+        children: mysql_children(union),
+        ..Default::default()
+      })),
+      ("materialized_from_subquery", Json::Object(subquery)) => children.push(with_children_cost(PlanNode {
+        label: "materialized subquery".to_owned(),
+        children: mysql_children(subquery),
+        ..Default::default()
+      })),
+      ("query_specifications" | "attached_subqueries" | "optimized_away_subqueries", Json::Array(subqueries)) => {
+        children.extend(subqueries.iter().filter_map(Json::as_object).flat_map(mysql_children))
+      },
+      ("ordering_operation" | "grouping_operation" | "duplicates_removal" | "windowing", Json::Object(operation)) => {
+        let mut label = match key.as_str() {
+          "ordering_operation" => "order",
+          "grouping_operation" => "group",
+          "duplicates_removal" => "remove duplicates",
+          _ => "window",
+        }
+        .to_owned();
+        if operation.get("using_filesort") == Some(&Json::Bool(true)) {
+          label.push_str(" using filesort");
+        }
+        if operation.get("using_temporary_table") == Some(&Json::Bool(true)) {
+          label.push_str(" using temporary table");
+        }
+        children.push(with_children_cost(PlanNode {
+          label,
+          total_cost: mysql_cost(operation, "sort_cost"),
+          children: mysql_children(operation),
+          ..Default::default()
+        }));
+      },
+      _ => {},
+    }
+  }
+  children
+}
+
+fn mysql_table(table: &Object) -> PlanNode {
+  let mut label = format!(
+    "{} ({})",
+    text(table, "table_name").unwrap_or("?"),
+    text(table, "access_type").unwrap_or("unknown access")
+  );
+  if let Some(key) = text(table, "key") {
+    label.push_str(&format!(" using {key}"));
+  }
+  let mut details = vec![];
+  if let Some(condition) = text(table, "attached_condition") {
+    details.push(format!("condition: {condition}"));
+  }
+  if let Some(Json::Array(keys)) = table.get("possible_keys") {
+    let keys = keys.iter().filter_map(Json::as_str).collect::<Vec<_>>();
+    details.push(format!("possible keys: {}", keys.join(", ")));
+  }
+  if let Some(filtered) = text(table, "filtered") {
+    details.push(format!("filtered: {filtered}%"));
+  }
+  let cost = match (mysql_cost(table, "read_cost"), mysql_cost(table, "eval_cost")) {
+    (None, None) => None,
+    (read, eval) => Some(read.unwrap_or(0.0) + eval.unwrap_or(0.0)),
+  };
+  with_children_cost(PlanNode {
+    label,
+    details,
+    total_cost: cost,
+    plan_rows: number(table.get("rows_examined_per_scan")),
+    children: mysql_children(table),
+    ..Default::default()
+  })
+}
+
+// every row names its parent by id, with 0 for the top of the plan
+fn sqlite_plan(rows: &Rows) -> Option<Plan> {
+  let steps = rows
+    .rows
+    .iter()
+    .map(|row| match row.as_slice() {
+      [id, parent, _, detail] => {
+        Some((id.to_string().parse().ok()?, parent.to_string().parse().ok()?, detail.to_string()))
+      },
+      _ => None,
+    })
+    .collect::<Option<Vec<(i64, i64, String)>>>()?;
+  fn children_of(parent: i64, steps: &[(i64, i64, String)]) -> Vec<PlanNode> {
+    steps
+      .iter()
+      .filter(|(_, p, _)| *p == parent)
+      .map(|(id, _, detail)| PlanNode {
+        label: detail.clone(),
+        children: children_of(*id, steps),
+        ..Default::default()
+      })
+      .collect()
+  }
+  let root = PlanNode { label: "QUERY PLAN".to_owned(), children: children_of(0, &steps), ..Default::default() };
+  Some(Plan { root, ..Default::default() })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::database::{Header, get_first_query};
+
+  fn explain(query: &str, driver: Driver) -> Option<String> {
+    structured_explain(&get_first_query(query.to_owned(), driver).unwrap().1, driver)
+  }
+
+  fn json_rows(json: &str) -> Rows {
+    Rows {
+      headers: vec![Header { name: "QUERY PLAN".to_owned(), type_name: "JSON".to_owned() }],
+      rows: vec![vec![Value::Json(serde_json::from_str(json).unwrap())]],
+      rows_affected: None,
+    }
+  }
+
+  #[test]
+  fn test_structured_explain() {
+    assert_eq!(
+      explain("EXPLAIN SELECT * FROM users", Driver::Postgres).as_deref(),
+      Some("EXPLAIN (FORMAT JSON) SELECT * FROM users")
+    );
+    assert_eq!(
+      explain("EXPLAIN ANALYZE VERBOSE SELECT * FROM users", Driver::Postgres).as_deref(),
+      Some("EXPLAIN (ANALYZE, VERBOSE, FORMAT JSON) SELECT * FROM users")
+    );
+    assert_eq!(
+      explain("EXPLAIN (ANALYZE, BUFFERS) DELETE FROM users WHERE id = 1", Driver::Postgres).as_deref(),
+      Some("EXPLAIN (ANALYZE, BUFFERS, FORMAT JSON) DELETE FROM users WHERE id = 1")
+    );
+    assert_eq!(explain("EXPLAIN (FORMAT YAML) SELECT 1", Driver::Postgres), None);
+    assert_eq!(explain("SELECT 1", Driver::Postgres), None);
+    assert_eq!(
+      explain("EXPLAIN SELECT * FROM users", Driver::MySql).as_deref(),
+      Some("EXPLAIN FORMAT=JSON SELECT * FROM users")
+    );
+    assert_eq!(explain("EXPLAIN ANALYZE SELECT * FROM users", Driver::MySql), None);
+    assert_eq!(explain("EXPLAIN QUERY PLAN SELECT * FROM users", Driver::Sqlite), None);
+    assert_eq!(explain("EXPLAIN SELECT * FROM users", Driver::Sqlite), None);
+  }
+
+  #[test]
+  fn test_parse_postgres_plan() {
+    let plan = parse_plan(&json_rows(
+      r#"[{"Plan": {"Node Type": "Hash Join", "Join Type": "Left", "Startup Cost": 1.5, "Total Cost": 40.0,
+        "Plan Rows": 5, "Actual Total Time": 12.0, "Actual Rows": 900, "Actual Loops": 1,
+        "Hash Cond": "(o.user_id = u.id)",
+        "Plans": [
+          {"Node Type": "Seq Scan", "Relation Name": "orders", "Alias": "o", "Startup Cost": 0.0, "Total Cost": 30.0,
+           "Plan Rows": 1000, "Actual Total Time": 2.5, "Actual Rows": 1000, "Actual Loops": 2,
+           "Filter": "(total > 10)", "Rows Removed by Filter": 3},
+          {"Node Type": "Aggregate", "Strategy": "Hashed", "Total Cost": 2.0, "Plan Rows": 10,
+           "Actual Total Time": 0.0, "Actual Rows": 0, "Actual Loops": 0}
+        ]},
+        "Planning Time": 0.2, "Execution Time": 12.5}]"#,
+    ))
+    .unwrap();
+    assert!(plan.analyzed);
+    assert_eq!(plan.execution_time, Some(12.5));
+    let root = &plan.root;
+    assert_eq!(root.label, "Hash Left Join");
+    assert_eq!(root.details, ["Hash Cond: (o.user_id = u.id)"]);
+    assert_eq!(root.self_cost(), Some(8.0));
+    assert_eq!(root.self_time(), Some(7.0));
+    assert_eq!(root.misestimate(), Some(180.0));
+
+    let scan = &root.children[0];
+    assert_eq!(scan.label, "Seq Scan on orders o");
+    assert_eq!(scan.details, ["Filter: (total > 10)", "Rows Removed by Filter: 3"]);
+    assert_eq!(scan.actual_time, Some(5.0));
+    assert_eq!(scan.misestimate(), None);
+    assert_eq!(plan.share(scan), Some(5.0 / 12.0));
+    // never executed, so nothing is known about its rows
+    assert_eq!(root.children[1].label, "HashAggregate");
+    assert_eq!(root.children[1].misestimate(), None);
+  }
+
+  #[test]
+  fn test_parse_mysql_plan() {
+    let rows = Rows {
+      headers: vec![Header { name: "EXPLAIN".to_owned(), type_name: "TEXT".to_owned() }],
+      rows: vec![vec![Value::Text(
+        r#"{"query_block": {"select_id": 1, "cost_info": {"query_cost": "12.50"},
+          "ordering_operation": {"using_filesort": true, "nested_loop": [
+            {"table": {"table_name": "u", "access_type": "ALL", "rows_examined_per_scan": 10,
+              "cost_info": {"read_cost": "1.00", "eval_cost": "1.00"}, "attached_condition": "(u.id > 1)"}},
+            {"table": {"table_name": "o", "access_type": "ref", "key": "user_id", "possible_keys": ["user_id"],
+              "rows_examined_per_scan": 3, "cost_info": {"read_cost": "7.00", "eval_cost": "3.00"}}}
+          ]}}}"#
+          .to_owned(),
+      )]],
+      rows_affected: None,
+    };
+    let plan = parse_plan(&rows).unwrap();
+    assert!(!plan.analyzed);
+    assert_eq!(plan.root.label, "select #1");
+    assert_eq!(plan.root.total_cost, Some(12.5));
+    let order = &plan.root.children[0];
+    assert_eq!(order.label, "order using filesort");
+    assert_eq!(order.total_cost, Some(12.0));
+    let tables = &order.children[0].children;
+    assert_eq!(tables[0].label, "u (ALL)");
+    assert_eq!(tables[0].details, ["condition: (u.id > 1)"]);
+    assert_eq!(tables[1].label, "o (ref) using user_id");
+    assert_eq!(tables[1].plan_rows, Some(3.0));
+    assert_eq!(plan.share(&tables[1]), Some(10.0 / 12.5));
+  }
+
+  #[test]
+  fn test_parse_sqlite_plan() {
+    let header = |name: &str| Header { name: name.to_owned(), type_name: "".to_owned() };
+    let step = |id: i128, parent: i128, detail: &str| {
+      vec![Value::Int(id), Value::Int(parent), Value::Int(0), Value::Text(detail.to_owned())]
+    };
+    let rows = Rows {
+      headers: vec![header("id"), header("parent"), header("notused"), header("detail")],
+      rows: vec![
+        step(2, 0, "SCAN users"),
+        step(5, 0, "CORRELATED SCALAR SUBQUERY 1"),
+        step(8, 5, "SEARCH orders USING INDEX orders_user (user_id=?)"),
+      ],
+      rows_affected: None,
+    };
+    let plan = parse_plan(&rows).unwrap();
+    assert_eq!(plan.root.children.len(), 2);
+    assert_eq!(plan.root.children[1].children[0].label, "SEARCH orders USING INDEX orders_user (user_id=?)");
+    assert_eq!(plan.share(&plan.root), None);
+  }
+
+  #[test]
+  fn test_parse_plan_text() {
+    let rows = Rows {
+      headers: vec![Header { name: "QUERY PLAN".to_owned(), type_name: "TEXT".to_owned() }],
+      rows: vec![vec![Value::Text("Seq Scan on users  (cost=0.00..1.01 rows=1 width=4)".to_owned())]],
+      rows_affected: None,
+    };
+    assert_eq!(parse_plan(&rows), None);
+  }
+}
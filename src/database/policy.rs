@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use sqlparser::ast::{Expr, Statement};
+use strum::Display;
+
+use super::ExecutionType;
+
+/// The kinds of statements that `[execution_policy]` sets an
+/// `ExecutionType` for. Statements of any other kind run normally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementKind {
+  #[strum(serialize = "SELECT")]
+  Select,
+  #[strum(serialize = "INSERT")]
+  Insert,
+  #[strum(serialize = "UPDATE")]
+  Update,
+  #[strum(serialize = "DELETE")]
+  Delete,
+  #[strum(serialize = "MERGE")]
+  Merge,
+  #[strum(serialize = "CREATE")]
+  Create,
+  #[strum(serialize = "ALTER")]
+  Alter,
+  #[strum(serialize = "DROP")]
+  Drop,
+  #[strum(serialize = "TRUNCATE")]
+  Truncate,
+  #[strum(serialize = "GRANT")]
+  Grant,
+  #[strum(serialize = "REVOKE")]
+  Revoke,
+  /// Takes the place of `Update` when there's no WHERE clause.
+  #[strum(serialize = "UPDATE without a WHERE clause")]
+  UpdateWithoutWhere,
+  /// Takes the place of `Delete` when there's no WHERE clause.
+  #[strum(serialize = "DELETE without a WHERE clause")]
+  DeleteWithoutWhere,
+  /// Takes the place of `Insert` on protected connections.
+  #[strum(serialize = "INSERT on a protected connection")]
+  ProtectedInsert,
+}
+
+/// How each kind of statement is run on a connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionPolicy {
+  rules: HashMap<StatementKind, ExecutionType>,
+  // set when the config forbids a kind of statement, rather than only the
+  // built-in rules
+  configured_forbid: bool,
+}
+
+impl Default for ExecutionPolicy {
+  fn default() -> Self {
+    let rules = HashMap::from([
+      (StatementKind::Update, ExecutionType::Transaction),
+      (StatementKind::Delete, ExecutionType::Transaction),
+      (StatementKind::Alter, ExecutionType::Confirm),
+      (StatementKind::Drop, ExecutionType::Confirm),
+      (StatementKind::Truncate, ExecutionType::Confirm),
+      (StatementKind::UpdateWithoutWhere, ExecutionType::Forbid),
+      (StatementKind::DeleteWithoutWhere, ExecutionType::Forbid),
+      (StatementKind::ProtectedInsert, ExecutionType::Confirm),
+    ]);
+    Self { rules, configured_forbid: false }
+  }
+}
+
+impl ExecutionPolicy {
+  /// Layers each set of rules over the built-in ones, later ones winning.
+  pub fn new<'a>(rules: impl IntoIterator<Item = &'a HashMap<StatementKind, ExecutionType>>, protected: bool) -> Self {
+    let mut policy = Self::default();
+    for rules in rules {
+      policy.configured_forbid |= rules.values().any(|execution_type| *execution_type == ExecutionType::Forbid);
+      policy.rules.extend(rules.iter().map(|(kind, execution_type)| (*kind, execution_type.clone())));
+    }
+    if protected && let Some(execution_type) = policy.rules.get(&StatementKind::ProtectedInsert).cloned() {
+      policy.rules.insert(StatementKind::Insert, execution_type);
+    }
+    policy
+  }
+
+  pub fn execution_type(&self, kind: StatementKind) -> ExecutionType {
+    self.rules.get(&kind).cloned().unwrap_or(ExecutionType::Normal)
+  }
+
+  /// Whether the config forbids any kind of statement, in which case queries
+  /// have to be parsed to be run. The built-in rules don't count, so that
+  /// the parser can still be bypassed with them.
+  pub fn configures_forbid(&self) -> bool {
+    self.configured_forbid
+  }
+}
+
+/// Whether the statement is an EXPLAIN that runs the statement it explains,
+/// either with `EXPLAIN ANALYZE` or with an `ANALYZE` option, as in
+/// `EXPLAIN (ANALYZE) ...`, that isn't turned off.
+pub fn explain_analyzes(statement: &Statement) -> bool {
+  let Statement::Explain { analyze, options, .. } = statement else {
+    return false;
+  };
+  *analyze
+    || options.iter().flatten().any(|option| {
+      option.name.value.eq_ignore_ascii_case("analyze") && option.arg.as_ref().is_none_or(|arg| !is_off(arg))
+    })
+}
+
+fn is_off(arg: &Expr) -> bool {
+  let arg = match arg {
+    Expr::Value(value) => value.value.to_string(),
+    Expr::Identifier(ident) => ident.value.clone(),
+    _ => return false,
+  };
+  ["false", "off", "0"].iter().any(|off| arg.trim_matches('\'').eq_ignore_ascii_case(off))
+}
+
+/// The most specific kind of the statement. EXPLAIN ANALYZE runs the
+/// statement it explains, so it has the same kind, however it's written.
+pub fn statement_kind(statement: &Statement) -> Option<StatementKind> {
+  match statement {
+    Statement::Query(_) => Some(StatementKind::Select),
+    Statement::Insert(_) => Some(StatementKind::Insert),
+    Statement::Update { selection: None, .. } => Some(StatementKind::UpdateWithoutWhere),
+    Statement::Update { .. } => Some(StatementKind::Update),
+    Statement::Delete(delete) if delete.selection.is_none() => Some(StatementKind::DeleteWithoutWhere),
+    Statement::Delete(_) => Some(StatementKind::Delete),
+    Statement::Merge { .. } => Some(StatementKind::Merge),
+    Statement::CreateView { .. }
+    | Statement::CreateTable(_)
+    | Statement::CreateVirtualTable { .. }
+    | Statement::CreateIndex(_)
+    | Statement::CreateRole { .. }
+    | Statement::CreateSecret { .. }
+    | Statement::CreateServer(_)
+    | Statement::CreatePolicy { .. }
+    | Statement::CreateConnector(_)
+    | Statement::CreateExtension { .. }
+    | Statement::CreateSchema { .. }
+    | Statement::CreateDatabase { .. }
+    | Statement::CreateFunction(_)
+    | Statement::CreateTrigger { .. }
+    | Statement::CreateProcedure { .. }
+    | Statement::CreateMacro { .. }
+    | Statement::CreateStage { .. }
+    | Statement::CreateSequence { .. }
+    | Statement::CreateDomain(_)
+    | Statement::CreateType { .. } => Some(StatementKind::Create),
+    Statement::AlterTable { .. }
+    | Statement::AlterIndex { .. }
+    | Statement::AlterView { .. }
+    | Statement::AlterType(_)
+    | Statement::AlterRole { .. }
+    | Statement::AlterPolicy { .. }
+    | Statement::AlterConnector { .. }
+    | Statement::RenameTable(_) => Some(StatementKind::Alter),
+    Statement::Drop { .. }
+    | Statement::DropFunction { .. }
+    | Statement::DropDomain(_)
+    | Statement::DropProcedure { .. }
+    | Statement::DropSecret { .. }
+    | Statement::DropPolicy { .. }
+    | Statement::DropConnector { .. }
+    | Statement::DropExtension { .. }
+    | Statement::DropTrigger { .. } => Some(StatementKind::Drop),
+    Statement::Truncate { .. } => Some(StatementKind::Truncate),
+    Statement::Grant { .. } => Some(StatementKind::Grant),
+    Statement::Revoke { .. } => Some(StatementKind::Revoke),
+    Statement::Explain { statement: explained, .. } if explain_analyzes(statement) => statement_kind(explained),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{cli::Driver, database::get_first_query};
+
+  fn kind(query: &str) -> Option<StatementKind> {
+    statement_kind(&get_first_query(query.to_owned(), Driver::Postgres).unwrap().1)
+  }
+
+  #[test]
+  fn test_statement_kind() {
+    assert_eq!(kind("SELECT * FROM users"), Some(StatementKind::Select));
+    assert_eq!(kind("UPDATE users SET name = 'a' WHERE id = 1"), Some(StatementKind::Update));
+    assert_eq!(kind("UPDATE users SET name = 'a'"), Some(StatementKind::UpdateWithoutWhere));
+    assert_eq!(kind("DELETE FROM users"), Some(StatementKind::DeleteWithoutWhere));
+    assert_eq!(kind("CREATE INDEX users_name ON users (name)"), Some(StatementKind::Create));
+    assert_eq!(kind("ALTER TABLE users ADD COLUMN age int"), Some(StatementKind::Alter));
+    assert_eq!(kind("DROP FUNCTION f"), Some(StatementKind::Drop));
+    assert_eq!(kind("EXPLAIN ANALYZE DELETE FROM users WHERE id = 1"), Some(StatementKind::Delete));
+    assert_eq!(kind("EXPLAIN (ANALYZE) DELETE FROM users"), Some(StatementKind::DeleteWithoutWhere));
+    assert_eq!(kind("EXPLAIN (ANALYZE, BUFFERS) DELETE FROM users WHERE id = 1"), Some(StatementKind::Delete));
+    assert_eq!(kind("EXPLAIN (ANALYZE false) DELETE FROM users"), None);
+    assert_eq!(kind("EXPLAIN DELETE FROM users WHERE id = 1"), None);
+    assert_eq!(kind("SHOW search_path"), None);
+  }
+
+  #[test]
+  fn test_execution_policy() {
+    let global = HashMap::from([(StatementKind::Create, ExecutionType::Confirm)]);
+    let connection = HashMap::from([
+      (StatementKind::Create, ExecutionType::Forbid),
+      (StatementKind::DeleteWithoutWhere, ExecutionType::Transaction),
+    ]);
+
+    let policy = ExecutionPolicy::new([&global], false);
+    assert_eq!(policy.execution_type(StatementKind::Create), ExecutionType::Confirm);
+    assert_eq!(policy.execution_type(StatementKind::Drop), ExecutionType::Confirm);
+    assert_eq!(policy.execution_type(StatementKind::DeleteWithoutWhere), ExecutionType::Forbid);
+    assert_eq!(policy.execution_type(StatementKind::Insert), ExecutionType::Normal);
+
+    let policy = ExecutionPolicy::new([&global, &connection], true);
+    assert_eq!(policy.execution_type(StatementKind::Create), ExecutionType::Forbid);
+    assert_eq!(policy.execution_type(StatementKind::DeleteWithoutWhere), ExecutionType::Transaction);
+    assert_eq!(policy.execution_type(StatementKind::Insert), ExecutionType::Confirm);
+    assert_eq!(policy.execution_type(StatementKind::Select), ExecutionType::Normal);
+    assert!(policy.configures_forbid());
+
+    assert!(!ExecutionPolicy::default().configures_forbid());
+    assert!(!ExecutionPolicy::new([&global], false).configures_forbid());
+  }
+}
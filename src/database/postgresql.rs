@@ -1,34 +1,62 @@
 use std::{
-  fmt::Write,
+  collections::VecDeque,
   io::{self, Write as _},
   str::FromStr,
   string::String,
   sync::Arc,
+  time::Duration,
 };
 
 use async_trait::async_trait;
 use color_eyre::eyre::{self, Result};
-use futures::stream::StreamExt;
+use futures::stream::{BoxStream, StreamExt};
 use sqlparser::ast::Statement;
 use sqlx::{
-  Column, Either, Row, ValueRef,
+  Column, Either, Executor, Row, ValueRef,
   pool::PoolConnection,
-  postgres::{PgConnectOptions, PgConnection, PgPoolOptions, Postgres},
+  postgres::{PgConnectOptions, PgConnection, PgListener, PgPoolOptions, PgRow, PgSslMode, Postgres},
   types::Uuid,
 };
-use tokio::sync::Mutex;
+use tokio::sync::{
+  Mutex, OwnedMutexGuard,
+  mpsc::{UnboundedReceiver, unbounded_channel},
+};
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 use super::{
-  Database, DbTaskResult, Driver, Header, Headers, QueryResultsWithMetadata, QueryTask, Rows, Value, vec_to_string,
+  Database, DbTaskResult, Driver, Header, Headers, LockWait, Notification, ObjectKind, ParameterizedStatement,
+  QueryOptions, QueryResultsWithMetadata, QueryTask, Rows, ScriptErrorPolicy, ServerSession, TableDetails, TableInfo,
+  TxPreview, TxPreviewQueries, Value, activity, ddl, edit, schema, stream::PageSender, vec_to_string,
 };
+use crate::cli::{SslMode, TlsOptions};
+
+// how long a partly read cursor's transaction stays open without any more
+// rows being asked for
+const CURSOR_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
 
 type PostgresTransaction<'a> = sqlx::Transaction<'a, Postgres>;
-type TransactionTask<'a> = JoinHandle<(QueryResultsWithMetadata, PostgresTransaction<'a>)>;
+type TransactionTask<'a> = JoinHandle<(Vec<QueryResultsWithMetadata>, Vec<TxPreview>, PostgresTransaction<'a>)>;
 enum PostgresTask<'a> {
   Query(QueryTask),
   TxStart(TransactionTask<'a>),
-  TxPending(Box<(PostgresTransaction<'a>, QueryResultsWithMetadata)>),
+  TxPending(Box<(PostgresTransaction<'a>, Vec<QueryResultsWithMetadata>)>),
+}
+
+// LISTEN holds on to a connection for as long as it lasts, so the listener
+// gets a pool of its own rather than taking one of the query pool's
+struct Listener {
+  pool: sqlx::Pool<Postgres>,
+  channels: Vec<String>,
+  task: JoinHandle<()>,
+  notifications: UnboundedReceiver<Notification>,
+}
+
+impl Listener {
+  async fn close(self) {
+    self.task.abort();
+    self.pool.close().await;
+  }
 }
 
 #[derive(Default)]
@@ -37,45 +65,76 @@ pub struct PostgresDriver<'a> {
   task: Option<PostgresTask<'a>>,
   querying_conn: Option<Arc<Mutex<PoolConnection<Postgres>>>>,
   querying_pid: Option<String>,
+  // pinned by `begin_session_tx()` for every query until the transaction ends
+  session_conn: Option<Arc<Mutex<PoolConnection<Postgres>>>>,
+  // cancelled when the transaction ends, so that results still streaming
+  // from `session_conn` hand it back
+  session_streams: CancellationToken,
+  listener: Option<Listener>,
 }
 
 #[async_trait(?Send)]
 impl Database for PostgresDriver<'_> {
   async fn init(&mut self, args: crate::cli::Cli) -> Result<()> {
-    let opts = super::postgresql::PostgresDriver::<'_>::build_connection_opts(args)?;
+    let read_only = args.read_only;
+    let mut opts = super::postgresql::PostgresDriver::<'_>::build_connection_opts(args)?;
+    if read_only {
+      opts = opts.options([("default_transaction_read_only", "on")]);
+    }
     let pool = Arc::new(PgPoolOptions::new().max_connections(3).connect_with(opts).await?);
     self.pool = Some(pool);
     Ok(())
   }
 
-  // since it's possible for raw_sql to execute multiple queries in a single string,
-  // we only execute the first one and then drop the rest.
-  async fn start_query(&mut self, query: String, bypass_parser: bool) -> Result<()> {
-    let (first_query, statement_type) = match bypass_parser {
-      true => (query, None),
-      false => {
-        let (first, stmt) = super::get_first_query(query, Driver::Postgres)?;
-        (first, Some(stmt))
-      },
-    };
+  // raw_sql would happily run every statement in a single string, but we
+  // run them one at a time so that each one gets its own results. rows of
+  // the last statement are streamed a page at a time.
+  async fn start_query(&mut self, query: String, bypass_parser: bool, options: QueryOptions) -> Result<()> {
+    let mut queries = super::get_script(query, bypass_parser, Driver::Postgres, options.max_rows)?;
+    // the cursor used for streaming lives in its own transaction, which
+    // would get tangled up with one that the script or session manages itself
+    let use_cursor = self.session_conn.is_none()
+      && !queries.iter().any(|(_, statement_type)| statement_type.as_ref().is_some_and(super::is_transaction_control));
     let pool = self.pool.clone().unwrap();
-    self.querying_conn = Some(Arc::new(Mutex::new(pool.acquire().await?)));
+    let session_streams = self.session_conn.is_some().then(|| self.session_streams.clone());
+    self.querying_conn = Some(match self.session_conn.clone() {
+      Some(conn) => conn,
+      None => Arc::new(Mutex::new(pool.acquire().await?)),
+    });
     let conn = self.querying_conn.clone().unwrap();
     let conn_for_task = conn.clone();
     let pid = sqlx::raw_sql("SELECT pg_backend_pid()").fetch_one(conn.lock().await.as_mut()).await?.get::<i32, _>(0);
     log::info!("Starting query with PID {}", pid.clone());
     self.querying_pid = Some(pid.to_string().clone());
     self.task = Some(PostgresTask::Query(tokio::spawn(async move {
-      let results = query_with_conn(conn_for_task.lock().await.as_mut(), first_query.clone()).await;
-      match results {
-        Ok(ref rows) => {
-          log::info!("{:?} rows, {:?} affected", rows.rows.len(), rows.rows_affected);
-        },
-        Err(ref e) => {
-          log::error!("{e:?}");
-        },
-      };
-      QueryResultsWithMetadata { results, statement_type: statement_type.clone() }
+      let mut conn = conn_for_task.lock_owned().await;
+      let mut results = vec![];
+      let last = queries.pop();
+      for (query, statement_type) in queries {
+        let rows = query_with_conn(conn.as_mut(), query).await;
+        log_rows(&rows);
+        let failed = rows.is_err();
+        results.push(QueryResultsWithMetadata { results: rows, statement_type, stream: None });
+        if failed && options.on_error == ScriptErrorPolicy::Stop {
+          return results;
+        }
+      }
+      if let Some((query, statement_type)) = last {
+        let (rows, stream) = if statement_type.as_ref().is_some_and(super::is_streamable_query) {
+          let (stream, pages) = super::stream::row_stream(options.page_size, options.row_cap);
+          let pages = match session_streams {
+            Some(token) => pages.cancel_on(token),
+            None => pages,
+          };
+          tokio::spawn(stream_query(conn, query, use_cursor, pages));
+          stream.first_page().await
+        } else {
+          (query_with_conn(conn.as_mut(), query).await, None)
+        };
+        log_rows(&rows);
+        results.push(QueryResultsWithMetadata { results: rows, statement_type, stream });
+      }
+      results
     })));
     Ok(())
   }
@@ -89,23 +148,10 @@ impl Database for PostgresDriver<'_> {
           _ => {},
         };
         if let Some(pid) = self.querying_pid.take() {
-          let result =
-            sqlx::raw_sql(&format!("SELECT pg_cancel_backend({pid})")).fetch_one(&*self.pool.clone().unwrap()).await;
-          let msg = match &result {
-            Ok(_) => "Successfully killed".to_string(),
-            Err(e) => format!("Failed to kill: {e:?}"),
-          };
-
-          let success = result.as_ref().is_ok_and(|r| r.try_get::<bool, _>(0).unwrap_or(false));
-          let status_string =
-            result.map_or("ERROR".to_string(), |r| r.try_get_unchecked::<String, _>(0).unwrap_or("ERROR".to_string()));
-
-          if !success {
-            log::warn!("Unexpected response when cancelling backend process with PID {pid}: {msg}");
-            log::warn!("Status: {status_string}");
-          } else {
-            log::info!("Tried to cancel backend process with PID {pid}: {msg}");
-            log::info!("Status: {status_string}");
+          match signal_backend(&self.pool.clone().unwrap(), "pg_cancel_backend", &pid).await {
+            Ok(true) => log::info!("Cancelled backend process with PID {pid}"),
+            Ok(false) => log::warn!("Backend process with PID {pid} could not be cancelled"),
+            Err(e) => log::warn!("Failed to cancel backend process with PID {pid}: {e:?}"),
           }
         }
         self.querying_conn = None;
@@ -136,23 +182,16 @@ impl Database for PostgresDriver<'_> {
         if !handle.is_finished() {
           (DbTaskResult::Pending, Some(PostgresTask::TxStart(handle)))
         } else {
-          let (result, tx) = handle.await?;
-          let rows_affected = match &result.results {
-            Ok(rows) => rows.rows_affected,
-            _ => None,
-          };
-          match result {
-            // if tx failed to start, return the error immediately
-            QueryResultsWithMetadata { results: Err(e), statement_type } => {
-              log::error!("Transaction didn't start: {e:?}");
-              self.querying_conn = None;
-              self.querying_pid = None;
-              (DbTaskResult::Finished(QueryResultsWithMetadata { results: Err(e), statement_type }), None)
-            },
-            _ => (
-              DbTaskResult::ConfirmTx(rows_affected, result.statement_type.clone()),
-              Some(PostgresTask::TxPending(Box::new((tx, result)))),
-            ),
+          let (results, previews, tx) = handle.await?;
+          // if a statement failed, the transaction is rolled back as it's
+          // dropped, and the results are returned immediately
+          if results.iter().any(|result| result.results.is_err()) {
+            log::error!("Transaction failed, rolling back");
+            self.querying_conn = None;
+            self.querying_pid = None;
+            (DbTaskResult::Finished(results), None)
+          } else {
+            (super::confirm_tx(&results, previews), Some(PostgresTask::TxPending(Box::new((tx, results)))))
           }
         }
       },
@@ -162,50 +201,64 @@ impl Database for PostgresDriver<'_> {
     Ok(task_result)
   }
 
-  async fn start_tx(&mut self, query: String) -> Result<()> {
-    let (first_query, statement_type) = super::get_first_query(query, Driver::Postgres)?;
+  async fn start_tx(&mut self, query: String, options: QueryOptions) -> Result<()> {
+    let queries = super::get_queries(query, Driver::Postgres)?;
     let mut tx = self.pool.clone().unwrap().begin().await?;
     let pid = sqlx::raw_sql("SELECT pg_backend_pid()").fetch_one(&mut *tx).await?.get::<i32, _>(0);
     log::info!("Starting transaction with PID {}", pid.clone());
     self.querying_pid = Some(pid.to_string().clone());
     self.task = Some(PostgresTask::TxStart(tokio::spawn(async move {
-      let (results, tx) = query_with_tx(tx, &first_query).await;
+      let mut results = vec![];
+      let mut previews = vec![];
+      for (query, statement_type) in queries {
+        let (result, preview, next_tx) = tx_statement(tx, query, statement_type, options.row_cap).await;
+        tx = next_tx;
+        let failed = result.results.is_err();
+        results.push(result);
+        previews.extend(preview);
+        if failed {
+          break;
+        }
+      }
+      (results, previews, tx)
+    })));
+    Ok(())
+  }
+
+  async fn start_tx_with_params(&mut self, statements: Vec<ParameterizedStatement>) -> Result<()> {
+    let statement_type = statements
+      .first()
+      .and_then(|statement| super::get_first_query(statement.sql.clone(), Driver::Postgres).ok())
+      .map(|(_, statement_type)| statement_type);
+    let mut tx = self.pool.clone().unwrap().begin().await?;
+    let pid = sqlx::raw_sql("SELECT pg_backend_pid()").fetch_one(&mut *tx).await?.get::<i32, _>(0);
+    log::info!("Starting transaction with PID {}", pid.clone());
+    self.querying_pid = Some(pid.to_string().clone());
+    self.task = Some(PostgresTask::TxStart(tokio::spawn(async move {
+      let (results, tx) = execute_with_params(tx, &statements).await;
       match results {
-        Ok(Either::Left(rows_affected)) => {
-          log::info!("{rows_affected:?} rows affected");
-          (
-            QueryResultsWithMetadata {
-              results: Ok(Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) }),
-              statement_type: Some(statement_type),
-            },
-            tx,
-          )
-        },
-        Ok(Either::Right(rows)) => {
-          log::info!("{:?} rows affected", rows.rows_affected);
-          (QueryResultsWithMetadata { results: Ok(rows), statement_type: Some(statement_type) }, tx)
-        },
-        Err(e) => {
-          log::error!("{e:?}");
-          (QueryResultsWithMetadata { results: Err(e), statement_type: Some(statement_type) }, tx)
-        },
+        Ok(rows_affected) => log::info!("{rows_affected:?} rows affected"),
+        Err(ref e) => log::error!("{e:?}"),
       }
+      let results =
+        results.map(|rows_affected| Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) });
+      (vec![QueryResultsWithMetadata { results, statement_type, stream: None }], vec![], tx)
     })));
     Ok(())
   }
 
-  async fn commit_tx(&mut self) -> Result<Option<QueryResultsWithMetadata>> {
+  async fn commit_tx(&mut self) -> Result<Vec<QueryResultsWithMetadata>> {
     if !matches!(self.task, Some(PostgresTask::TxPending(_))) {
-      Ok(None)
+      Ok(vec![])
     } else {
       match self.task.take() {
         Some(PostgresTask::TxPending(b)) => {
           b.0.commit().await?;
           self.querying_conn = None;
           self.querying_pid = None;
-          Ok(Some(b.1))
+          Ok(b.1)
         },
-        _ => Ok(None),
+        _ => Ok(vec![]),
       }
     }
   }
@@ -219,20 +272,338 @@ impl Database for PostgresDriver<'_> {
     Ok(())
   }
 
+  async fn begin_session_tx(&mut self) -> Result<()> {
+    if self.session_conn.is_some() {
+      return Err(eyre::Report::msg("A transaction is already in progress"));
+    }
+    let mut conn = self.pool.clone().unwrap().acquire().await?;
+    conn.as_mut().execute(sqlx::raw_sql("BEGIN")).await?;
+    self.session_conn = Some(Arc::new(Mutex::new(conn)));
+    self.session_streams = CancellationToken::new();
+    Ok(())
+  }
+
+  async fn commit_session_tx(&mut self) -> Result<()> {
+    self.end_session_tx("COMMIT").await
+  }
+
+  async fn rollback_session_tx(&mut self) -> Result<()> {
+    self.end_session_tx("ROLLBACK").await
+  }
+
+  async fn close(&mut self) -> Result<()> {
+    self.rollback_tx().await?;
+    self.abort_query().await?;
+    self.rollback_session_tx().await?;
+    self.unlisten_all().await?;
+    if let Some(pool) = self.pool.take() {
+      pool.close().await;
+    }
+    Ok(())
+  }
+
+  // the listener can't be told about a new channel while it waits for a
+  // notification, so it's replaced by one that listens on every channel
+  async fn listen(&mut self, channel: &str) -> Result<()> {
+    let mut channels = self.listener.as_ref().map_or(vec![], |listener| listener.channels.clone());
+    if channels.iter().any(|c| c == channel) {
+      return Ok(());
+    }
+    channels.push(channel.to_owned());
+    let options = (*self.pool.clone().unwrap().connect_options()).clone();
+    let pool = PgPoolOptions::new().max_connections(1).connect_lazy_with(options);
+    let mut pg_listener = PgListener::connect_with(&pool).await?;
+    pg_listener.listen_all(channels.iter().map(String::as_str)).await?;
+
+    let (tx, notifications) = unbounded_channel();
+    if let Some(mut old) = self.listener.take() {
+      while let Ok(notification) = old.notifications.try_recv() {
+        tx.send(notification).ok();
+      }
+      old.close().await;
+    }
+    let task = tokio::spawn(async move {
+      loop {
+        match pg_listener.recv().await {
+          Ok(notification) => {
+            let notification = Notification {
+              channel: notification.channel().to_owned(),
+              payload: notification.payload().to_owned(),
+              process_id: notification.process_id(),
+              received_at: chrono::Local::now(),
+            };
+            if tx.send(notification).is_err() {
+              break;
+            }
+          },
+          Err(sqlx::Error::PoolClosed) => break,
+          // recv reconnects on its own, so this is only given a moment
+          Err(e) => {
+            log::error!("Failed to receive notifications: {e:?}");
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+          },
+        }
+      }
+    });
+    log::info!("Listening on {}", channels.join(", "));
+    self.listener = Some(Listener { pool, channels, task, notifications });
+    Ok(())
+  }
+
+  async fn unlisten_all(&mut self) -> Result<()> {
+    if let Some(listener) = self.listener.take() {
+      listener.close().await;
+    }
+    Ok(())
+  }
+
+  fn take_notifications(&mut self) -> Vec<Notification> {
+    let mut notifications = vec![];
+    if let Some(listener) = self.listener.as_mut() {
+      while let Ok(notification) = listener.notifications.try_recv() {
+        notifications.push(notification);
+      }
+    }
+    notifications
+  }
+
+  // idle sessions are timed from when they became idle
+  async fn load_activity(&self) -> Result<Vec<ServerSession>> {
+    let rows = query_with_pool(
+      self.pool.clone().unwrap(),
+      "select pid::text, usename::text, datname::text,
+        concat_ws(' ', nullif(application_name, ''),
+          '(' || coalesce(host(client_addr) || ':' || client_port, 'local') || ')'),
+        state, wait_event_type || ': ' || wait_event,
+        extract(epoch from clock_timestamp() - case state when 'active' then query_start else state_change end)::float8,
+        query
+      from pg_stat_activity
+      where backend_type = 'client backend' and pid <> pg_backend_pid()
+      order by state = 'active' desc, 7 desc nulls last"
+        .to_owned(),
+    )
+    .await?;
+    Ok(activity::sessions_from_rows(rows))
+  }
+
+  async fn cancel_session(&self, id: &str) -> Result<()> {
+    match signal_backend(&self.pool.clone().unwrap(), "pg_cancel_backend", id).await? {
+      true => Ok(()),
+      false => Err(eyre::Report::msg(format!("Session {id} could not be cancelled"))),
+    }
+  }
+
+  async fn terminate_session(&self, id: &str) -> Result<()> {
+    match signal_backend(&self.pool.clone().unwrap(), "pg_terminate_backend", id).await? {
+      true => Ok(()),
+      false => Err(eyre::Report::msg(format!("Session {id} could not be terminated"))),
+    }
+  }
+
+  // a session that waits is timed from when its query started, and one that
+  // only blocks from when its transaction began. row locks are waited on
+  // through the transaction holding them, so their table is found through
+  // the tuple lock of the waiting session
+  async fn load_lock_waits(&self) -> Result<Vec<LockWait>> {
+    let rows = query_with_pool(
+      self.pool.clone().unwrap(),
+      "with blocked as (
+        select pid, pg_blocking_pids(pid) as blockers from pg_stat_activity
+        where cardinality(pg_blocking_pids(pid)) > 0
+      ), involved as (
+        select pid from blocked union select unnest(blockers) from blocked
+      )
+      select a.pid::text, array_to_string(b.blockers, ','), a.state, a.query, l.mode,
+        coalesce(
+          l.relation::regclass::text,
+          (select t.relation::regclass::text from pg_locks t where t.pid = a.pid and t.locktype = 'tuple' limit 1),
+          l.locktype
+        ),
+        extract(epoch from clock_timestamp() - coalesce(
+          case when b.pid is not null then a.query_start end, a.xact_start, a.query_start
+        ))::float8
+      from involved i
+      join pg_stat_activity a on a.pid = i.pid
+      left join blocked b on b.pid = a.pid
+      left join lateral (
+        select l.mode, l.locktype, l.relation from pg_locks l
+        where l.pid = a.pid and (not l.granted or exists (
+          select from pg_locks w
+          where not w.granted and w.pid <> a.pid and w.locktype = l.locktype
+          and w.database is not distinct from l.database and w.relation is not distinct from l.relation
+          and w.page is not distinct from l.page and w.tuple is not distinct from l.tuple
+          and w.transactionid is not distinct from l.transactionid
+        ))
+        order by l.granted, l.relation is null
+        limit 1
+      ) l on true
+      order by a.pid"
+        .to_owned(),
+    )
+    .await?;
+    Ok(activity::lock_waits_from_rows(rows))
+  }
+
   async fn load_menu(&self) -> Result<Rows> {
     query_with_pool(
       self.pool.clone().unwrap(),
-      "select table_schema, table_name
-      from information_schema.tables
-      where table_schema != 'pg_catalog'
-      and table_schema != 'information_schema'
-      group by table_schema, table_name
-      order by table_schema, table_name asc"
+      "with schemas as (
+        select oid, nspname from pg_namespace
+        where nspname not in ('pg_catalog', 'information_schema') and nspname not like 'pg_toast%'
+      )
+      select s.nspname::text, c.relname::text,
+        case c.relkind
+          when 'v' then 'view' when 'm' then 'materialized view' when 'S' then 'sequence' else 'table'
+        end
+      from pg_class c join schemas s on s.oid = c.relnamespace
+      where c.relkind in ('r', 'p', 'f', 'v', 'm', 'S')
+      union
+      select s.nspname::text, p.proname::text, case p.prokind when 'p' then 'procedure' else 'function' end
+      from pg_proc p join schemas s on s.oid = p.pronamespace
+      where p.prokind in ('f', 'p')
+      union
+      select s.nspname::text, t.tgname::text, 'trigger'
+      from pg_trigger t join pg_class c on c.oid = t.tgrelid join schemas s on s.oid = c.relnamespace
+      where not t.tgisinternal
+      union
+      select s.nspname::text, t.typname::text, 'enum'
+      from pg_type t join schemas s on s.oid = t.typnamespace
+      where t.typtype = 'e'
+      order by 1, 2"
         .to_owned(),
     )
     .await
   }
 
+  async fn load_tables(&self) -> Result<Vec<TableInfo>> {
+    let rows = query_with_pool(
+      self.pool.clone().unwrap(),
+      "select n.nspname::text, c.relname::text,
+        case c.relkind when 'v' then 'view' when 'm' then 'materialized view' else 'table' end,
+        obj_description(c.oid, 'pg_class')
+      from pg_class c
+      join pg_namespace n on n.oid = c.relnamespace
+      where c.relkind in ('r', 'p', 'f', 'v', 'm')
+      and n.nspname not in ('pg_catalog', 'information_schema')
+      and n.nspname not like 'pg_toast%'
+      order by n.nspname, c.relname"
+        .to_owned(),
+    )
+    .await?;
+    Ok(schema::tables_from_rows(rows))
+  }
+
+  async fn describe_table(&self, schema: &str, table: &str) -> Result<TableDetails> {
+    let pool = self.pool.clone().unwrap();
+    // narrows a catalog down to the table, given the column holding its oid
+    let relation = |oid: &str| {
+      format!(
+        "join pg_class c on c.oid = {oid}
+        join pg_namespace n on n.oid = c.relnamespace
+        where n.nspname = {} and c.relname = {}",
+        schema::literal(schema),
+        schema::literal(table)
+      )
+    };
+    let columns = query_with_pool(
+      pool.clone(),
+      format!(
+        "select a.attname::text, format_type(a.atttypid, a.atttypmod), not a.attnotnull,
+          pg_get_expr(d.adbin, d.adrelid), col_description(a.attrelid, a.attnum)
+        from pg_attribute a
+        left join pg_attrdef d on d.adrelid = a.attrelid and d.adnum = a.attnum
+        {}
+        and a.attnum > 0 and not a.attisdropped
+        order by a.attnum",
+        relation("a.attrelid")
+      ),
+    )
+    .await?;
+    let foreign_keys = query_with_pool(
+      pool.clone(),
+      format!(
+        "select con.conname::text, a.attname::text, fn.nspname::text, fc.relname::text, fa.attname::text
+        from pg_constraint con
+        cross join unnest(con.conkey, con.confkey) with ordinality as k(attnum, fattnum, pos)
+        join pg_attribute a on a.attrelid = con.conrelid and a.attnum = k.attnum
+        join pg_class fc on fc.oid = con.confrelid
+        join pg_namespace fn on fn.oid = fc.relnamespace
+        join pg_attribute fa on fa.attrelid = con.confrelid and fa.attnum = k.fattnum
+        {}
+        and con.contype = 'f'
+        order by con.conname, k.pos",
+        relation("con.conrelid")
+      ),
+    )
+    .await?;
+    let indexes = query_with_pool(
+      pool,
+      format!(
+        "select ic.relname::text, pg_get_indexdef(i.indexrelid, k.pos, true), i.indisunique, i.indisprimary
+        from pg_index i
+        cross join generate_series(1, i.indnkeyatts) as k(pos)
+        join pg_class ic on ic.oid = i.indexrelid
+        {}
+        order by ic.relname, k.pos",
+        relation("i.indrelid")
+      ),
+    )
+    .await?;
+    Ok(TableDetails::from_indexes(
+      schema::columns_from_rows(columns),
+      schema::foreign_keys_from_rows(foreign_keys),
+      schema::indexes_from_rows(indexes),
+    ))
+  }
+
+  async fn load_ddl(&self, kind: ObjectKind, schema: &str, name: &str) -> Result<String> {
+    let pool = self.pool.clone().unwrap();
+    let (schema_literal, name_literal) = (schema::literal(schema), schema::literal(name));
+    let query = match kind {
+      ObjectKind::Table => return table_ddl(pool, schema, name).await,
+      ObjectKind::View | ObjectKind::MaterializedView => format!(
+        "select format(E'CREATE %s %I.%I AS\\n%s', case c.relkind when 'm' then 'MATERIALIZED VIEW' else 'VIEW' end,
+          n.nspname, c.relname, pg_get_viewdef(c.oid, true))
+        from pg_class c join pg_namespace n on n.oid = c.relnamespace
+        where n.nspname = {schema_literal} and c.relname = {name_literal} and c.relkind in ('v', 'm')"
+      ),
+      ObjectKind::Function | ObjectKind::Procedure => format!(
+        "select pg_get_functiondef(p.oid)
+        from pg_proc p join pg_namespace n on n.oid = p.pronamespace
+        where n.nspname = {schema_literal} and p.proname = {name_literal}
+        order by p.oid"
+      ),
+      ObjectKind::Sequence => format!(
+        "select format('CREATE SEQUENCE %I.%I AS %s INCREMENT BY %s MINVALUE %s MAXVALUE %s START WITH %s CACHE %s%s',
+          schemaname, sequencename, data_type, increment_by, min_value, max_value, start_value, cache_size,
+          case when cycle then ' CYCLE' else '' end)
+        from pg_sequences
+        where schemaname = {schema_literal} and sequencename = {name_literal}"
+      ),
+      ObjectKind::Trigger => format!(
+        "select pg_get_triggerdef(t.oid, true)
+        from pg_trigger t
+        join pg_class c on c.oid = t.tgrelid
+        join pg_namespace n on n.oid = c.relnamespace
+        where n.nspname = {schema_literal} and t.tgname = {name_literal}"
+      ),
+      ObjectKind::Enum => format!(
+        "select format('CREATE TYPE %I.%I AS ENUM (%s)', n.nspname, t.typname,
+          string_agg(quote_literal(e.enumlabel), ', ' order by e.enumsortorder))
+        from pg_type t
+        join pg_namespace n on n.oid = t.typnamespace
+        join pg_enum e on e.enumtypid = t.oid
+        where n.nspname = {schema_literal} and t.typname = {name_literal}
+        group by n.nspname, t.typname"
+      ),
+    };
+    let rows = query_with_pool(pool, query).await?;
+    match ddl::script(rows.rows.iter().filter_map(|row| schema::text(&row[0]))) {
+      ddl if ddl.is_empty() => Err(eyre::Report::msg(format!("Could not find the {kind} {schema}.{name}"))),
+      ddl => Ok(ddl),
+    }
+  }
+
   fn preview_rows_query(&self, schema: &str, table: &str) -> String {
     format!("select * from \"{schema}\".\"{table}\" limit 100")
   }
@@ -256,18 +627,86 @@ impl Database for PostgresDriver<'_> {
   fn preview_policies_query(&self, schema: &str, table: &str) -> String {
     format!("select * from pg_policies where schemaname = '{schema}' and tablename = '{table}'")
   }
+
+  fn preview_definition_query(&self, schema: &str, view: &str) -> String {
+    format!("select pg_get_viewdef('\"{schema}\".\"{view}\"'::regclass, true) as definition")
+  }
+
+  fn preview_source_query(&self, schema: &str, function: &str) -> String {
+    format!(
+      "select pg_get_functiondef(p.oid) as source
+      from pg_proc p join pg_namespace n on n.oid = p.pronamespace
+      where n.nspname = '{schema}' and p.proname = '{function}'"
+    )
+  }
+
+  fn preview_sequence_query(&self, schema: &str, sequence: &str) -> String {
+    format!("select last_value, * from pg_sequences where schemaname = '{schema}' and sequencename = '{sequence}'")
+  }
+
+  fn preview_trigger_query(&self, schema: &str, trigger: &str) -> String {
+    format!(
+      "select c.relname as table_name, pg_get_triggerdef(t.oid, true) as definition,
+        pg_get_functiondef(t.tgfoid) as function_source
+      from pg_trigger t
+      join pg_class c on c.oid = t.tgrelid
+      join pg_namespace n on n.oid = c.relnamespace
+      where n.nspname = '{schema}' and t.tgname = '{trigger}'"
+    )
+  }
+
+  fn preview_enum_query(&self, schema: &str, name: &str) -> String {
+    format!(
+      "select e.enumlabel as value
+      from pg_enum e
+      join pg_type t on t.oid = e.enumtypid
+      join pg_namespace n on n.oid = t.typnamespace
+      where n.nspname = '{schema}' and t.typname = '{name}'
+      order by e.enumsortorder"
+    )
+  }
 }
 
 impl PostgresDriver<'_> {
   pub fn new() -> Self {
-    Self { pool: None, task: None, querying_conn: None, querying_pid: None }
+    Self {
+      pool: None,
+      task: None,
+      querying_conn: None,
+      querying_pid: None,
+      session_conn: None,
+      session_streams: CancellationToken::new(),
+      listener: None,
+    }
+  }
+
+  // if the transaction can't be ended, the connection is closed instead of
+  // going back to the pool with the transaction still open
+  async fn end_session_tx(&mut self, statement: &str) -> Result<()> {
+    let Some(conn) = self.session_conn.take() else {
+      return Ok(());
+    };
+    self.session_streams.cancel();
+    let mut conn = conn.lock_owned().await;
+    let result = conn.as_mut().execute(sqlx::raw_sql(statement)).await;
+    if result.is_err() {
+      conn.close_on_drop();
+    }
+    result?;
+    Ok(())
   }
 
   fn build_connection_opts(
     args: crate::cli::Cli,
   ) -> Result<<<sqlx::Postgres as sqlx::Database>::Connection as sqlx::Connection>::Options> {
     match args.connection_url {
-      Some(url) => Ok(PgConnectOptions::from_str(&url)?),
+      Some(url) => {
+        let mut opts = PgConnectOptions::from_str(&url)?;
+        if let Some(socket) = &args.socket {
+          opts = opts.socket(socket);
+        }
+        Ok(with_tls(opts, &args.tls))
+      },
       None => {
         let mut opts = PgConnectOptions::new();
 
@@ -295,7 +734,9 @@ impl PostgresDriver<'_> {
           }
         }
 
-        if let Some(host) = args.host {
+        if let Some(socket) = &args.socket {
+          opts = opts.socket(socket);
+        } else if let Some(host) = args.host {
           opts = opts.host(&host);
         } else {
           let mut host: String = String::new();
@@ -308,9 +749,11 @@ impl PostgresDriver<'_> {
           }
         }
 
+        // postgres sockets are named after the port, so it's still used
+        // with one, but left at its default unless given
         if let Some(port) = args.port {
           opts = opts.port(port);
-        } else {
+        } else if args.socket.is_none() {
           let mut port: String = String::new();
           print!("port (ex. 5432): ");
           io::stdout().flush().unwrap();
@@ -334,21 +777,230 @@ impl PostgresDriver<'_> {
           }
         }
 
-        Ok(opts)
+        Ok(with_tls(opts, &args.tls))
       },
     }
   }
 }
 
+// settings given explicitly win over any in the connection url
+fn with_tls(mut opts: PgConnectOptions, tls: &TlsOptions) -> PgConnectOptions {
+  if let Some(mode) = tls.ssl_mode {
+    opts = opts.ssl_mode(match mode {
+      SslMode::Disable => PgSslMode::Disable,
+      SslMode::Prefer => PgSslMode::Prefer,
+      SslMode::Require => PgSslMode::Require,
+      SslMode::VerifyCa => PgSslMode::VerifyCa,
+      SslMode::VerifyFull => PgSslMode::VerifyFull,
+    });
+  }
+  if let Some(root_cert) = &tls.ssl_root_cert {
+    opts = opts.ssl_root_cert(root_cert);
+  }
+  if let Some(cert) = &tls.ssl_cert {
+    opts = opts.ssl_client_cert(cert);
+  }
+  if let Some(key) = &tls.ssl_key {
+    opts = opts.ssl_client_key(key);
+  }
+  opts
+}
+
+// runs pg_cancel_backend or pg_terminate_backend, which return false when
+// there's no such backend
+async fn signal_backend(pool: &sqlx::Pool<Postgres>, function: &str, pid: &str) -> Result<bool> {
+  let pid = pid.parse::<i32>()?;
+  let row = sqlx::query(&format!("SELECT {function}($1)")).bind(pid).fetch_one(pool).await?;
+  Ok(row.try_get::<bool, _>(0)?)
+}
+
+// reconstructs a table's CREATE statement from the catalogs, followed by
+// the indexes that aren't behind one of its constraints and its comments
+async fn table_ddl(pool: Arc<sqlx::Pool<Postgres>>, schema: &str, table: &str) -> Result<String> {
+  let table_filter = format!(
+    "where n.nspname = {} and c.relname = {} and c.relkind in ('r', 'p')",
+    schema::literal(schema),
+    schema::literal(table)
+  );
+  // narrows a catalog down to the table, given the column holding its oid
+  let relation = |oid: &str| {
+    format!("join pg_class c on c.oid = {oid} join pg_namespace n on n.oid = c.relnamespace {table_filter}")
+  };
+  let name = query_with_pool(
+    pool.clone(),
+    format!(
+      "select format('%I.%I', n.nspname, c.relname), obj_description(c.oid, 'pg_class')
+      from pg_class c join pg_namespace n on n.oid = c.relnamespace
+      {table_filter}"
+    ),
+  )
+  .await?;
+  let Some(row) = name.rows.first() else {
+    return Err(eyre::Report::msg(format!("Could not find the table {schema}.{table}")));
+  };
+  let (name, comment) = (schema::text(&row[0]).unwrap_or_default(), schema::text(&row[1]));
+  let columns = query_with_pool(
+    pool.clone(),
+    format!(
+      "select format('%I %s%s%s', a.attname, format_type(a.atttypid, a.atttypmod),
+        case
+          when a.attidentity = 'a' then ' GENERATED ALWAYS AS IDENTITY'
+          when a.attidentity = 'd' then ' GENERATED BY DEFAULT AS IDENTITY'
+          when a.attgenerated = 's' then format(' GENERATED ALWAYS AS (%s) STORED', pg_get_expr(d.adbin, d.adrelid))
+          when d.adbin is not null then ' DEFAULT ' || pg_get_expr(d.adbin, d.adrelid)
+          else ''
+        end,
+        case when a.attnotnull then ' NOT NULL' else '' end)
+      from pg_attribute a
+      left join pg_attrdef d on d.adrelid = a.attrelid and d.adnum = a.attnum
+      {}
+      and a.attnum > 0 and not a.attisdropped
+      order by a.attnum",
+      relation("a.attrelid")
+    ),
+  )
+  .await?;
+  // newer versions of postgres also keep NOT NULL as constraints, which the
+  // columns already have
+  let constraints = query_with_pool(
+    pool.clone(),
+    format!(
+      "select format('CONSTRAINT %I %s', con.conname, pg_get_constraintdef(con.oid, true))
+      from pg_constraint con
+      {}
+      and con.contype <> 'n'
+      order by case con.contype when 'p' then 0 when 'u' then 1 when 'f' then 2 else 3 end, con.conname",
+      relation("con.conrelid")
+    ),
+  )
+  .await?;
+  let indexes = query_with_pool(
+    pool.clone(),
+    format!(
+      "select pg_get_indexdef(i.indexrelid)
+      from pg_index i
+      {}
+      and not exists (
+        select 1 from pg_constraint con
+        where con.conindid = i.indexrelid and con.conrelid = i.indrelid and con.contype in ('p', 'u', 'x')
+      )
+      order by i.indexrelid",
+      relation("i.indrelid")
+    ),
+  )
+  .await?;
+  let column_comments = query_with_pool(
+    pool,
+    format!(
+      "select format('COMMENT ON COLUMN %I.%I.%I IS %L', n.nspname, c.relname, a.attname, d.description)
+      from pg_attribute a
+      join pg_description d on d.objoid = a.attrelid and d.classoid = 'pg_class'::regclass and d.objsubid = a.attnum
+      {}
+      order by a.attnum",
+      relation("a.attrelid")
+    ),
+  )
+  .await?;
+  let text = |rows: Rows| rows.rows.iter().filter_map(|row| schema::text(&row[0])).collect::<Vec<_>>();
+  let elements = [text(columns), text(constraints)].concat();
+  let mut statements = text(indexes);
+  if let Some(comment) = comment {
+    statements.push(format!("COMMENT ON TABLE {name} IS '{}'", comment.replace('\'', "''")));
+  }
+  statements.extend(text(column_comments));
+  Ok(ddl::create_table(&name, &elements, &statements))
+}
+
 async fn query_with_pool(pool: Arc<sqlx::Pool<Postgres>>, query: String) -> Result<Rows> {
   query_with_stream(&*pool.clone(), &query).await
 }
 
+fn log_rows(rows: &Result<Rows>) {
+  match rows {
+    Ok(rows) => {
+      log::info!("{:?} rows, {:?} affected", rows.rows.len(), rows.rows_affected);
+    },
+    Err(e) => {
+      log::error!("{e:?}");
+    },
+  };
+}
+
+// a cursor keeps the rows that haven't been asked for yet on the server. it
+// only lives as long as the transaction it's declared in, which is committed
+// once the rows stop being read. queries that can't be declared as a cursor
+// (e.g. ones with data-modifying CTEs) are streamed straight off the socket.
+// the cursor lives in a transaction of its own, which holds on to its
+// snapshot while a partly read result is on screen, so it's committed once
+// no more rows have been asked for in a while
+async fn stream_query(
+  mut conn: OwnedMutexGuard<PoolConnection<Postgres>>,
+  query: String,
+  use_cursor: bool,
+  pages: PageSender,
+) {
+  let declared = use_cursor
+    && match conn
+      .as_mut()
+      .execute(sqlx::raw_sql(&format!("BEGIN; DECLARE rainfrog_cursor NO SCROLL CURSOR FOR {query}")))
+      .await
+    {
+      Ok(_) => true,
+      Err(e) => {
+        log::warn!("Couldn't declare cursor, streaming without one: {e:?}");
+        if let Err(e) = conn.as_mut().execute(sqlx::raw_sql("ROLLBACK")).await {
+          log::error!("{e:?}");
+        }
+        false
+      },
+    };
+  if declared {
+    let fetch = format!("FETCH FORWARD {} FROM rainfrog_cursor", pages.page_size());
+    let pages = pages.idle_timeout(CURSOR_IDLE_TIMEOUT);
+    pages.serve(cursor_rows(conn.as_mut(), &fetch), row_to_vec, get_headers).await;
+    // committing closes the cursor, or rolls back if fetching failed
+    if let Err(e) = conn.as_mut().execute(sqlx::raw_sql("COMMIT")).await {
+      log::error!("{e:?}");
+    }
+  } else {
+    pages.serve(sqlx::raw_sql(&query).fetch(conn.as_mut()), row_to_vec, get_headers).await;
+  }
+}
+
+// reads rows out of the cursor, fetching another page from the server
+// only once every row of the previous one has been read.
+fn cursor_rows<'c>(conn: &'c mut PgConnection, fetch: &'c str) -> BoxStream<'c, Result<PgRow, sqlx::Error>> {
+  futures::stream::unfold((conn, fetch, VecDeque::new()), next_cursor_row).boxed()
+}
+
+type CursorState<'c> = (&'c mut PgConnection, &'c str, VecDeque<PgRow>);
+
+async fn next_cursor_row(
+  (conn, fetch, mut buffer): CursorState<'_>,
+) -> Option<(Result<PgRow, sqlx::Error>, CursorState<'_>)> {
+  if buffer.is_empty() {
+    match conn.fetch_all(sqlx::raw_sql(fetch)).await {
+      Ok(rows) => buffer.extend(rows),
+      Err(e) => return Some((Err(e), (conn, fetch, buffer))),
+    }
+  }
+  buffer.pop_front().map(|row| (Ok(row), (conn, fetch, buffer)))
+}
+
 async fn query_with_conn(conn: &mut PgConnection, query: String) -> Result<Rows> {
   query_with_stream(conn, &query).await
 }
 
 async fn query_with_stream<'a, E>(e: E, query: &'a str) -> Result<Rows>
+where
+  E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+{
+  query_with_capped_stream(e, query, usize::MAX).await
+}
+
+// rows past the cap are read but not kept, so that a statement returning
+// them still runs to the end
+async fn query_with_capped_stream<'a, E>(e: E, query: &'a str, row_cap: usize) -> Result<Rows>
 where
   E: sqlx::Executor<'a, Database = sqlx::Postgres>,
 {
@@ -364,7 +1016,9 @@ where
       },
       Ok(Either::Right(row)) => {
         // For SELECT queries
-        query_rows.push(row_to_vec(&row));
+        if query_rows.len() < row_cap {
+          query_rows.push(row_to_vec(&row));
+        }
         if headers.is_empty() {
           headers = get_headers(&row);
         }
@@ -375,6 +1029,58 @@ where
   Ok(Rows { rows_affected: query_rows_affected, headers, rows: query_rows })
 }
 
+// runs one statement of a transaction. UPDATE and DELETE return the rows
+// they change, so they can be previewed before the transaction is committed
+async fn tx_statement(
+  tx: PostgresTransaction<'static>,
+  query: String,
+  statement_type: Statement,
+  row_cap: usize,
+) -> (QueryResultsWithMetadata, Option<TxPreview>, PostgresTransaction<'static>) {
+  if let Some(queries) = super::get_tx_preview_queries(&statement_type, Driver::Postgres) {
+    let (results, tx) = preview_with_tx(tx, &queries, row_cap).await;
+    return match results {
+      Ok((selected, returned)) => {
+        log::info!("{:?} rows affected", returned.rows_affected);
+        let rows = Rows { headers: vec![], rows: vec![], rows_affected: returned.rows_affected };
+        let preview = TxPreview::new(statement_type.clone(), selected, returned, row_cap);
+        (
+          QueryResultsWithMetadata { results: Ok(rows), statement_type: Some(statement_type), stream: None },
+          Some(preview),
+          tx,
+        )
+      },
+      Err(e) => {
+        log::error!("{e:?}");
+        (QueryResultsWithMetadata { results: Err(e), statement_type: Some(statement_type), stream: None }, None, tx)
+      },
+    };
+  }
+  let (results, tx) = query_with_tx(tx, &query).await;
+  match results {
+    Ok(Either::Left(rows_affected)) => {
+      log::info!("{rows_affected:?} rows affected");
+      (
+        QueryResultsWithMetadata {
+          results: Ok(Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) }),
+          statement_type: Some(statement_type),
+          stream: None,
+        },
+        None,
+        tx,
+      )
+    },
+    Ok(Either::Right(rows)) => {
+      log::info!("{:?} rows affected", rows.rows_affected);
+      (QueryResultsWithMetadata { results: Ok(rows), statement_type: Some(statement_type), stream: None }, None, tx)
+    },
+    Err(e) => {
+      log::error!("{e:?}");
+      (QueryResultsWithMetadata { results: Err(e), statement_type: Some(statement_type), stream: None }, None, tx)
+    },
+  }
+}
+
 async fn query_with_tx<'a>(
   mut tx: PostgresTransaction<'static>,
   query: &str,
@@ -386,8 +1092,10 @@ where
   let first_query = super::get_first_query(query.to_string(), Driver::Postgres);
   match first_query {
     Ok((first_query, statement_type)) => match statement_type {
-      Statement::Explain { .. } => {
-        let result = query_with_stream(&mut *tx, &first_query).await;
+      // queries in a script that runs in a transaction keep their rows
+      Statement::Explain { .. } | Statement::Query(_) => {
+        let query = super::plan::structured_explain(&statement_type, Driver::Postgres).unwrap_or(first_query);
+        let result = query_with_stream(&mut *tx, &query).await;
         match result {
           Ok(result) => (Ok(Either::Right(result)), tx),
           Err(e) => (Err(e), tx),
@@ -405,6 +1113,45 @@ where
   }
 }
 
+// runs each statement with its parameters bound as text, adding up the rows
+// they change
+async fn execute_with_params(
+  mut tx: PostgresTransaction<'static>,
+  statements: &[ParameterizedStatement],
+) -> (Result<u64>, PostgresTransaction<'static>) {
+  let mut rows_affected = 0;
+  for statement in statements {
+    let query = statement.params.iter().fold(sqlx::query(&statement.sql), |query, param| query.bind(param.clone()));
+    let result = match query.execute(&mut *tx).await {
+      Ok(result) => edit::expect_one_row(statement, result.rows_affected()),
+      Err(e) => Err(e.into()),
+    };
+    match result {
+      Ok(()) => rows_affected += 1,
+      Err(e) => return (Err(e), tx),
+    }
+  }
+  (Ok(rows_affected), tx)
+}
+
+// runs the SELECT that captures the rows an UPDATE will change, and then the
+// statement itself, returning what each of them returned
+async fn preview_with_tx(
+  mut tx: PostgresTransaction<'static>,
+  queries: &TxPreviewQueries,
+  row_cap: usize,
+) -> (Result<(Option<Rows>, Rows)>, PostgresTransaction<'static>) {
+  let selected = match queries.capped_select(row_cap) {
+    Some(select) => match query_with_stream(&mut *tx, &select).await {
+      Ok(rows) => Some(rows),
+      Err(e) => return (Err(e), tx),
+    },
+    None => None,
+  };
+  let result = query_with_capped_stream(&mut *tx, &queries.statement, row_cap.saturating_add(1)).await;
+  (result.map(|returned| (selected, returned)), tx)
+}
+
 fn get_headers(row: &<sqlx::Postgres as sqlx::Database>::Row) -> Headers {
   row
     .columns()
@@ -413,184 +1160,99 @@ fn get_headers(row: &<sqlx::Postgres as sqlx::Database>::Row) -> Headers {
     .collect()
 }
 
-fn row_to_vec(row: &<sqlx::Postgres as sqlx::Database>::Row) -> Vec<String> {
-  row.columns().iter().map(|col| parse_value(row, col).unwrap().string).collect()
+fn row_to_vec(row: &<sqlx::Postgres as sqlx::Database>::Row) -> Vec<Value> {
+  row.columns().iter().map(|col| parse_value(row, col)).collect()
+}
+
+fn decode<'r, T>(row: &'r PgRow, col: &<Postgres as sqlx::Database>::Column, to_value: impl FnOnce(T) -> Value) -> Value
+where
+  T: sqlx::Decode<'r, Postgres> + sqlx::Type<Postgres>,
+{
+  row.try_get::<T, usize>(col.ordinal()).map_or(Value::Unknown("_ERROR_".to_string()), to_value)
+}
+
+fn decode_unchecked<'r, T>(
+  row: &'r PgRow,
+  col: &<Postgres as sqlx::Database>::Column,
+  to_value: impl FnOnce(T) -> Value,
+) -> Value
+where
+  T: sqlx::Decode<'r, Postgres>,
+{
+  row.try_get_unchecked::<T, usize>(col.ordinal()).map_or(Value::Unknown("_ERROR_".to_string()), to_value)
+}
+
+fn array<T: std::string::ToString>(received: Vec<T>) -> Value {
+  Value::Unknown(vec_to_string(received))
 }
 
 // parsed based on https://docs.rs/sqlx/latest/sqlx/postgres/types/index.html
-fn parse_value(row: &<Postgres as sqlx::Database>::Row, col: &<Postgres as sqlx::Database>::Column) -> Option<Value> {
+fn parse_value(row: &<Postgres as sqlx::Database>::Row, col: &<Postgres as sqlx::Database>::Column) -> Value {
   let col_type = col.type_info().to_string();
   if row.try_get_raw(col.ordinal()).is_ok_and(|v| v.is_null()) {
-    return Some(Value { parse_error: false, string: "NULL".to_string(), is_null: true });
+    return Value::Null;
   }
   match col_type.to_uppercase().as_str() {
-    "TIMESTAMPTZ" => Some(row.try_get::<chrono::DateTime<chrono::Utc>, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "TIMESTAMP" => Some(row.try_get::<chrono::NaiveDateTime, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "DATE" => Some(row.try_get::<chrono::NaiveDate, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "TIME" => Some(row.try_get::<chrono::NaiveTime, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "UUID" => Some(row.try_get::<Uuid, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "INET" | "CIDR" => Some(row.try_get::<std::net::IpAddr, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "JSON" | "JSONB" => Some(row.try_get::<serde_json::Value, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "BOOL" => Some(row.try_get::<bool, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "SMALLINT" | "SMALLSERIAL" | "INT2" => Some(row.try_get::<i16, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "INT" | "SERIAL" | "INT4" => Some(row.try_get::<i32, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "BIGINT" | "BIGSERIAL" | "INT8" => Some(row.try_get::<i64, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "REAL" | "FLOAT4" => Some(row.try_get::<f32, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "DOUBLE PRECISION" | "FLOAT8" => Some(row.try_get::<f64, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "TEXT" | "VARCHAR" | "NAME" | "CITEXT" | "BPCHAR" | "CHAR" => {
-      Some(row.try_get::<String, usize>(col.ordinal()).map_or(
-        Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-        |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-      ))
-    },
-    "BYTEA" => Some(row.try_get::<Vec<u8>, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value {
-        parse_error: false,
-        string: received.iter().fold(String::new(), |mut output, b| {
-          let _ = write!(output, "{b:02X}");
-          output
-        }),
-        is_null: false,
-      },
-    )),
-    "VOID" => Some(Value { parse_error: false, string: "".to_string(), is_null: false }),
+    "TIMESTAMPTZ" => decode(row, col, |v: chrono::DateTime<chrono::Utc>| Value::Temporal(v.to_string())),
+    "TIMESTAMP" => decode(row, col, |v: chrono::NaiveDateTime| Value::Temporal(v.to_string())),
+    "DATE" => decode(row, col, |v: chrono::NaiveDate| Value::Temporal(v.to_string())),
+    "TIME" => decode(row, col, |v: chrono::NaiveTime| Value::Temporal(v.to_string())),
+    "TIMETZ" | "INTERVAL" => decode_unchecked(row, col, Value::Temporal),
+    "UUID" => decode(row, col, |v: Uuid| Value::Text(v.to_string())),
+    "INET" | "CIDR" => decode(row, col, |v: std::net::IpAddr| Value::Text(v.to_string())),
+    "JSON" | "JSONB" => decode(row, col, Value::Json),
+    "BOOL" => decode(row, col, Value::Bool),
+    "SMALLINT" | "SMALLSERIAL" | "INT2" => decode(row, col, |v: i16| Value::Int(v.into())),
+    "INT" | "SERIAL" | "INT4" => decode(row, col, |v: i32| Value::Int(v.into())),
+    "BIGINT" | "BIGSERIAL" | "INT8" => decode(row, col, |v: i64| Value::Int(v.into())),
+    // go through the f32's own formatting so it doesn't pick up digits it never had
+    "REAL" | "FLOAT4" => decode(row, col, |v: f32| Value::Float(v.to_string().parse().unwrap_or(v.into()))),
+    "DOUBLE PRECISION" | "FLOAT8" => decode(row, col, Value::Float),
+    "NUMERIC" => decode_unchecked(row, col, Value::Decimal),
+    "TEXT" | "VARCHAR" | "NAME" | "CITEXT" | "BPCHAR" | "CHAR" => decode(row, col, Value::Text),
+    "BYTEA" => decode(row, col, Value::Bytes),
+    "VOID" => Value::Text("".to_string()),
     _ if col_type.to_uppercase().ends_with("[]") => {
       let array_type = col_type.to_uppercase().replace("[]", "");
       match array_type.as_str() {
-        "TIMESTAMPTZ" => Some(row.try_get::<Vec<chrono::DateTime<chrono::Utc>>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "TIMESTAMP" => Some(row.try_get::<Vec<chrono::NaiveDateTime>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "DATE" => Some(row.try_get::<Vec<chrono::NaiveDate>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "TIME" => Some(row.try_get::<Vec<chrono::NaiveTime>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "UUID" => Some(row.try_get::<Vec<Uuid>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "INET" | "CIDR" => Some(row.try_get::<Vec<std::net::IpAddr>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "JSON" | "JSONB" => Some(row.try_get::<Vec<serde_json::Value>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "BOOL" => Some(row.try_get::<Vec<bool>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "SMALLINT" | "SMALLSERIAL" | "INT2" => Some(row.try_get::<Vec<i16>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "INT" | "SERIAL" | "INT4" => Some(row.try_get::<Vec<i32>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "BIGINT" | "BIGSERIAL" | "INT8" => Some(row.try_get::<Vec<i64>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "REAL" | "FLOAT4" => Some(row.try_get::<Vec<f32>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "DOUBLE PRECISION" | "FLOAT8" => Some(row.try_get::<Vec<f64>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-        )),
-        "TEXT" | "VARCHAR" | "NAME" | "CITEXT" | "BPCHAR" | "CHAR" => {
-          Some(row.try_get::<Vec<String>, usize>(col.ordinal()).map_or(
-            Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-            |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-          ))
-        },
-        "BYTEA" => Some(row.try_get::<Vec<u8>, usize>(col.ordinal()).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value {
-            parse_error: false,
-            string: received.iter().fold(String::new(), |mut output, b| {
-              let _ = write!(output, "{b:02X}");
-              output
-            }),
-            is_null: false,
-          },
-        )),
-        _ => {
-          // try to cast custom or other types to strings
-          Some(row.try_get_unchecked::<Vec<String>, usize>(col.ordinal()).map_or(
-            Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-            |received| Value { parse_error: false, string: vec_to_string(received), is_null: false },
-          ))
-        },
+        "TIMESTAMPTZ" => decode(row, col, array::<chrono::DateTime<chrono::Utc>>),
+        "TIMESTAMP" => decode(row, col, array::<chrono::NaiveDateTime>),
+        "DATE" => decode(row, col, array::<chrono::NaiveDate>),
+        "TIME" => decode(row, col, array::<chrono::NaiveTime>),
+        "UUID" => decode(row, col, array::<Uuid>),
+        "INET" | "CIDR" => decode(row, col, array::<std::net::IpAddr>),
+        "JSON" | "JSONB" => decode(row, col, array::<serde_json::Value>),
+        "BOOL" => decode(row, col, array::<bool>),
+        "SMALLINT" | "SMALLSERIAL" | "INT2" => decode(row, col, array::<i16>),
+        "INT" | "SERIAL" | "INT4" => decode(row, col, array::<i32>),
+        "BIGINT" | "BIGSERIAL" | "INT8" => decode(row, col, array::<i64>),
+        "REAL" | "FLOAT4" => decode(row, col, array::<f32>),
+        "DOUBLE PRECISION" | "FLOAT8" => decode(row, col, array::<f64>),
+        "TEXT" | "VARCHAR" | "NAME" | "CITEXT" | "BPCHAR" | "CHAR" => decode(row, col, array::<String>),
+        "BYTEA" => decode(row, col, |received: Vec<u8>| Value::Unknown(Value::Bytes(received).to_string())),
+        // try to cast custom or other types to strings
+        _ => decode_unchecked(row, col, array::<String>),
       }
     },
-    _ => {
-      // try to cast custom or other types to strings
-      Some(row.try_get_unchecked::<String, usize>(col.ordinal()).map_or(
-        Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-        |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-      ))
-    },
+    // try to cast custom or other types to strings
+    _ => decode_unchecked(row, col, Value::Unknown),
   }
 }
 
 #[cfg(test)]
 mod tests {
+  use std::{collections::HashMap, path::PathBuf};
+
+  use clap::Parser;
   use sqlparser::{dialect::PostgreSqlDialect, parser::ParserError};
+  use sqlx::ConnectOptions;
 
   use super::*;
-  use crate::database::{ExecutionType, ParseError, get_execution_type, get_first_query};
+  use crate::cli::Cli;
+  use crate::database::{
+    ExecutionPolicy, ExecutionType, ParseError, StatementKind, TxPreviewQueries, get_execution_type, get_first_query,
+    get_queries, get_script, get_tx_preview_queries, is_streamable_query, is_unbounded_query,
+  };
 
   #[test]
   fn test_get_first_query() {
@@ -676,6 +1338,53 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_is_streamable_query() {
+    let parse = |query: &str| get_first_query(query.to_owned(), Driver::Postgres).unwrap().1;
+    assert!(!is_streamable_query(&parse("select * from users for update")));
+    assert!(!is_streamable_query(&parse("select * from users where id = 1 for share skip locked")));
+    assert!(is_streamable_query(&parse("select * from users")));
+    assert!(!is_streamable_query(&parse("update users set name = 'a'")));
+  }
+
+  #[test]
+  fn test_get_queries() {
+    let queries =
+      get_queries("SELECT 1; UPDATE users SET name = 'John' WHERE id = 1;".to_owned(), Driver::Postgres).unwrap();
+    assert_eq!(
+      queries.iter().map(|(query, _)| query.as_str()).collect::<Vec<_>>(),
+      vec!["SELECT 1", "UPDATE users SET name = 'John' WHERE id = 1"]
+    );
+    assert!(matches!(queries[0].1, Statement::Query(_)));
+    assert!(matches!(queries[1].1, Statement::Update { .. }));
+
+    assert!(matches!(get_queries("   ".to_owned(), Driver::Postgres), Err(ParseError::EmptyQuery(_))));
+  }
+
+  #[test]
+  fn test_get_script_max_rows() {
+    let test_cases = vec![
+      ("SELECT * FROM users", "SELECT * FROM users LIMIT 100"),
+      ("SELECT * FROM users ORDER BY id", "SELECT * FROM users ORDER BY id LIMIT 100"),
+      ("SELECT 1 UNION SELECT 2", "SELECT 1 UNION SELECT 2 LIMIT 100"),
+      ("SELECT * FROM users LIMIT 5", "SELECT * FROM users LIMIT 5"),
+      ("SELECT * FROM users FETCH FIRST 5 ROWS ONLY", "SELECT * FROM users FETCH FIRST 5 ROWS ONLY"),
+      ("DELETE FROM users WHERE id = 1", "DELETE FROM users WHERE id = 1"),
+    ];
+    for (query, expected) in test_cases {
+      let script = get_script(query.to_owned(), false, Driver::Postgres, Some(100)).unwrap();
+      assert_eq!(script[0].0, expected, "Failed for query: {query}");
+    }
+
+    // the statement is left as it was written
+    let script = get_script("SELECT * FROM users".to_owned(), false, Driver::Postgres, Some(100)).unwrap();
+    assert!(is_unbounded_query(script[0].1.as_ref().unwrap()));
+    let script = get_script("SELECT * FROM users".to_owned(), false, Driver::Postgres, None).unwrap();
+    assert_eq!(script[0].0, "SELECT * FROM users");
+    let script = get_script("SELECT * FROM users".to_owned(), true, Driver::Postgres, Some(100)).unwrap();
+    assert_eq!(script[0].0, "SELECT * FROM users");
+  }
+
   #[test]
   fn test_execution_type_postgres() {
     let test_cases = vec![
@@ -688,14 +1397,200 @@ mod tests {
       ("EXPLAIN ANALYZE DROP TABLE users", ExecutionType::Confirm),
       ("EXPLAIN SELECT * FROM users", ExecutionType::Normal),
       ("EXPLAIN ANALYZE SELECT * FROM users WHERE id = 1", ExecutionType::Normal),
+      ("SELECT 1; SELECT 2", ExecutionType::Normal),
+      ("INSERT INTO users (name) VALUES ('John'); SELECT * FROM users", ExecutionType::Normal),
+      ("SELECT 1; DELETE FROM users WHERE id = 1", ExecutionType::Transaction),
+      ("UPDATE users SET name = 'a' WHERE id = 1; DELETE FROM users WHERE id = 2", ExecutionType::Transaction),
+      ("BEGIN; DELETE FROM users WHERE id = 1; COMMIT", ExecutionType::Confirm),
+      ("DELETE FROM users WHERE id = 1; DROP TABLE users", ExecutionType::Confirm),
+      ("DROP TABLE users; SELECT 1", ExecutionType::Confirm),
     ];
 
     for (query, expected) in test_cases {
       assert_eq!(
-        get_execution_type(query.to_string(), false, Driver::Postgres).unwrap().0,
+        get_execution_type(query.to_string(), false, Driver::Postgres, false, &ExecutionPolicy::default()).unwrap().0,
         expected,
         "Failed for query: {query}"
       );
     }
   }
+
+  #[test]
+  fn test_execution_type_read_only() {
+    for query in [
+      "SELECT * FROM users",
+      "EXPLAIN SELECT * FROM users",
+      "EXPLAIN DELETE FROM users",
+      "EXPLAIN ANALYZE SELECT * FROM users",
+      "EXPLAIN (ANALYZE false) DELETE FROM users WHERE id = 1",
+      "SHOW search_path",
+      "BEGIN; SELECT 1; COMMIT",
+      "START TRANSACTION READ ONLY; SELECT 1; COMMIT",
+      "WITH u AS (SELECT * FROM users) SELECT * FROM u UNION SELECT * FROM users",
+    ] {
+      let result = get_execution_type(query.to_string(), false, Driver::Postgres, true, &ExecutionPolicy::default());
+      assert_eq!(result.unwrap().0, ExecutionType::Normal, "Failed for query: {query}");
+    }
+    for query in [
+      "INSERT INTO users (name) VALUES ('John')",
+      "DELETE FROM users WHERE id = 1",
+      "EXPLAIN ANALYZE DELETE FROM users WHERE id = 1",
+      "EXPLAIN (ANALYZE) DELETE FROM users WHERE id = 1",
+      "EXPLAIN (ANALYZE true, BUFFERS) DELETE FROM users WHERE id = 1",
+      "START TRANSACTION READ WRITE",
+      "BEGIN ISOLATION LEVEL SERIALIZABLE, READ WRITE; SELECT 1; COMMIT",
+      "WITH d AS (DELETE FROM users WHERE id = 1 RETURNING *) SELECT * FROM d",
+      "WITH u AS (UPDATE users SET name = 'a' WHERE id = 1 RETURNING *) SELECT * FROM u",
+      "WITH i AS (INSERT INTO users (name) VALUES ('a') RETURNING *) SELECT * FROM i",
+      "SELECT * INTO users_backup FROM users",
+      "SELECT 1 UNION SELECT * INTO users_backup FROM users",
+      "CREATE TABLE t (id int)",
+      "SELECT 1; DROP TABLE users",
+    ] {
+      let result = get_execution_type(query.to_string(), true, Driver::Postgres, true, &ExecutionPolicy::default());
+      assert!(
+        result.is_err_and(|e| e.to_string().ends_with("can't be run on a read-only connection")),
+        "Failed for query: {query}"
+      );
+    }
+  }
+
+  #[test]
+  fn test_execution_type_policy() {
+    let policy = ExecutionPolicy::default();
+    for query in ["DELETE FROM users", "UPDATE users SET name = 'John'", "SELECT 1; DELETE FROM users"] {
+      let result = get_execution_type(query.to_string(), true, Driver::Postgres, false, &policy);
+      assert!(result.is_err(), "Failed for query: {query}");
+    }
+
+    let rules = std::collections::HashMap::from([
+      (StatementKind::Create, ExecutionType::Transaction),
+      (StatementKind::Drop, ExecutionType::Forbid),
+      (StatementKind::DeleteWithoutWhere, ExecutionType::Transaction),
+    ]);
+    let policy = ExecutionPolicy::new([&rules], true);
+    let test_cases = vec![
+      ("CREATE TABLE t (id int)", ExecutionType::Transaction),
+      ("DELETE FROM users", ExecutionType::Transaction),
+      ("INSERT INTO users (name) VALUES ('John')", ExecutionType::Confirm),
+      ("SELECT * FROM users", ExecutionType::Normal),
+    ];
+    for (query, expected) in test_cases {
+      assert_eq!(
+        get_execution_type(query.to_string(), false, Driver::Postgres, false, &policy).unwrap().0,
+        expected,
+        "Failed for query: {query}"
+      );
+    }
+    let result = get_execution_type("DROP TABLE users".to_string(), true, Driver::Postgres, false, &policy);
+    assert_eq!(result.unwrap_err().to_string(), "DROP is forbidden by the execution policy");
+  }
+
+  #[test]
+  fn test_tx_preview_queries() {
+    let test_cases = vec![
+      (
+        "UPDATE users SET name = 'John' WHERE id = 1",
+        Some(TxPreviewQueries {
+          select: Some("SELECT users.* FROM users WHERE id = 1".to_owned()),
+          statement: "UPDATE users SET name = 'John' WHERE id = 1 RETURNING *".to_owned(),
+        }),
+      ),
+      (
+        "UPDATE public.users AS u SET name = o.name FROM others o WHERE u.id = o.id",
+        Some(TxPreviewQueries {
+          select: Some("SELECT u.* FROM public.users AS u, others AS o WHERE u.id = o.id".to_owned()),
+          statement: "UPDATE public.users AS u SET name = o.name FROM others AS o WHERE u.id = o.id RETURNING u.*"
+            .to_owned(),
+        }),
+      ),
+      (
+        "UPDATE users SET name = 'John' RETURNING id",
+        Some(TxPreviewQueries {
+          select: Some("SELECT users.* FROM users".to_owned()),
+          statement: "UPDATE users SET name = 'John' RETURNING id".to_owned(),
+        }),
+      ),
+      (
+        "DELETE FROM users WHERE id = 1",
+        Some(TxPreviewQueries { select: None, statement: "DELETE FROM users WHERE id = 1 RETURNING *".to_owned() }),
+      ),
+      (
+        "DELETE FROM users u USING others o WHERE u.id = o.id",
+        Some(TxPreviewQueries {
+          select: None,
+          statement: "DELETE FROM users AS u USING others AS o WHERE u.id = o.id RETURNING u.*".to_owned(),
+        }),
+      ),
+      ("INSERT INTO users (name) VALUES ('John')", None),
+      ("EXPLAIN ANALYZE DELETE FROM users WHERE id = 1", None),
+    ];
+
+    for (query, expected) in test_cases {
+      let (_, statement) = get_first_query(query.to_owned(), Driver::Postgres).unwrap();
+      assert_eq!(get_tx_preview_queries(&statement, Driver::Postgres), expected, "Failed for query: {query}");
+    }
+
+    let (_, statement) =
+      get_first_query("UPDATE users SET name = o.name FROM others o WHERE users.id = o.id".to_owned(), Driver::Sqlite)
+        .unwrap();
+    assert_eq!(
+      get_tx_preview_queries(&statement, Driver::Sqlite),
+      Some(TxPreviewQueries {
+        select: Some("SELECT users.* FROM users, others AS o WHERE users.id = o.id".to_owned()),
+        statement: "UPDATE users SET name = o.name FROM others AS o WHERE users.id = o.id RETURNING *".to_owned(),
+      })
+    );
+  }
+
+  #[test]
+  fn test_build_connection_opts_tls() {
+    let args = Cli::parse_from([
+      "rainfrog",
+      "--url",
+      "postgres://app@localhost:5432/app?sslmode=require&sslrootcert=/etc/url-ca.pem",
+      "--ssl-mode",
+      "verify-full",
+      "--ssl-cert",
+      "/etc/client.pem",
+      "--ssl-key",
+      "/etc/client.key",
+    ]);
+    let url = PostgresDriver::build_connection_opts(args).unwrap().to_url_lossy();
+    let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    assert_eq!(params["sslmode"], "verify-full");
+    assert_eq!(params["sslrootcert"], "file: /etc/url-ca.pem");
+    assert_eq!(params["sslcert"], "file: /etc/client.pem");
+    assert_eq!(params["sslkey"], "file: /etc/client.key");
+
+    let args = Cli::parse_from(["rainfrog", "--url", "postgres://app@localhost:5432/app"]);
+    let opts = PostgresDriver::build_connection_opts(args).unwrap();
+    assert!(matches!(opts.get_ssl_mode(), PgSslMode::Prefer));
+  }
+
+  #[test]
+  fn test_build_connection_opts_socket() {
+    // nothing is prompted for, since the host and port aren't needed
+    let args = Cli::parse_from([
+      "rainfrog",
+      "--driver",
+      "postgres",
+      "--username",
+      "app",
+      "--password",
+      "secret",
+      "--database",
+      "app",
+      "--socket",
+      "/var/run/postgresql",
+    ]);
+    let opts = PostgresDriver::build_connection_opts(args).unwrap();
+    assert_eq!(opts.get_socket(), Some(&PathBuf::from("/var/run/postgresql")));
+    assert_eq!(opts.get_database(), Some("app"));
+
+    let args =
+      Cli::parse_from(["rainfrog", "--url", "postgres://app@localhost/app", "--socket", "/var/run/postgresql"]);
+    let opts = PostgresDriver::build_connection_opts(args).unwrap();
+    assert_eq!(opts.get_socket(), Some(&PathBuf::from("/var/run/postgresql")));
+  }
 }
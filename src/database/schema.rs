@@ -0,0 +1,286 @@
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use super::{Rows, Value};
+
+/// The kinds of objects listed in the menu. `load_tables()` only returns
+/// tables, views and materialized views.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+pub enum ObjectKind {
+  Table,
+  View,
+  #[strum(serialize = "materialized view")]
+  MaterializedView,
+  Function,
+  Procedure,
+  Sequence,
+  Trigger,
+  Enum,
+}
+
+impl ObjectKind {
+  pub fn plural(&self) -> String {
+    format!("{self}s")
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableInfo {
+  /// Empty for databases without schemas, like sqlite.
+  pub schema: String,
+  pub name: String,
+  pub kind: ObjectKind,
+  pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+  pub name: String,
+  /// The type as the database would write it, e.g. `character varying(20)`.
+  pub type_name: String,
+  pub nullable: bool,
+  /// The default expression, if the column has one.
+  pub default: Option<String>,
+  pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+  pub name: String,
+  pub columns: Vec<String>,
+  pub foreign_schema: String,
+  pub foreign_table: String,
+  /// In the same order as `columns`.
+  pub foreign_columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexInfo {
+  pub name: String,
+  /// Column names, or the expression for indexes on expressions.
+  pub columns: Vec<String>,
+  pub unique: bool,
+  pub primary: bool,
+}
+
+/// Everything `describe_table()` knows about a table or view.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableDetails {
+  /// In the order they were declared.
+  pub columns: Vec<ColumnInfo>,
+  /// Empty if the table has no primary key.
+  pub primary_key: Vec<String>,
+  pub foreign_keys: Vec<ForeignKey>,
+  pub indexes: Vec<IndexInfo>,
+}
+
+impl TableDetails {
+  /// Builds the details for drivers where the primary key is one of the indexes.
+  pub fn from_indexes(columns: Vec<ColumnInfo>, foreign_keys: Vec<ForeignKey>, indexes: Vec<IndexInfo>) -> Self {
+    let primary_key = indexes.iter().find(|i| i.primary).map(|i| i.columns.clone()).unwrap_or_default();
+    Self { columns, primary_key, foreign_keys, indexes }
+  }
+}
+
+/// Quotes a name so it can be compared against in a catalog query.
+pub fn literal(s: &str) -> String {
+  format!("'{}'", s.replace('\'', "''"))
+}
+
+// the catalog queries select their columns in the order these functions
+// read them, so that every driver can share them
+
+/// Expects rows of (schema, name, kind, comment), where kind is one of
+/// `table`, `view` or `materialized view`.
+pub fn tables_from_rows(rows: Rows) -> Vec<TableInfo> {
+  rows
+    .rows
+    .iter()
+    .map(|row| TableInfo {
+      schema: text(&row[0]).unwrap_or_default(),
+      name: text(&row[1]).unwrap_or_default(),
+      kind: text(&row[2]).and_then(|kind| kind.parse().ok()).unwrap_or(ObjectKind::Table),
+      comment: text(&row[3]),
+    })
+    .collect()
+}
+
+/// Expects rows of (name, type, nullable, default, comment).
+pub fn columns_from_rows(rows: Rows) -> Vec<ColumnInfo> {
+  rows
+    .rows
+    .iter()
+    .map(|row| ColumnInfo {
+      name: text(&row[0]).unwrap_or_default(),
+      type_name: text(&row[1]).unwrap_or_default(),
+      nullable: flag(&row[2]),
+      default: text(&row[3]),
+      comment: text(&row[4]),
+    })
+    .collect()
+}
+
+/// Expects rows of (constraint name, column, foreign schema, foreign table,
+/// foreign column), ordered by constraint and then column position.
+pub fn foreign_keys_from_rows(rows: Rows) -> Vec<ForeignKey> {
+  let mut keys: Vec<ForeignKey> = vec![];
+  for row in rows.rows.iter() {
+    let name = text(&row[0]).unwrap_or_default();
+    let column = text(&row[1]).unwrap_or_default();
+    let foreign_column = text(&row[4]).unwrap_or_default();
+    match keys.last_mut() {
+      Some(key) if key.name == name => {
+        key.columns.push(column);
+        key.foreign_columns.push(foreign_column);
+      },
+      _ => keys.push(ForeignKey {
+        name,
+        columns: vec![column],
+        foreign_schema: text(&row[2]).unwrap_or_default(),
+        foreign_table: text(&row[3]).unwrap_or_default(),
+        foreign_columns: vec![foreign_column],
+      }),
+    }
+  }
+  keys
+}
+
+/// Expects rows of (index name, column, unique, primary), ordered by index
+/// and then column position.
+pub fn indexes_from_rows(rows: Rows) -> Vec<IndexInfo> {
+  let mut indexes: Vec<IndexInfo> = vec![];
+  for row in rows.rows.iter() {
+    let name = text(&row[0]).unwrap_or_default();
+    let column = text(&row[1]).unwrap_or_default();
+    match indexes.last_mut() {
+      Some(index) if index.name == name => index.columns.push(column),
+      _ => indexes.push(IndexInfo { name, columns: vec![column], unique: flag(&row[2]), primary: flag(&row[3]) }),
+    }
+  }
+  indexes
+}
+
+/// Expects rows with the column name first, ordered by position in the key.
+pub fn key_from_rows(rows: Rows) -> Vec<String> {
+  rows.rows.iter().filter_map(|row| text(&row[0])).collect()
+}
+
+pub(super) fn text(value: &Value) -> Option<String> {
+  match value {
+    Value::Null => None,
+    // some mysql versions return catalog strings as binary
+    Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+    value => Some(value.to_string()),
+  }
+}
+
+// catalogs report booleans as real booleans, integers, or text
+fn flag(value: &Value) -> bool {
+  match value {
+    Value::Bool(b) => *b,
+    Value::Int(i) => *i != 0,
+    value => matches!(text(value).as_deref(), Some("YES" | "yes" | "t" | "true" | "1")),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rows(rows: Vec<Vec<Value>>) -> Rows {
+    Rows { headers: vec![], rows, rows_affected: None }
+  }
+
+  fn t(s: &str) -> Value {
+    Value::Text(s.to_owned())
+  }
+
+  #[test]
+  fn test_tables_from_rows() {
+    let tables = tables_from_rows(rows(vec![
+      vec![t("public"), t("users"), t("table"), t("people")],
+      vec![t("public"), t("active_users"), t("view"), Value::Null],
+      vec![t("public"), t("stats"), t("materialized view"), Value::Null],
+    ]));
+    assert_eq!(
+      tables[0],
+      TableInfo {
+        schema: "public".to_owned(),
+        name: "users".to_owned(),
+        kind: ObjectKind::Table,
+        comment: Some("people".to_owned())
+      }
+    );
+    assert_eq!(tables[1].kind, ObjectKind::View);
+    assert_eq!(tables[1].comment, None);
+    assert_eq!(tables[2].kind, ObjectKind::MaterializedView);
+  }
+
+  #[test]
+  fn test_columns_from_rows() {
+    let columns = columns_from_rows(rows(vec![
+      vec![t("id"), t("integer"), Value::Bool(false), t("nextval('users_id_seq'::regclass)"), Value::Null],
+      vec![t("name"), t("text"), Value::Int(1), Value::Null, t("full name")],
+      vec![t("email"), t("varchar(20)"), t("YES"), Value::Null, Value::Bytes(b"contact".to_vec())],
+    ]));
+    assert!(!columns[0].nullable);
+    assert_eq!(columns[0].default.as_deref(), Some("nextval('users_id_seq'::regclass)"));
+    assert!(columns[1].nullable);
+    assert_eq!(columns[1].default, None);
+    assert_eq!(columns[1].comment.as_deref(), Some("full name"));
+    assert!(columns[2].nullable);
+    assert_eq!(columns[2].comment.as_deref(), Some("contact"));
+  }
+
+  #[test]
+  fn test_foreign_keys_from_rows() {
+    let keys = foreign_keys_from_rows(rows(vec![
+      vec![t("fk_order"), t("order_id"), t("shop"), t("orders"), t("id")],
+      vec![t("fk_product"), t("product_id"), t("shop"), t("products"), t("id")],
+      vec![t("fk_product"), t("variant"), t("shop"), t("products"), t("variant")],
+    ]));
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[0].columns, vec!["order_id"]);
+    assert_eq!(
+      keys[1],
+      ForeignKey {
+        name: "fk_product".to_owned(),
+        columns: vec!["product_id".to_owned(), "variant".to_owned()],
+        foreign_schema: "shop".to_owned(),
+        foreign_table: "products".to_owned(),
+        foreign_columns: vec!["id".to_owned(), "variant".to_owned()],
+      }
+    );
+  }
+
+  #[test]
+  fn test_indexes_and_primary_key() {
+    let indexes = indexes_from_rows(rows(vec![
+      vec![t("PRIMARY"), t("a"), Value::Int(1), Value::Int(1)],
+      vec![t("PRIMARY"), t("b"), Value::Int(1), Value::Int(1)],
+      vec![t("by_name"), t("lower(name)"), Value::Int(0), Value::Int(0)],
+    ]));
+    assert_eq!(indexes.len(), 2);
+    assert!(indexes[0].unique && indexes[0].primary);
+    assert_eq!(indexes[1].columns, vec!["lower(name)"]);
+    assert!(!indexes[1].unique);
+    let details = TableDetails::from_indexes(vec![], vec![], indexes);
+    assert_eq!(details.primary_key, vec!["a", "b"]);
+    assert!(TableDetails::from_indexes(vec![], vec![], vec![]).primary_key.is_empty());
+  }
+
+  #[test]
+  fn test_object_kind_names() {
+    assert_eq!("materialized view".parse::<ObjectKind>().unwrap(), ObjectKind::MaterializedView);
+    assert_eq!("function".parse::<ObjectKind>().unwrap(), ObjectKind::Function);
+    assert_eq!(ObjectKind::MaterializedView.plural(), "materialized views");
+    assert_eq!(ObjectKind::Trigger.plural(), "triggers");
+  }
+
+  #[test]
+  fn test_literal() {
+    assert_eq!(literal("users"), "'users'");
+    assert_eq!(literal("o'brien"), "'o''brien'");
+  }
+}
@@ -1,5 +1,4 @@
 use std::{
-  fmt::Write,
   io::{self, Write as _},
   str::FromStr,
   string::String,
@@ -11,58 +10,102 @@ use color_eyre::eyre::{self, Result};
 use futures::stream::StreamExt;
 use sqlparser::ast::Statement;
 use sqlx::{
-  Column, Either, Row, ValueRef,
-  sqlite::{Sqlite, SqliteConnectOptions, SqlitePoolOptions},
+  Column, Either, Executor, Row, ValueRef,
+  pool::PoolConnection,
+  sqlite::{Sqlite, SqliteConnectOptions, SqlitePoolOptions, SqliteRow},
   types::uuid,
 };
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio_util::sync::CancellationToken;
 
-use super::{Database, DbTaskResult, Driver, Header, Headers, QueryResultsWithMetadata, QueryTask, Rows, Value};
+use super::{
+  Database, DbTaskResult, Driver, Header, Headers, LockWait, Notification, ObjectKind, ParameterizedStatement,
+  QueryOptions, QueryResultsWithMetadata, QueryTask, Rows, ScriptErrorPolicy, ServerSession, TableDetails, TableInfo,
+  TxPreview, TxPreviewQueries, Value, ddl, edit, schema, stream::PageSender,
+};
 
 type SqliteTransaction<'a> = sqlx::Transaction<'a, Sqlite>;
-type TransactionTask<'a> = tokio::task::JoinHandle<(QueryResultsWithMetadata, SqliteTransaction<'a>)>;
+type TransactionTask<'a> =
+  tokio::task::JoinHandle<(Vec<QueryResultsWithMetadata>, Vec<TxPreview>, SqliteTransaction<'a>)>;
 enum SqliteTask<'a> {
   Query(QueryTask),
   TxStart(TransactionTask<'a>),
-  TxPending(Box<(SqliteTransaction<'a>, QueryResultsWithMetadata)>),
+  TxPending(Box<(SqliteTransaction<'a>, Vec<QueryResultsWithMetadata>)>),
 }
 
 #[derive(Default)]
 pub struct SqliteDriver<'a> {
   pool: Option<Arc<sqlx::Pool<Sqlite>>>,
   task: Option<SqliteTask<'a>>,
+  // pinned by `begin_session_tx()` for every query until the transaction ends
+  session_conn: Option<Arc<Mutex<PoolConnection<Sqlite>>>>,
+  // cancelled when the transaction ends, so that results still streaming
+  // from `session_conn` hand it back
+  session_streams: CancellationToken,
 }
 
 #[async_trait(?Send)]
 impl Database for SqliteDriver<'_> {
   async fn init(&mut self, args: crate::cli::Cli) -> Result<()> {
-    let opts = super::sqlite::SqliteDriver::<'_>::build_connection_opts(args)?;
+    let read_only = args.read_only;
+    let mut opts = super::sqlite::SqliteDriver::<'_>::build_connection_opts(args)?;
+    if read_only {
+      opts = opts.pragma("query_only", "ON");
+    }
     let pool = Arc::new(SqlitePoolOptions::new().max_connections(3).connect_with(opts).await?);
     self.pool = Some(pool);
     Ok(())
   }
 
-  // since it's possible for raw_sql to execute multiple queries in a single string,
-  // we only execute the first one and then drop the rest.
-  async fn start_query(&mut self, query: String, bypass_parser: bool) -> Result<()> {
-    let (first_query, statement_type) = match bypass_parser {
-      true => (query, None),
-      false => {
-        let (first, stmt) = super::get_first_query(query, Driver::Sqlite)?;
-        (first, Some(stmt))
-      },
-    };
+  // raw_sql would happily run every statement in a single string, but we
+  // run them one at a time so that each one gets its own results. rows of
+  // the last statement are streamed a page at a time, unless it locks them.
+  async fn start_query(&mut self, query: String, bypass_parser: bool, options: QueryOptions) -> Result<()> {
+    let mut queries = super::get_script(query, bypass_parser, Driver::Sqlite, options.max_rows)?;
     let pool = self.pool.clone().unwrap();
+    let session_conn = self.session_conn.clone();
+    let session_streams = session_conn.is_some().then(|| self.session_streams.clone());
     self.task = Some(SqliteTask::Query(tokio::spawn(async move {
-      let results = query_with_pool(pool, first_query.clone()).await;
-      match results {
-        Ok(ref rows) => {
-          log::info!("{:?} rows, {:?} affected", rows.rows.len(), rows.rows_affected);
-        },
-        Err(ref e) => {
-          log::error!("{e:?}");
+      // statements in a script share a connection, so that things like
+      // temp tables and pragmas carry over from one to the next
+      let conn = match session_conn {
+        Some(conn) => conn,
+        None => match pool.acquire().await {
+          Ok(conn) => Arc::new(Mutex::new(conn)),
+          Err(e) => {
+            log::error!("{e:?}");
+            return vec![QueryResultsWithMetadata { results: Err(e.into()), statement_type: None, stream: None }];
+          },
         },
       };
-      QueryResultsWithMetadata { results, statement_type: statement_type.clone() }
+      let mut conn = conn.lock_owned().await;
+      let mut results = vec![];
+      let last = queries.pop();
+      for (query, statement_type) in queries {
+        let rows = query_with_stream(conn.as_mut(), &query).await;
+        log_rows(&rows);
+        let failed = rows.is_err();
+        results.push(QueryResultsWithMetadata { results: rows, statement_type, stream: None });
+        if failed && options.on_error == ScriptErrorPolicy::Stop {
+          return results;
+        }
+      }
+      if let Some((query, statement_type)) = last {
+        let (rows, stream) = if statement_type.as_ref().is_some_and(super::is_streamable_query) {
+          let (stream, pages) = super::stream::row_stream(options.page_size, options.row_cap);
+          let pages = match session_streams {
+            Some(token) => pages.cancel_on(token),
+            None => pages,
+          };
+          tokio::spawn(stream_query(conn, query, pages));
+          stream.first_page().await
+        } else {
+          (query_with_stream(conn.as_mut(), &query).await, None)
+        };
+        log_rows(&rows);
+        results.push(QueryResultsWithMetadata { results: rows, statement_type, stream });
+      }
+      results
     })));
     Ok(())
   }
@@ -96,21 +139,14 @@ impl Database for SqliteDriver<'_> {
         if !handle.is_finished() {
           (DbTaskResult::Pending, Some(SqliteTask::TxStart(handle)))
         } else {
-          let (result, tx) = handle.await?;
-          let rows_affected = match &result.results {
-            Ok(rows) => rows.rows_affected,
-            _ => None,
-          };
-          match result {
-            // if tx failed to start, return the error immediately
-            QueryResultsWithMetadata { results: Err(e), statement_type } => {
-              log::error!("Transaction didn't start: {e:?}");
-              (DbTaskResult::Finished(QueryResultsWithMetadata { results: Err(e), statement_type }), None)
-            },
-            _ => (
-              DbTaskResult::ConfirmTx(rows_affected, result.statement_type.clone()),
-              Some(SqliteTask::TxPending(Box::new((tx, result)))),
-            ),
+          let (results, previews, tx) = handle.await?;
+          // if a statement failed, the transaction is rolled back as it's
+          // dropped, and the results are returned immediately
+          if results.iter().any(|result| result.results.is_err()) {
+            log::error!("Transaction failed, rolling back");
+            (DbTaskResult::Finished(results), None)
+          } else {
+            (super::confirm_tx(&results, previews), Some(SqliteTask::TxPending(Box::new((tx, results)))))
           }
         }
       },
@@ -120,45 +156,56 @@ impl Database for SqliteDriver<'_> {
     Ok(task_result)
   }
 
-  async fn start_tx(&mut self, query: String) -> Result<()> {
-    let (first_query, statement_type) = super::get_first_query(query, Driver::Sqlite)?;
+  async fn start_tx(&mut self, query: String, options: QueryOptions) -> Result<()> {
+    let queries = super::get_queries(query, Driver::Sqlite)?;
+    let mut tx = self.pool.as_mut().unwrap().begin().await?;
+    self.task = Some(SqliteTask::TxStart(tokio::spawn(async move {
+      let mut results = vec![];
+      let mut previews = vec![];
+      for (query, statement_type) in queries {
+        let (result, preview, next_tx) = tx_statement(tx, query, statement_type, options.row_cap).await;
+        tx = next_tx;
+        let failed = result.results.is_err();
+        results.push(result);
+        previews.extend(preview);
+        if failed {
+          break;
+        }
+      }
+      (results, previews, tx)
+    })));
+    Ok(())
+  }
+
+  async fn start_tx_with_params(&mut self, statements: Vec<ParameterizedStatement>) -> Result<()> {
+    let statement_type = statements
+      .first()
+      .and_then(|statement| super::get_first_query(statement.sql.clone(), Driver::Sqlite).ok())
+      .map(|(_, statement_type)| statement_type);
     let tx = self.pool.as_mut().unwrap().begin().await?;
     self.task = Some(SqliteTask::TxStart(tokio::spawn(async move {
-      let (results, tx) = query_with_tx(tx, &first_query).await;
+      let (results, tx) = execute_with_params(tx, &statements).await;
       match results {
-        Ok(Either::Left(rows_affected)) => {
-          log::info!("{rows_affected:?} rows affected");
-          (
-            QueryResultsWithMetadata {
-              results: Ok(Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) }),
-              statement_type: Some(statement_type),
-            },
-            tx,
-          )
-        },
-        Ok(Either::Right(rows)) => {
-          log::info!("{:?} rows affected", rows.rows_affected);
-          (QueryResultsWithMetadata { results: Ok(rows), statement_type: Some(statement_type) }, tx)
-        },
-        Err(e) => {
-          log::error!("{e:?}");
-          (QueryResultsWithMetadata { results: Err(e), statement_type: Some(statement_type) }, tx)
-        },
+        Ok(rows_affected) => log::info!("{rows_affected:?} rows affected"),
+        Err(ref e) => log::error!("{e:?}"),
       }
+      let results =
+        results.map(|rows_affected| Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) });
+      (vec![QueryResultsWithMetadata { results, statement_type, stream: None }], vec![], tx)
     })));
     Ok(())
   }
 
-  async fn commit_tx(&mut self) -> Result<Option<QueryResultsWithMetadata>> {
+  async fn commit_tx(&mut self) -> Result<Vec<QueryResultsWithMetadata>> {
     if !matches!(self.task, Some(SqliteTask::TxPending(_))) {
-      Ok(None)
+      Ok(vec![])
     } else {
       match self.task.take() {
         Some(SqliteTask::TxPending(b)) => {
           b.0.commit().await?;
-          Ok(Some(b.1))
+          Ok(b.1)
         },
-        _ => Ok(None),
+        _ => Ok(vec![]),
       }
     }
   }
@@ -170,12 +217,69 @@ impl Database for SqliteDriver<'_> {
     Ok(())
   }
 
+  async fn begin_session_tx(&mut self) -> Result<()> {
+    if self.session_conn.is_some() {
+      return Err(eyre::Report::msg("A transaction is already in progress"));
+    }
+    let mut conn = self.pool.clone().unwrap().acquire().await?;
+    conn.as_mut().execute(sqlx::raw_sql("BEGIN")).await?;
+    self.session_conn = Some(Arc::new(Mutex::new(conn)));
+    self.session_streams = CancellationToken::new();
+    Ok(())
+  }
+
+  async fn commit_session_tx(&mut self) -> Result<()> {
+    self.end_session_tx("COMMIT").await
+  }
+
+  async fn rollback_session_tx(&mut self) -> Result<()> {
+    self.end_session_tx("ROLLBACK").await
+  }
+
+  async fn close(&mut self) -> Result<()> {
+    self.rollback_tx().await?;
+    self.abort_query().await?;
+    self.rollback_session_tx().await?;
+    if let Some(pool) = self.pool.take() {
+      pool.close().await;
+    }
+    Ok(())
+  }
+
+  async fn listen(&mut self, channel: &str) -> Result<()> {
+    Err(eyre::Report::msg("LISTEN/NOTIFY is not supported for SQLite"))
+  }
+
+  async fn unlisten_all(&mut self) -> Result<()> {
+    Ok(())
+  }
+
+  fn take_notifications(&mut self) -> Vec<Notification> {
+    vec![]
+  }
+
+  async fn load_activity(&self) -> Result<Vec<ServerSession>> {
+    Err(eyre::Report::msg("The activity monitor is not supported for SQLite"))
+  }
+
+  async fn cancel_session(&self, id: &str) -> Result<()> {
+    Err(eyre::Report::msg("Cancelling sessions is not supported for SQLite"))
+  }
+
+  async fn terminate_session(&self, id: &str) -> Result<()> {
+    Err(eyre::Report::msg("Terminating sessions is not supported for SQLite"))
+  }
+
+  async fn load_lock_waits(&self) -> Result<Vec<LockWait>> {
+    Err(eyre::Report::msg("The lock inspector is not supported for SQLite"))
+  }
+
   async fn load_menu(&self) -> Result<Rows> {
     query_with_pool(
       self.pool.clone().unwrap(),
-      "select '' as table_schema, name as table_name
+      "select '' as table_schema, name, type
       from sqlite_master
-      where type = 'table'
+      where type in ('table', 'view', 'trigger')
       and name not like 'sqlite_%'
       order by name asc"
         .to_owned(),
@@ -183,6 +287,85 @@ impl Database for SqliteDriver<'_> {
     .await
   }
 
+  async fn load_tables(&self) -> Result<Vec<TableInfo>> {
+    let rows = query_with_pool(
+      self.pool.clone().unwrap(),
+      "select '', name, type, null
+      from sqlite_master
+      where type in ('table', 'view')
+      and name not like 'sqlite_%'
+      order by name asc"
+        .to_owned(),
+    )
+    .await?;
+    Ok(schema::tables_from_rows(rows))
+  }
+
+  // sqlite has no comments, and a rowid primary key isn't backed by an
+  // index, so the primary key is read from the columns instead
+  async fn describe_table(&self, schema: &str, table: &str) -> Result<TableDetails> {
+    let pool = self.pool.clone().unwrap();
+    let table = schema::literal(table);
+    let columns = query_with_pool(
+      pool.clone(),
+      format!("select name, type, \"notnull\" = 0, dflt_value, null from pragma_table_info({table}) order by cid"),
+    )
+    .await?;
+    let primary_key =
+      query_with_pool(pool.clone(), format!("select name from pragma_table_info({table}) where pk > 0 order by pk"))
+        .await?;
+    // a foreign key without columns references the primary key of the other table
+    let foreign_keys = query_with_pool(
+      pool.clone(),
+      format!(
+        "select fk.id, fk.\"from\", '', fk.\"table\",
+          coalesce(fk.\"to\", (select name from pragma_table_info(fk.\"table\") where pk = fk.seq + 1))
+        from pragma_foreign_key_list({table}) fk
+        order by fk.id, fk.seq"
+      ),
+    )
+    .await?;
+    let indexes = query_with_pool(
+      pool,
+      format!(
+        "select il.name, coalesce(ii.name, '<expression>'), il.\"unique\", il.origin = 'pk'
+        from pragma_index_list({table}) il
+        join pragma_index_info(il.name) ii
+        order by il.name, ii.seqno"
+      ),
+    )
+    .await?;
+    Ok(TableDetails {
+      columns: schema::columns_from_rows(columns),
+      primary_key: schema::key_from_rows(primary_key),
+      foreign_keys: schema::foreign_keys_from_rows(foreign_keys),
+      indexes: schema::indexes_from_rows(indexes),
+    })
+  }
+
+  // tables and views are followed by their indexes and triggers. indexes
+  // sqlite creates itself for constraints have no sql.
+  async fn load_ddl(&self, kind: ObjectKind, schema: &str, name: &str) -> Result<String> {
+    let name = schema::literal(name);
+    let dependents = match kind {
+      ObjectKind::Table | ObjectKind::View => format!("or (tbl_name = {name} and type in ('index', 'trigger'))"),
+      _ => "".to_owned(),
+    };
+    let rows = query_with_pool(
+      self.pool.clone().unwrap(),
+      format!(
+        "select sql from sqlite_master
+        where sql is not null and (name = {name} {dependents})
+        order by name <> {name}, type, name"
+      ),
+    )
+    .await?;
+    match ddl::script(rows.rows.iter().filter_map(|row| schema::text(&row[0]))) {
+      ddl if ddl.is_empty() => Err(eyre::Report::msg(format!("Could not find the {kind} {name}"))),
+      ddl => Ok(ddl),
+    }
+  }
+
   fn preview_rows_query(&self, schema: &str, table: &str) -> String {
     format!("select * from \"{table}\" limit 100")
   }
@@ -202,11 +385,49 @@ impl Database for SqliteDriver<'_> {
   fn preview_policies_query(&self, schema: &str, table: &str) -> String {
     "select 'SQLite does not support row-level security policies' as message".to_owned()
   }
+
+  fn preview_definition_query(&self, schema: &str, view: &str) -> String {
+    format!("select sql as definition from sqlite_master where type = 'view' and name = '{view}'")
+  }
+
+  fn preview_source_query(&self, schema: &str, function: &str) -> String {
+    "select 'SQLite does not support stored functions' as message".to_owned()
+  }
+
+  fn preview_sequence_query(&self, schema: &str, sequence: &str) -> String {
+    "select 'SQLite does not support sequences' as message".to_owned()
+  }
+
+  fn preview_trigger_query(&self, schema: &str, trigger: &str) -> String {
+    format!(
+      "select tbl_name as table_name, sql as definition from sqlite_master where type = 'trigger' and name = '{trigger}'"
+    )
+  }
+
+  fn preview_enum_query(&self, schema: &str, name: &str) -> String {
+    "select 'SQLite does not support enum types' as message".to_owned()
+  }
 }
 
 impl SqliteDriver<'_> {
   pub fn new() -> Self {
-    Self { pool: None, task: None }
+    Self { pool: None, task: None, session_conn: None, session_streams: CancellationToken::new() }
+  }
+
+  // if the transaction can't be ended, the connection is closed instead of
+  // going back to the pool with the transaction still open
+  async fn end_session_tx(&mut self, statement: &str) -> Result<()> {
+    let Some(conn) = self.session_conn.take() else {
+      return Ok(());
+    };
+    self.session_streams.cancel();
+    let mut conn = conn.lock_owned().await;
+    let result = conn.as_mut().execute(sqlx::raw_sql(statement)).await;
+    if result.is_err() {
+      conn.close_on_drop();
+    }
+    result?;
+    Ok(())
   }
 
   fn build_connection_opts(
@@ -240,7 +461,33 @@ async fn query_with_pool(pool: Arc<sqlx::Pool<Sqlite>>, query: String) -> Result
   query_with_stream(&*pool.clone(), &query).await
 }
 
+fn log_rows(rows: &Result<Rows>) {
+  match rows {
+    Ok(rows) => {
+      log::info!("{:?} rows, {:?} affected", rows.rows.len(), rows.rows_affected);
+    },
+    Err(e) => {
+      log::error!("{e:?}");
+    },
+  };
+}
+
+// sqlite steps through a statement one row at a time, so the statement
+// itself works as the cursor; it's only stepped as pages are asked for.
+async fn stream_query(mut conn: OwnedMutexGuard<PoolConnection<Sqlite>>, query: String, pages: PageSender) {
+  pages.serve(sqlx::raw_sql(&query).fetch(conn.as_mut()), row_to_vec, get_headers).await;
+}
+
 async fn query_with_stream<'a, E>(e: E, query: &'a str) -> Result<Rows>
+where
+  E: sqlx::Executor<'a, Database = sqlx::Sqlite>,
+{
+  query_with_capped_stream(e, query, usize::MAX).await
+}
+
+// rows past the cap are read but not kept, so that a statement returning
+// them still runs to the end
+async fn query_with_capped_stream<'a, E>(e: E, query: &'a str, row_cap: usize) -> Result<Rows>
 where
   E: sqlx::Executor<'a, Database = sqlx::Sqlite>,
 {
@@ -256,7 +503,9 @@ where
       },
       Ok(Either::Right(row)) => {
         // For SELECT queries
-        query_rows.push(row_to_vec(&row));
+        if query_rows.len() < row_cap {
+          query_rows.push(row_to_vec(&row));
+        }
         if headers.is_empty() {
           headers = get_headers(&row);
         }
@@ -267,6 +516,79 @@ where
   Ok(Rows { rows_affected: query_rows_affected, headers, rows: query_rows })
 }
 
+// runs each statement with its parameters bound as text, adding up the rows
+// they change
+async fn execute_with_params(
+  mut tx: SqliteTransaction<'static>,
+  statements: &[ParameterizedStatement],
+) -> (Result<u64>, SqliteTransaction<'static>) {
+  let mut rows_affected = 0;
+  for statement in statements {
+    let query = statement.params.iter().fold(sqlx::query(&statement.sql), |query, param| query.bind(param.clone()));
+    let result = match query.execute(&mut *tx).await {
+      Ok(result) => edit::expect_one_row(statement, result.rows_affected()),
+      Err(e) => Err(e.into()),
+    };
+    match result {
+      Ok(()) => rows_affected += 1,
+      Err(e) => return (Err(e), tx),
+    }
+  }
+  (Ok(rows_affected), tx)
+}
+
+// runs one statement of a transaction. UPDATE and DELETE return the rows
+// they change, so they can be previewed before the transaction is committed
+async fn tx_statement(
+  tx: SqliteTransaction<'static>,
+  query: String,
+  statement_type: Statement,
+  row_cap: usize,
+) -> (QueryResultsWithMetadata, Option<TxPreview>, SqliteTransaction<'static>) {
+  if let Some(queries) = super::get_tx_preview_queries(&statement_type, Driver::Sqlite) {
+    let (results, tx) = preview_with_tx(tx, &queries, row_cap).await;
+    return match results {
+      Ok((selected, returned)) => {
+        log::info!("{:?} rows affected", returned.rows_affected);
+        let rows = Rows { headers: vec![], rows: vec![], rows_affected: returned.rows_affected };
+        let preview = TxPreview::new(statement_type.clone(), selected, returned, row_cap);
+        (
+          QueryResultsWithMetadata { results: Ok(rows), statement_type: Some(statement_type), stream: None },
+          Some(preview),
+          tx,
+        )
+      },
+      Err(e) => {
+        log::error!("{e:?}");
+        (QueryResultsWithMetadata { results: Err(e), statement_type: Some(statement_type), stream: None }, None, tx)
+      },
+    };
+  }
+  let (results, tx) = query_with_tx(tx, &query).await;
+  match results {
+    Ok(Either::Left(rows_affected)) => {
+      log::info!("{rows_affected:?} rows affected");
+      (
+        QueryResultsWithMetadata {
+          results: Ok(Rows { headers: vec![], rows: vec![], rows_affected: Some(rows_affected) }),
+          statement_type: Some(statement_type),
+          stream: None,
+        },
+        None,
+        tx,
+      )
+    },
+    Ok(Either::Right(rows)) => {
+      log::info!("{:?} rows affected", rows.rows_affected);
+      (QueryResultsWithMetadata { results: Ok(rows), statement_type: Some(statement_type), stream: None }, None, tx)
+    },
+    Err(e) => {
+      log::error!("{e:?}");
+      (QueryResultsWithMetadata { results: Err(e), statement_type: Some(statement_type), stream: None }, None, tx)
+    },
+  }
+}
+
 async fn query_with_tx<'a>(
   mut tx: SqliteTransaction<'static>,
   query: &str,
@@ -278,7 +600,8 @@ where
   let first_query = super::get_first_query(query.to_string(), Driver::Sqlite);
   match first_query {
     Ok((first_query, statement_type)) => match statement_type {
-      Statement::Explain { .. } => {
+      // queries in a script that runs in a transaction keep their rows
+      Statement::Explain { .. } | Statement::Query(_) => {
         let result = query_with_stream(&mut *tx, &first_query).await;
         match result {
           Ok(result) => (Ok(Either::Right(result)), tx),
@@ -297,6 +620,24 @@ where
   }
 }
 
+// runs the SELECT that captures the rows an UPDATE will change, and then the
+// statement itself, returning what each of them returned
+async fn preview_with_tx(
+  mut tx: SqliteTransaction<'static>,
+  queries: &TxPreviewQueries,
+  row_cap: usize,
+) -> (Result<(Option<Rows>, Rows)>, SqliteTransaction<'static>) {
+  let selected = match queries.capped_select(row_cap) {
+    Some(select) => match query_with_stream(&mut *tx, &select).await {
+      Ok(rows) => Some(rows),
+      Err(e) => return (Err(e), tx),
+    },
+    None => None,
+  };
+  let result = query_with_capped_stream(&mut *tx, &queries.statement, row_cap.saturating_add(1)).await;
+  (result.map(|returned| (selected, returned)), tx)
+}
+
 fn get_headers(row: &<sqlx::Sqlite as sqlx::Database>::Row) -> Headers {
   row
     .columns()
@@ -305,47 +646,53 @@ fn get_headers(row: &<sqlx::Sqlite as sqlx::Database>::Row) -> Headers {
     .collect()
 }
 
-fn row_to_vec(row: &<sqlx::Sqlite as sqlx::Database>::Row) -> Vec<String> {
-  row.columns().iter().map(|col| parse_value(row, col).unwrap().string).collect()
+fn row_to_vec(row: &<sqlx::Sqlite as sqlx::Database>::Row) -> Vec<Value> {
+  row.columns().iter().map(|col| parse_value(row, col)).collect()
+}
+
+fn decode<'r, T>(
+  row: &'r SqliteRow,
+  col: &<Sqlite as sqlx::Database>::Column,
+  to_value: impl FnOnce(T) -> Value,
+) -> Value
+where
+  T: sqlx::Decode<'r, Sqlite> + sqlx::Type<Sqlite>,
+{
+  row.try_get::<T, usize>(col.ordinal()).map_or(Value::Unknown("_ERROR_".to_string()), to_value)
 }
 
 // parsed based on https://docs.rs/sqlx/latest/sqlx/sqlite/types/index.html
-fn parse_value(row: &<Sqlite as sqlx::Database>::Row, col: &<Sqlite as sqlx::Database>::Column) -> Option<Value> {
+fn parse_value(row: &<Sqlite as sqlx::Database>::Row, col: &<Sqlite as sqlx::Database>::Column) -> Value {
   let col_type = col.type_info().to_string();
   if row.try_get_raw(col.ordinal()).is_ok_and(|v| v.is_null()) {
-    return Some(Value { parse_error: false, string: "NULL".to_string(), is_null: true });
+    return Value::Null;
   }
   match col_type.to_uppercase().as_str() {
-    "BOOLEAN" => Some(row.try_get::<bool, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "INTEGER" | "INT4" | "INT8" | "BIGINT" => Some(row.try_get::<i64, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
-    "REAL" => Some(row.try_get::<f64, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-    )),
+    "BOOLEAN" => decode(row, col, Value::Bool),
+    "INTEGER" | "INT4" | "INT8" | "BIGINT" => decode(row, col, |v: i64| Value::Int(v.into())),
+    "REAL" => decode(row, col, Value::Float),
+    "NUMERIC" => match row.try_get::<i64, _>(col.ordinal()) {
+      Ok(i) => Value::Int(i.into()),
+      _ => row
+        .try_get_unchecked::<String, usize>(col.ordinal())
+        .map_or(Value::Unknown("_ERROR_".to_string()), Value::Decimal),
+    },
     "TEXT" => {
       // Try parsing as different types that might be stored as TEXT
       match row.try_get::<chrono::NaiveDateTime, _>(col.ordinal()) {
-        Ok(dt) => Some(Value { parse_error: false, string: dt.to_string(), is_null: false }),
+        Ok(dt) => Value::Temporal(dt.to_string()),
         _ => match row.try_get::<chrono::DateTime<chrono::Utc>, _>(col.ordinal()) {
-          Ok(dt) => Some(Value { parse_error: false, string: dt.to_string(), is_null: false }),
+          Ok(dt) => Value::Temporal(dt.to_string()),
           _ => match row.try_get::<chrono::NaiveDate, _>(col.ordinal()) {
-            Ok(date) => Some(Value { parse_error: false, string: date.to_string(), is_null: false }),
+            Ok(date) => Value::Temporal(date.to_string()),
             _ => match row.try_get::<chrono::NaiveTime, _>(col.ordinal()) {
-              Ok(time) => Some(Value { parse_error: false, string: time.to_string(), is_null: false }),
+              Ok(time) => Value::Temporal(time.to_string()),
               _ => match row.try_get::<uuid::Uuid, _>(col.ordinal()) {
-                Ok(uuid) => Some(Value { parse_error: false, string: uuid.to_string(), is_null: false }),
+                Ok(uuid) => Value::Text(uuid.to_string()),
+                // only objects and arrays, so that plain strings and numbers stay text
                 _ => match row.try_get::<serde_json::Value, _>(col.ordinal()) {
-                  Ok(json) => Some(Value { parse_error: false, string: json.to_string(), is_null: false }),
-                  _ => match row.try_get::<String, _>(col.ordinal()) {
-                    Ok(string) => Some(Value { parse_error: false, string, is_null: false }),
-                    _ => Some(Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false }),
-                  },
+                  Ok(json) if json.is_object() || json.is_array() => Value::Json(json),
+                  _ => decode(row, col, Value::Text),
                 },
               },
             },
@@ -353,62 +700,37 @@ fn parse_value(row: &<Sqlite as sqlx::Database>::Row, col: &<Sqlite as sqlx::Dat
         },
       }
     },
-    "BLOB" => Some(row.try_get::<Vec<u8>, usize>(col.ordinal()).map_or(
-      Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-      |received| {
-        if let Ok(s) = String::from_utf8(received.clone()) {
-          Value { parse_error: false, string: s, is_null: false }
-        } else {
-          Value {
-            parse_error: false,
-            string: received.iter().fold(String::new(), |mut output, b| {
-              let _ = write!(output, "{b:02X}");
-              output
-            }),
-            is_null: false,
-          }
-        }
-      },
-    )),
+    "BLOB" => decode(row, col, |received: Vec<u8>| match String::from_utf8(received) {
+      Ok(s) => Value::Text(s),
+      Err(e) => Value::Bytes(e.into_bytes()),
+    }),
     "DATETIME" => {
       // Similar to TEXT, but we'll try timestamp first
       match row.try_get::<i64, _>(col.ordinal()) {
-        Ok(dt) => Some(chrono::DateTime::from_timestamp(dt, 0).map_or(
-          Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-          |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-        )),
+        Ok(dt) => chrono::DateTime::from_timestamp(dt, 0)
+          .map_or(Value::Unknown("_ERROR_".to_string()), |received| Value::Temporal(received.to_string())),
         _ => match row.try_get::<chrono::NaiveDateTime, _>(col.ordinal()) {
-          Ok(dt) => Some(Value { parse_error: true, string: dt.to_string(), is_null: false }),
+          Ok(dt) => Value::Temporal(dt.to_string()),
           _ => match row.try_get::<chrono::DateTime<chrono::Utc>, _>(col.ordinal()) {
-            Ok(dt) => Some(Value { parse_error: true, string: dt.to_string(), is_null: false }),
-            _ => Some(row.try_get::<String, usize>(col.ordinal()).map_or(
-              Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-              |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-            )),
+            Ok(dt) => Value::Temporal(dt.to_string()),
+            _ => decode(row, col, Value::Text),
           },
         },
       }
     },
     "DATE" => match row.try_get::<chrono::NaiveDate, _>(col.ordinal()) {
-      Ok(date) => Some(Value { parse_error: true, string: date.to_string(), is_null: false }),
-      _ => Some(row.try_get::<String, usize>(col.ordinal()).map_or(
-        Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-        |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-      )),
+      Ok(date) => Value::Temporal(date.to_string()),
+      _ => decode(row, col, Value::Text),
     },
     "TIME" => match row.try_get::<chrono::NaiveTime, _>(col.ordinal()) {
-      Ok(time) => Some(Value { parse_error: true, string: time.to_string(), is_null: false }),
-      _ => Some(row.try_get::<String, usize>(col.ordinal()).map_or(
-        Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-        |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-      )),
+      Ok(time) => Value::Temporal(time.to_string()),
+      _ => decode(row, col, Value::Text),
     },
     _ => {
       // For any other types, try to cast to string
-      Some(row.try_get_unchecked::<String, usize>(col.ordinal()).map_or(
-        Value { parse_error: true, string: "_ERROR_".to_string(), is_null: false },
-        |received| Value { parse_error: false, string: received.to_string(), is_null: false },
-      ))
+      row
+        .try_get_unchecked::<String, usize>(col.ordinal())
+        .map_or(Value::Unknown("_ERROR_".to_string()), Value::Unknown)
     },
   }
 }
@@ -418,7 +740,10 @@ mod tests {
   use sqlparser::{ast::Statement, dialect::SQLiteDialect, parser::ParserError};
 
   use super::*;
-  use crate::database::{ExecutionType, ParseError, get_execution_type, get_first_query};
+  use crate::database::{
+    ColumnInfo, ExecutionPolicy, ExecutionType, ForeignKey, ObjectKind, ParseError, get_execution_type,
+    get_first_query, statement_type_string, stream::StreamState,
+  };
 
   #[test]
   fn test_get_first_query() {
@@ -512,14 +837,359 @@ mod tests {
       ("EXPLAIN SELECT * FROM users", ExecutionType::Normal),
       ("EXPLAIN QUERY PLAN SELECT * FROM users", ExecutionType::Normal),
       ("EXPLAIN Query PLAN DELETE FROM users WHERE id = 1", ExecutionType::Normal),
+      ("SELECT 1; SELECT 2", ExecutionType::Normal),
+      ("INSERT INTO users (name) VALUES ('John'); SELECT * FROM users", ExecutionType::Normal),
+      ("SELECT 1; DELETE FROM users WHERE id = 1", ExecutionType::Transaction),
+      ("UPDATE users SET name = 'a' WHERE id = 1; DELETE FROM users WHERE id = 2", ExecutionType::Transaction),
+      ("BEGIN; DELETE FROM users WHERE id = 1; COMMIT", ExecutionType::Confirm),
+      ("DELETE FROM users WHERE id = 1; DROP TABLE users", ExecutionType::Confirm),
+      ("DROP TABLE users; SELECT 1", ExecutionType::Confirm),
     ];
 
     for (query, expected) in test_cases {
       assert_eq!(
-        get_execution_type(query.to_string(), false, Driver::Sqlite).unwrap().0,
+        get_execution_type(query.to_string(), false, Driver::Sqlite, false, &ExecutionPolicy::default()).unwrap().0,
         expected,
         "Failed for query: {query}"
       );
     }
   }
+
+  // every connection in the pool shares the same named in-memory database
+  async fn memory_driver(name: &str) -> SqliteDriver<'static> {
+    let mut driver = SqliteDriver::new();
+    let url = format!("sqlite:file:{name}?mode=memory&cache=shared");
+    driver.init(<crate::cli::Cli as clap::Parser>::parse_from(["rainfrog", "--url", &url])).await.unwrap();
+    query_with_pool(
+      driver.pool.clone().unwrap(),
+      "create table authors (id integer primary key, name text not null default 'anon');
+      create table books (
+        isbn text,
+        edition integer,
+        author_id integer references authors,
+        title text,
+        primary key (isbn, edition)
+      );
+      create unique index books_title on books (title);
+      create view titles as select title from books;"
+        .to_owned(),
+    )
+    .await
+    .unwrap();
+    driver
+  }
+
+  #[tokio::test]
+  async fn test_load_tables() {
+    let driver = memory_driver("load_tables").await;
+    let tables = driver.load_tables().await.unwrap();
+    let names: Vec<(&str, ObjectKind)> = tables.iter().map(|t| (t.name.as_str(), t.kind)).collect();
+    assert_eq!(names, vec![("authors", ObjectKind::Table), ("books", ObjectKind::Table), ("titles", ObjectKind::View)]);
+    assert!(tables.iter().all(|t| t.schema.is_empty() && t.comment.is_none()));
+  }
+
+  #[tokio::test]
+  async fn test_describe_table() {
+    let driver = memory_driver("describe_table").await;
+
+    let authors = driver.describe_table("", "authors").await.unwrap();
+    assert_eq!(authors.primary_key, vec!["id"]);
+    assert_eq!(
+      authors.columns[1],
+      ColumnInfo {
+        name: "name".to_owned(),
+        type_name: "TEXT".to_owned(),
+        nullable: false,
+        default: Some("'anon'".to_owned()),
+        comment: None,
+      }
+    );
+    // a rowid primary key has no index
+    assert!(authors.indexes.is_empty());
+
+    let books = driver.describe_table("", "books").await.unwrap();
+    assert_eq!(books.primary_key, vec!["isbn", "edition"]);
+    assert!(books.columns[3].nullable);
+    assert_eq!(
+      books.foreign_keys,
+      vec![ForeignKey {
+        name: "0".to_owned(),
+        columns: vec!["author_id".to_owned()],
+        foreign_schema: String::new(),
+        foreign_table: "authors".to_owned(),
+        foreign_columns: vec!["id".to_owned()],
+      }]
+    );
+    let title = books.indexes.iter().find(|i| i.name == "books_title").unwrap();
+    assert!(title.unique && !title.primary);
+    assert_eq!(title.columns, vec!["title"]);
+    let primary = books.indexes.iter().find(|i| i.primary).unwrap();
+    assert_eq!(primary.columns, vec!["isbn", "edition"]);
+
+    let view = driver.describe_table("", "titles").await.unwrap();
+    assert_eq!(view.columns.len(), 1);
+    assert!(view.primary_key.is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_load_ddl() {
+    let driver = memory_driver("load_ddl").await;
+
+    let books = driver.load_ddl(ObjectKind::Table, "", "books").await.unwrap();
+    assert!(books.starts_with("CREATE TABLE books (\n        isbn text,"));
+    assert!(books.ends_with(");\n\nCREATE UNIQUE INDEX books_title on books (title);"));
+
+    let view = driver.load_ddl(ObjectKind::View, "", "titles").await.unwrap();
+    assert_eq!(view, "CREATE VIEW titles as select title from books;");
+
+    assert!(driver.load_ddl(ObjectKind::Table, "", "missing").await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_read_only_connection() {
+    let writable = memory_driver("read_only").await;
+    let url = "sqlite:file:read_only?mode=memory&cache=shared";
+    let mut driver = SqliteDriver::new();
+    driver
+      .init(<crate::cli::Cli as clap::Parser>::parse_from(["rainfrog", "--url", url, "--read-only"]))
+      .await
+      .unwrap();
+    let pool = driver.pool.clone().unwrap();
+    assert!(query_with_pool(pool.clone(), "select * from authors".to_owned()).await.is_ok());
+    assert!(query_with_pool(pool, "insert into authors (name) values ('someone')".to_owned()).await.is_err());
+    assert!(
+      query_with_pool(writable.pool.clone().unwrap(), "insert into authors (name) values ('someone')".to_owned())
+        .await
+        .is_ok()
+    );
+  }
+
+  #[tokio::test]
+  async fn test_tx_preview() {
+    let mut driver = memory_driver("tx_preview").await;
+    query_with_pool(driver.pool.clone().unwrap(), "insert into authors (name) values ('a'), ('b'), ('c')".to_owned())
+      .await
+      .unwrap();
+
+    driver.start_tx("update authors set name = 'z' where id < 3".to_owned(), QueryOptions::default()).await.unwrap();
+    let preview = loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::ConfirmTx(rows_affected, _, mut previews) => {
+          assert_eq!(rows_affected, Some(2));
+          break previews.remove(0);
+        },
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("transaction didn't start"),
+      }
+    };
+    let names = |rows: &Rows| rows.rows.iter().map(|row| row[1].to_string()).collect::<Vec<_>>();
+    assert_eq!(names(&preview.before), vec!["a", "b"]);
+    assert_eq!(names(preview.after.as_ref().unwrap()), vec!["z", "z"]);
+    assert_eq!(preview.truncated_at, None);
+    driver.rollback_tx().await.unwrap();
+
+    let options = QueryOptions { row_cap: 1, ..QueryOptions::default() };
+    driver.start_tx("update authors set name = 'z' where id < 3".to_owned(), options).await.unwrap();
+    let preview = loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::ConfirmTx(rows_affected, _, mut previews) => {
+          assert_eq!(rows_affected, Some(2));
+          break previews.remove(0);
+        },
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("transaction didn't start"),
+      }
+    };
+    assert_eq!(preview.before.rows.len(), 1);
+    assert_eq!(preview.after.as_ref().unwrap().rows.len(), 1);
+    assert_eq!(preview.truncated_at, Some(1));
+    driver.rollback_tx().await.unwrap();
+
+    driver.start_tx("delete from authors where name = 'c'".to_owned(), QueryOptions::default()).await.unwrap();
+    let preview = loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::ConfirmTx(_, _, mut previews) => break previews.remove(0),
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("transaction didn't start"),
+      }
+    };
+    assert_eq!(names(&preview.before), vec!["c"]);
+    assert!(preview.after.is_none());
+    driver.rollback_tx().await.unwrap();
+
+    query_with_pool(
+      driver.pool.clone().unwrap(),
+      "insert into books (isbn, edition, author_id, title) values ('x', 1, 1, 'first')".to_owned(),
+    )
+    .await
+    .unwrap();
+    driver
+      .start_tx(
+        "update authors set name = books.title from books where books.author_id = authors.id".to_owned(),
+        QueryOptions::default(),
+      )
+      .await
+      .unwrap();
+    let preview = loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::ConfirmTx(_, _, mut previews) => break previews.remove(0),
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("transaction didn't start"),
+      }
+    };
+    assert_eq!(names(&preview.before), vec!["a"]);
+    assert_eq!(names(preview.after.as_ref().unwrap()), vec!["first"]);
+    driver.rollback_tx().await.unwrap();
+
+    let rows = query_with_pool(driver.pool.clone().unwrap(), "select name from authors".to_owned()).await.unwrap();
+    assert_eq!(rows.rows.len(), 3);
+  }
+  #[tokio::test]
+  async fn test_start_tx_with_params() {
+    let mut driver = memory_driver("tx_with_params").await;
+    query_with_pool(driver.pool.clone().unwrap(), "insert into authors (name) values ('a'), ('b')".to_owned())
+      .await
+      .unwrap();
+    let update = |name: Option<&str>, id: &str| ParameterizedStatement {
+      sql: "UPDATE \"authors\" SET \"name\" = ? WHERE \"id\" = ?".to_owned(),
+      params: vec![name.map(str::to_owned), Some(id.to_owned())],
+    };
+    let pool = driver.pool.clone().unwrap();
+    let names = || async {
+      let rows = query_with_pool(pool.clone(), "select name from authors order by id".to_owned());
+      rows.await.unwrap().rows.iter().map(|row| row[0].to_string()).collect::<Vec<_>>()
+    };
+
+    driver.start_tx_with_params(vec![update(Some("o'neil"), "1"), update(Some("c"), "2")]).await.unwrap();
+    loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::ConfirmTx(rows_affected, ..) => break assert_eq!(rows_affected, Some(2)),
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("transaction didn't start"),
+      }
+    }
+    driver.commit_tx().await.unwrap();
+    assert_eq!(names().await, vec!["o'neil", "c"]);
+
+    // the key of the second row doesn't match, so neither is changed
+    driver.start_tx_with_params(vec![update(Some("d"), "1"), update(Some("e"), "3")]).await.unwrap();
+    loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::Finished(results) => break assert!(results[0].results.is_err()),
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("transaction didn't fail"),
+      }
+    }
+    assert_eq!(names().await, vec!["o'neil", "c"]);
+  }
+
+  async fn query_results(
+    driver: &mut SqliteDriver<'_>,
+    query: &str,
+    options: QueryOptions,
+  ) -> Vec<QueryResultsWithMetadata> {
+    driver.start_query(query.to_owned(), false, options).await.unwrap();
+    loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::Finished(results) => break results,
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("query didn't finish"),
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_script_on_error() {
+    let mut driver = memory_driver("script_on_error").await;
+    let script = "insert into authors (name) values ('a'); insert into missing values (1); insert into authors (name) values ('b')";
+    let names = |driver: &SqliteDriver<'_>| {
+      let pool = driver.pool.clone().unwrap();
+      async move {
+        let rows = query_with_pool(pool, "select name from authors order by id".to_owned()).await.unwrap();
+        rows.rows.iter().map(|row| row[0].to_string()).collect::<Vec<_>>()
+      }
+    };
+
+    let options = QueryOptions { on_error: ScriptErrorPolicy::Stop, ..QueryOptions::default() };
+    let results = query_results(&mut driver, script, options).await;
+    assert_eq!(results.len(), 2);
+    assert!(results[1].results.is_err());
+    assert_eq!(names(&driver).await, vec!["a"]);
+
+    let options = QueryOptions { on_error: ScriptErrorPolicy::Continue, ..QueryOptions::default() };
+    let results = query_results(&mut driver, script, options).await;
+    assert_eq!(results.len(), 3);
+    assert!(results[1].results.is_err() && results[2].results.is_ok());
+    assert_eq!(names(&driver).await, vec!["a", "a", "b"]);
+  }
+
+  #[tokio::test]
+  async fn test_script_tx() {
+    let mut driver = memory_driver("script_tx").await;
+    query_with_pool(driver.pool.clone().unwrap(), "insert into authors (name) values ('a'), ('b'), ('c')".to_owned())
+      .await
+      .unwrap();
+    let script =
+      "update authors set name = 'z' where id < 3; select count(*) from authors; delete from authors where id = 3";
+
+    driver.start_tx(script.to_owned(), QueryOptions::default()).await.unwrap();
+    loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::ConfirmTx(rows_affected, statement, previews) => {
+          assert_eq!(rows_affected, Some(3));
+          assert!(statement.is_none());
+          let kinds = previews.iter().map(|p| statement_type_string(Some(p.statement.clone()))).collect::<Vec<_>>();
+          assert_eq!(kinds, vec!["Update", "Delete"]);
+          break;
+        },
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("transaction didn't start"),
+      }
+    }
+    let results = driver.commit_tx().await.unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[1].results.as_ref().unwrap().rows[0][0].to_string(), "3");
+
+    // the transaction stops at the statement that fails, and is rolled back
+    let script = "delete from authors; insert into missing values (1); delete from books";
+    driver.start_tx(script.to_owned(), QueryOptions::default()).await.unwrap();
+    let results = loop {
+      match driver.get_query_results().await.unwrap() {
+        DbTaskResult::Finished(results) => break results,
+        DbTaskResult::Pending => tokio::task::yield_now().await,
+        _ => panic!("transaction didn't fail"),
+      }
+    };
+    assert_eq!(results.len(), 2);
+    assert!(results[1].results.is_err());
+    let rows = query_with_pool(driver.pool.clone().unwrap(), "select name from authors".to_owned()).await.unwrap();
+    assert_eq!(rows.rows.len(), 2);
+  }
+
+  // a result that's still streaming from the transaction's connection
+  // mustn't keep the transaction from ending
+  #[tokio::test]
+  async fn test_end_session_tx_with_open_stream() {
+    let mut driver = memory_driver("end_session_tx").await;
+    let options = QueryOptions { page_size: 1, ..QueryOptions::default() };
+    for commit in [true, false] {
+      driver.begin_session_tx().await.unwrap();
+      query_results(&mut driver, "insert into authors (name) values ('a')", options).await;
+      let mut results =
+        query_results(&mut driver, "select * from authors, (select 1 union all select 2)", options).await;
+      let mut stream = results.pop().unwrap().stream.expect("result wasn't streamed");
+
+      let end = async { if commit { driver.commit_session_tx().await } else { driver.rollback_session_tx().await } };
+      tokio::time::timeout(std::time::Duration::from_secs(5), end).await.expect("transaction didn't end").unwrap();
+      loop {
+        stream.try_next_page();
+        if stream.state() == StreamState::Expired {
+          break;
+        }
+        tokio::task::yield_now().await;
+      }
+    }
+    let rows = query_with_pool(driver.pool.clone().unwrap(), "select name from authors".to_owned()).await.unwrap();
+    assert_eq!(rows.rows.len(), 1);
+  }
 }
@@ -0,0 +1,350 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{self, Result};
+use futures::{Stream, StreamExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender, error::TryRecvError};
+use tokio_util::sync::CancellationToken;
+
+use super::{Headers, Rows, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamState {
+  /// More rows might be available; nothing has been asked for yet.
+  Idle,
+  /// A page has been asked for and hasn't arrived yet.
+  Fetching,
+  /// Every row of the result has been fetched.
+  Exhausted,
+  /// The row cap was reached before the result ran out.
+  Capped,
+  /// No more rows were asked for in time, or the connection was needed
+  /// back, so the rest weren't fetched.
+  Expired,
+}
+
+#[derive(Debug)]
+struct Page {
+  headers: Headers,
+  rows: Vec<Vec<Value>>,
+  state: StreamState,
+}
+
+/// Rows of a query result that haven't been fetched yet. They are read
+/// a page at a time by a task that holds on to the connection the query
+/// ran on, which is released once the rows run out, the row cap is hit,
+/// the reader is cancelled, or the stream is dropped.
+#[derive(Debug)]
+pub struct RowStream {
+  requests: UnboundedSender<()>,
+  pages: UnboundedReceiver<Result<Page>>,
+  state: StreamState,
+  row_cap: usize,
+}
+
+/// The other end of a `RowStream`, owned by the task that reads the rows.
+pub struct PageSender {
+  requests: UnboundedReceiver<()>,
+  pages: UnboundedSender<Result<Page>>,
+  page_size: usize,
+  row_cap: usize,
+  idle_timeout: Option<Duration>,
+  cancel: Option<CancellationToken>,
+}
+
+pub fn row_stream(page_size: usize, row_cap: usize) -> (RowStream, PageSender) {
+  let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+  let (pages_tx, pages_rx) = mpsc::unbounded_channel();
+  (
+    RowStream { requests: requests_tx, pages: pages_rx, state: StreamState::Idle, row_cap },
+    PageSender {
+      requests: requests_rx,
+      pages: pages_tx,
+      page_size: page_size.max(1),
+      row_cap,
+      idle_timeout: None,
+      cancel: None,
+    },
+  )
+}
+
+impl RowStream {
+  /// Waits for the first page of rows. The stream is only handed back if
+  /// there might be more rows to fetch.
+  pub async fn first_page(mut self) -> (Result<Rows>, Option<RowStream>) {
+    self.fetch_page();
+    let page = match self.pages.recv().await {
+      Some(Ok(page)) => page,
+      Some(Err(e)) => return (Err(e), None),
+      None => return (Err(eyre::Report::msg("Row stream closed before the first page arrived")), None),
+    };
+    self.state = page.state;
+    let rows = Rows { headers: page.headers, rows: page.rows, rows_affected: None };
+    (Ok(rows), if self.state == StreamState::Idle { Some(self) } else { None })
+  }
+
+  pub fn state(&self) -> StreamState {
+    self.state
+  }
+
+  pub fn row_cap(&self) -> usize {
+    self.row_cap
+  }
+
+  /// Asks for the next page, unless one is already on its way or there
+  /// is nothing left to fetch.
+  pub fn fetch_page(&mut self) {
+    if self.state == StreamState::Idle {
+      self.state = match self.requests.send(()) {
+        Ok(_) => StreamState::Fetching,
+        // the reader might have stopped because it expired
+        Err(_) => match self.pages.try_recv() {
+          Ok(Ok(page)) => page.state,
+          _ => StreamState::Exhausted,
+        },
+      };
+    }
+  }
+
+  /// Returns the page that was asked for, if it has arrived. While idle,
+  /// this only notices that the stream expired.
+  pub fn try_next_page(&mut self) -> Option<Result<Vec<Vec<Value>>>> {
+    if !matches!(self.state, StreamState::Fetching | StreamState::Idle) {
+      return None;
+    }
+    match self.pages.try_recv() {
+      Ok(Ok(page)) => {
+        self.state = page.state;
+        Some(Ok(page.rows))
+      },
+      Ok(Err(e)) => {
+        self.state = StreamState::Exhausted;
+        Some(Err(e))
+      },
+      Err(TryRecvError::Empty) => None,
+      Err(TryRecvError::Disconnected) => {
+        self.state = StreamState::Exhausted;
+        None
+      },
+    }
+  }
+}
+
+impl PageSender {
+  pub fn page_size(&self) -> usize {
+    self.page_size
+  }
+
+  /// Stops serving rows once no page has been asked for in `timeout`, for
+  /// readers that hold something open on the server while they wait.
+  pub fn idle_timeout(self, timeout: Duration) -> Self {
+    Self { idle_timeout: Some(timeout), ..self }
+  }
+
+  /// Stops serving rows once `token` is cancelled, for readers holding a
+  /// connection that has to be handed back, like one a transaction is
+  /// pinned to.
+  pub fn cancel_on(self, token: CancellationToken) -> Self {
+    Self { cancel: Some(token), ..self }
+  }
+
+  fn expire(&self) {
+    let _ = self.pages.send(Ok(Page { headers: vec![], rows: vec![], state: StreamState::Expired }));
+  }
+
+  async fn next_request(&mut self) -> Option<()> {
+    let request = match self.idle_timeout {
+      Some(timeout) => {
+        until_cancelled(&self.cancel, tokio::time::timeout(timeout, self.requests.recv())).await.and_then(Result::ok)
+      },
+      None => until_cancelled(&self.cancel, self.requests.recv()).await,
+    };
+    match request {
+      Some(request) => request,
+      None => {
+        self.expire();
+        None
+      },
+    }
+  }
+
+  /// Reads a page from `rows` every time one is asked for, until the rows
+  /// run out, the row cap is reached, the reader is cancelled, or the
+  /// `RowStream` is dropped.
+  pub async fn serve<R, E, S>(mut self, mut rows: S, row_to_vec: fn(&R) -> Vec<Value>, get_headers: fn(&R) -> Headers)
+  where
+    S: Stream<Item = Result<R, E>> + Unpin,
+    E: std::error::Error + Send + Sync + 'static,
+  {
+    let mut fetched = 0_usize;
+    while self.next_request().await.is_some() {
+      let mut headers = vec![];
+      let mut page = vec![];
+      let mut state = StreamState::Idle;
+      while page.len() < self.page_size {
+        if fetched + page.len() >= self.row_cap {
+          state = StreamState::Capped;
+          break;
+        }
+        let Some(row) = until_cancelled(&self.cancel, rows.next()).await else {
+          self.expire();
+          return;
+        };
+        match row {
+          Some(Ok(row)) => {
+            if fetched == 0 && page.is_empty() {
+              headers = get_headers(&row);
+            }
+            page.push(row_to_vec(&row));
+          },
+          Some(Err(e)) => {
+            let _ = self.pages.send(Err(eyre::Report::new(e)));
+            return;
+          },
+          None => {
+            state = StreamState::Exhausted;
+            break;
+          },
+        }
+      }
+      fetched += page.len();
+      if state == StreamState::Idle && fetched >= self.row_cap {
+        state = StreamState::Capped;
+      }
+      if self.pages.send(Ok(Page { headers, rows: page, state })).is_err() || state != StreamState::Idle {
+        return;
+      }
+    }
+  }
+}
+
+// None if `cancel` is cancelled before `future` completes
+async fn until_cancelled<T>(cancel: &Option<CancellationToken>, future: impl Future<Output = T>) -> Option<T> {
+  match cancel {
+    Some(cancel) => cancel.run_until_cancelled(future).await,
+    None => Some(future.await),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fmt;
+
+  use futures::stream;
+
+  use super::*;
+
+  #[derive(Debug)]
+  struct TestError;
+  impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "test error")
+    }
+  }
+  impl std::error::Error for TestError {}
+
+  fn to_vec(row: &u32) -> Vec<Value> {
+    vec![Value::Int(*row as i128)]
+  }
+
+  fn ints(rows: &[i128]) -> Vec<Vec<Value>> {
+    rows.iter().map(|n| vec![Value::Int(*n)]).collect()
+  }
+
+  fn headers(_: &u32) -> Headers {
+    vec![super::super::Header { name: "n".to_owned(), type_name: "INT".to_owned() }]
+  }
+
+  async fn next_page(stream: &mut RowStream) -> Result<Vec<Vec<Value>>> {
+    stream.fetch_page();
+    loop {
+      if let Some(page) = stream.try_next_page() {
+        return page;
+      }
+      tokio::task::yield_now().await;
+    }
+  }
+
+  #[tokio::test]
+  async fn test_pages_until_exhausted() {
+    let (stream, sender) = row_stream(2, 100);
+    let rows = stream::iter((0..5).map(Ok::<u32, TestError>));
+    tokio::spawn(sender.serve(rows, to_vec, headers));
+
+    let (first, stream) = stream.first_page().await;
+    let first = first.unwrap();
+    assert_eq!(first.headers.len(), 1);
+    assert_eq!(first.rows, ints(&[0, 1]));
+    let mut stream = stream.unwrap();
+    assert_eq!(next_page(&mut stream).await.unwrap(), ints(&[2, 3]));
+    assert_eq!(stream.state(), StreamState::Idle);
+    assert_eq!(next_page(&mut stream).await.unwrap(), ints(&[4]));
+    assert_eq!(stream.state(), StreamState::Exhausted);
+  }
+
+  #[tokio::test]
+  async fn test_stops_at_row_cap() {
+    let (stream, sender) = row_stream(2, 3);
+    let rows = stream::iter((0..5).map(Ok::<u32, TestError>));
+    tokio::spawn(sender.serve(rows, to_vec, headers));
+
+    let (_, stream) = stream.first_page().await;
+    let mut stream = stream.unwrap();
+    assert_eq!(next_page(&mut stream).await.unwrap(), ints(&[2]));
+    assert_eq!(stream.state(), StreamState::Capped);
+  }
+
+  #[tokio::test]
+  async fn test_small_result_is_not_streamed() {
+    let (stream, sender) = row_stream(10, 100);
+    let rows = stream::iter((0..3).map(Ok::<u32, TestError>));
+    tokio::spawn(sender.serve(rows, to_vec, headers));
+
+    let (first, stream) = stream.first_page().await;
+    assert_eq!(first.unwrap().rows.len(), 3);
+    assert!(stream.is_none());
+  }
+
+  #[tokio::test]
+  async fn test_error_ends_stream() {
+    let (stream, sender) = row_stream(2, 100);
+    let rows = stream::iter(vec![Ok(0), Ok(1), Err(TestError)]);
+    tokio::spawn(sender.serve(rows, to_vec, headers));
+
+    let (_, stream) = stream.first_page().await;
+    let mut stream = stream.unwrap();
+    assert!(next_page(&mut stream).await.is_err());
+    assert_eq!(stream.state(), StreamState::Exhausted);
+  }
+
+  #[tokio::test]
+  async fn test_expires_when_idle() {
+    let (stream, sender) = row_stream(2, 100);
+    let rows = stream::iter((0..5).map(Ok::<u32, TestError>));
+    tokio::spawn(sender.idle_timeout(Duration::from_millis(10)).serve(rows, to_vec, headers));
+
+    let (_, stream) = stream.first_page().await;
+    let mut stream = stream.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(stream.try_next_page().unwrap().unwrap(), ints(&[]));
+    assert_eq!(stream.state(), StreamState::Expired);
+    stream.fetch_page();
+    assert_eq!(stream.state(), StreamState::Expired);
+  }
+
+  #[tokio::test]
+  async fn test_expires_when_cancelled() {
+    let (stream, sender) = row_stream(2, 100);
+    let token = CancellationToken::new();
+    let rows = stream::iter((0..2).map(Ok::<u32, TestError>)).chain(stream::pending());
+    let reader = tokio::spawn(sender.cancel_on(token.clone()).serve(rows, to_vec, headers));
+
+    let (_, stream) = stream.first_page().await;
+    let mut stream = stream.unwrap();
+    // the next page never arrives, so the reader is cancelled mid-page
+    stream.fetch_page();
+    token.cancel();
+    reader.await.unwrap();
+    assert_eq!(stream.try_next_page().unwrap().unwrap(), ints(&[]));
+    assert_eq!(stream.state(), StreamState::Expired);
+  }
+}
@@ -0,0 +1,179 @@
+use std::fmt::{self, Write};
+
+use crate::cli::Driver;
+
+/// A single value in a row of results, decoded by the driver into the
+/// closest type that rainfrog knows how to display, copy and export.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  Null,
+  Bool(bool),
+  Int(i128),
+  Float(f64),
+  /// Arbitrary-precision numbers, kept as text so they don't lose precision.
+  Decimal(String),
+  Text(String),
+  Bytes(Vec<u8>),
+  Json(serde_json::Value),
+  /// Dates, times, timestamps and intervals, already formatted.
+  Temporal(String),
+  /// Anything that couldn't be decoded into one of the other variants,
+  /// along with its raw text.
+  Unknown(String),
+}
+
+impl Value {
+  pub fn is_null(&self) -> bool {
+    matches!(self, Value::Null)
+  }
+
+  pub fn is_numeric(&self) -> bool {
+    matches!(self, Value::Int(_) | Value::Float(_) | Value::Decimal(_))
+  }
+
+  /// The value as it would be written in a SQL statement for the driver's
+  /// dialect.
+  pub fn to_sql_literal(&self, driver: Driver) -> String {
+    match self {
+      Value::Null => "NULL".to_owned(),
+      Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_owned(),
+      Value::Int(i) => i.to_string(),
+      Value::Float(f) if f.is_finite() => f.to_string(),
+      Value::Decimal(d) => d.clone(),
+      Value::Bytes(bytes) => match driver {
+        Driver::Postgres => format!("'\\x{}'::bytea", hex(bytes)),
+        Driver::Oracle => format!("HEXTORAW('{}')", hex(bytes)),
+        Driver::MySql | Driver::Sqlite => format!("X'{}'", hex(bytes)),
+      },
+      _ => quote(&self.to_string()),
+    }
+  }
+
+  /// The value as a JSON value. Decimals are written as strings so that
+  /// they keep their precision.
+  pub fn to_json(&self) -> serde_json::Value {
+    match self {
+      Value::Null => serde_json::Value::Null,
+      Value::Bool(b) => serde_json::Value::Bool(*b),
+      Value::Int(i) => {
+        if let Ok(i) = i64::try_from(*i) {
+          serde_json::Value::from(i)
+        } else if let Ok(u) = u64::try_from(*i) {
+          serde_json::Value::from(u)
+        } else {
+          serde_json::Value::String(i.to_string())
+        }
+      },
+      Value::Float(f) => {
+        serde_json::Number::from_f64(*f).map_or(serde_json::Value::String(f.to_string()), serde_json::Value::Number)
+      },
+      Value::Json(json) => json.clone(),
+      _ => serde_json::Value::String(self.to_string()),
+    }
+  }
+
+  /// The value as a csv field. NULLs are left empty.
+  pub fn to_csv_field(&self) -> String {
+    match self {
+      Value::Null => String::new(),
+      _ => self.to_string(),
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Value::Null => write!(f, "NULL"),
+      Value::Bool(b) => write!(f, "{b}"),
+      Value::Int(i) => write!(f, "{i}"),
+      Value::Float(n) => write!(f, "{n}"),
+      Value::Bytes(bytes) => write!(f, "{}", hex(bytes)),
+      Value::Json(json) => write!(f, "{json}"),
+      Value::Decimal(s) | Value::Text(s) | Value::Temporal(s) | Value::Unknown(s) => write!(f, "{s}"),
+    }
+  }
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().fold(String::new(), |mut output, b| {
+    let _ = write!(output, "{b:02X}");
+    output
+  })
+}
+
+fn quote(s: &str) -> String {
+  format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_display() {
+    let test_cases = vec![
+      (Value::Null, "NULL"),
+      (Value::Bool(true), "true"),
+      (Value::Int(-42), "-42"),
+      (Value::Float(1.5), "1.5"),
+      (Value::Decimal("1.50".to_owned()), "1.50"),
+      (Value::Text("NULL".to_owned()), "NULL"),
+      (Value::Bytes(vec![0xde, 0xad]), "DEAD"),
+      (Value::Json(serde_json::json!({"a": [1, 2]})), "{\"a\":[1,2]}"),
+      (Value::Temporal("2024-01-01 00:00:00".to_owned()), "2024-01-01 00:00:00"),
+      (Value::Unknown("(1,2)".to_owned()), "(1,2)"),
+    ];
+    for (value, expected) in test_cases {
+      assert_eq!(value.to_string(), expected, "Failed for value: {value:?}");
+    }
+  }
+
+  #[test]
+  fn test_to_sql_literal() {
+    let test_cases = vec![
+      (Value::Null, "NULL"),
+      (Value::Text("NULL".to_owned()), "'NULL'"),
+      (Value::Bool(false), "FALSE"),
+      (Value::Int(7), "7"),
+      (Value::Float(f64::NAN), "'NaN'"),
+      (Value::Decimal("10.00".to_owned()), "10.00"),
+      (Value::Text("it's".to_owned()), "'it''s'"),
+      (Value::Json(serde_json::json!({"a": "b"})), "'{\"a\":\"b\"}'"),
+      (Value::Temporal("12:00:00".to_owned()), "'12:00:00'"),
+    ];
+    for (value, expected) in test_cases {
+      assert_eq!(value.to_sql_literal(Driver::Postgres), expected, "Failed for value: {value:?}");
+      assert_eq!(value.to_sql_literal(Driver::Sqlite), expected, "Failed for value: {value:?}");
+    }
+    let bytes = Value::Bytes(vec![0x01, 0xff]);
+    assert_eq!(bytes.to_sql_literal(Driver::Postgres), "'\\x01FF'::bytea");
+    assert_eq!(bytes.to_sql_literal(Driver::Sqlite), "X'01FF'");
+    assert_eq!(bytes.to_sql_literal(Driver::MySql), "X'01FF'");
+    assert_eq!(bytes.to_sql_literal(Driver::Oracle), "HEXTORAW('01FF')");
+  }
+
+  #[test]
+  fn test_to_json() {
+    let test_cases = vec![
+      (Value::Null, serde_json::Value::Null),
+      (Value::Bool(true), serde_json::json!(true)),
+      (Value::Int(u64::MAX as i128), serde_json::json!(u64::MAX)),
+      (Value::Int(i128::MAX), serde_json::json!(i128::MAX.to_string())),
+      (Value::Float(0.25), serde_json::json!(0.25)),
+      (Value::Float(f64::INFINITY), serde_json::json!("inf")),
+      (Value::Decimal("0.10".to_owned()), serde_json::json!("0.10")),
+      (Value::Json(serde_json::json!([1, null])), serde_json::json!([1, null])),
+      (Value::Text("x".to_owned()), serde_json::json!("x")),
+    ];
+    for (value, expected) in test_cases {
+      assert_eq!(value.to_json(), expected, "Failed for value: {value:?}");
+    }
+  }
+
+  #[test]
+  fn test_to_csv_field() {
+    assert_eq!(Value::Null.to_csv_field(), "");
+    assert_eq!(Value::Text("NULL".to_owned()).to_csv_field(), "NULL");
+  }
+}
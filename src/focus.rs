@@ -10,12 +10,14 @@ pub enum Focus {
   Data,
   PopUp,
   Favorites,
+  Notifications,
+  Activity,
 }
 
 impl Focus {
   pub fn tab_color(&self) -> Color {
     match self {
-      Focus::Editor | Focus::History | Focus::Favorites => Color::Green,
+      Focus::Editor | Focus::History | Focus::Favorites | Focus::Notifications | Focus::Activity => Color::Green,
       Focus::Menu | Focus::Data | Focus::PopUp => Color::default(),
     }
   }
@@ -24,6 +26,8 @@ impl Focus {
     match self {
       Focus::Editor => 0,
       Focus::History => 1,
+      Focus::Notifications => 3,
+      Focus::Activity => 4,
       Focus::Favorites | Focus::Menu | Focus::Data | Focus::PopUp => 2,
     }
   }
@@ -13,6 +13,24 @@ impl AsRef<str> for Password {
   }
 }
 
+impl From<String> for Password {
+  fn from(password: String) -> Self {
+    Self(password)
+  }
+}
+
+/// Looks up a saved password without prompting for one, since there is
+/// no terminal to prompt in once the app is running.
+pub fn find_password(connection_name: &str, username: &str) -> Result<Option<Password>> {
+  let entry = Entry::new("rainfrog", &format!("{connection_name}-{username}"))?;
+
+  match entry.get_password() {
+    Ok(password) => Ok(Some(Password(password))),
+    Err(keyring::Error::NoEntry) => Ok(None),
+    Err(e) => Err(eyre::Report::msg(format!("Failed to extract password from secret: {e:?}"))),
+  }
+}
+
 pub fn get_password(connection_name: &str, username: &str) -> Result<Password> {
   let entry = Entry::new("rainfrog", &format!("{connection_name}-{username}"))?;
 
@@ -4,13 +4,16 @@
 pub mod action;
 pub mod app;
 pub mod cli;
+pub mod completion;
 pub mod components;
 pub mod config;
 pub mod database;
 pub mod focus;
 pub mod keyring;
 pub mod popups;
+pub mod session;
 pub mod tui;
+pub mod tunnel;
 pub mod ui;
 pub mod utils;
 pub mod vim;
@@ -23,23 +26,32 @@ use std::{
 use clap::Parser;
 use cli::{Cli, Driver, extract_driver_from_url, prompt_for_database_selection};
 use color_eyre::eyre::Result;
-use config::{Config, ConnectionString};
+use config::Config;
 use dotenvy::dotenv;
 use keyring::get_password;
+use tunnel::SshTunnel;
 
 use crate::{
   app::App,
   utils::{initialize_logging, initialize_panic_handler},
 };
 
-async fn run_app(mut args: Cli, config: Config, driver: Driver) -> Result<()> {
+async fn run_app(
+  mut args: Cli,
+  config: Config,
+  driver: Driver,
+  connection_name: Option<String>,
+  tunnel: Option<SshTunnel>,
+) -> Result<()> {
   let mouse_mode = args.mouse_mode.take();
   let mut app = App::new(mouse_mode, config)?;
-  app.run(driver, args).await?;
+  app.run(driver, args, connection_name, tunnel).await?;
   Ok(())
 }
 
-fn resolve_driver(args: &mut Cli, config: &Config) -> Result<Driver> {
+// also returns the name of the connection if it was picked from the config,
+// and the ssh tunnel it goes through if it has one
+async fn resolve_driver(args: &mut Cli, config: &Config) -> Result<(Driver, Option<String>, Option<SshTunnel>)> {
   let url = args.connection_url.clone().or_else(|| {
     env::var("DATABASE_URL").map_or(None, |url| {
       if url.is_empty() {
@@ -55,38 +67,38 @@ fn resolve_driver(args: &mut Cli, config: &Config) -> Result<Driver> {
     || args.password.is_some()
     || args.host.is_some()
     || args.port.is_some()
+    || args.socket.is_some()
     || args.database.is_some();
 
-  let (driver, url) = match (url, has_cli_input) {
-    (Some(u), _) => {
-      if let Some(driver) = args.driver.take() { Ok(driver) } else { extract_driver_from_url(&u) }.map(|d| (d, Some(u)))
-    },
+  let mut tunnel = None;
+  let (driver, url, name) = match (url, has_cli_input) {
+    (Some(u), _) => if let Some(driver) = args.driver.take() { Ok(driver) } else { extract_driver_from_url(&u) }
+      .map(|d| (d, Some(u), None)),
     (None, true) => {
       if let Some(driver) = args.driver.take() {
-        Ok((driver, None))
+        Ok((driver, None, None))
       } else {
-        Ok((prompt_for_driver()?, None))
+        Ok((prompt_for_driver()?, None, None))
       }
     },
     (None, false) => Ok(match prompt_for_database_selection(config)? {
       Some((conn, name)) => {
-        let url = match conn.connection {
-          ConnectionString::Raw { connection_string } => Ok(connection_string),
-          ConnectionString::Structured { details } => {
-            let password = get_password(&name, &details.username)?;
-            details.connection_string(conn.driver, password)
-          },
-        }?;
-
-        (conn.driver, Some(url))
+        // tls flags given on the command line win over the config's
+        args.tls = args.tls.clone().or(conn.tls());
+        args.socket = conn.socket();
+        let password = conn.username().map(|username| get_password(&name, username)).transpose()?;
+        tunnel = conn.open_tunnel().await?;
+        let url = conn.connection_string(password, tunnel.as_ref())?;
+
+        (conn.driver, Some(url), Some(name))
       },
-      None => (prompt_for_driver()?, None),
+      None => (prompt_for_driver()?, None, None),
     }),
   }?;
 
   args.connection_url = url;
 
-  Ok(driver)
+  Ok((driver, name, tunnel))
 }
 
 async fn tokio_main() -> Result<()> {
@@ -97,9 +109,9 @@ async fn tokio_main() -> Result<()> {
   let mut args = Cli::parse();
   dotenv().ok();
   let config = Config::new()?;
-  let driver = resolve_driver(&mut args, &config)?;
+  let (driver, connection_name, tunnel) = resolve_driver(&mut args, &config).await?;
 
-  run_app(args, config, driver).await
+  run_app(args, config, driver, connection_name, tunnel).await
 }
 
 #[tokio::main]
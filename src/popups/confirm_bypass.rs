@@ -1,15 +1,16 @@
 use crossterm::event::KeyCode;
 
-use super::{PopUp, PopUpPayload};
+use super::{NameConfirmation, PopUp, PopUpPayload};
 
 #[derive(Debug)]
 pub struct ConfirmBypass {
   pending_query: String,
+  name_confirmation: NameConfirmation,
 }
 
 impl ConfirmBypass {
   pub fn new(pending_query: String) -> Self {
-    Self { pending_query }
+    Self { pending_query, name_confirmation: NameConfirmation::default() }
   }
 }
 
@@ -19,6 +20,13 @@ impl PopUp for ConfirmBypass {
     key: crossterm::event::KeyEvent,
     app_state: &mut crate::app::AppState,
   ) -> color_eyre::eyre::Result<Option<PopUpPayload>> {
+    if NameConfirmation::required(app_state) {
+      return Ok(match self.name_confirmation.handle_key_events(key, app_state) {
+        Some(true) => Some(PopUpPayload::ConfirmBypass(self.pending_query.to_owned())),
+        Some(false) => Some(PopUpPayload::SetDataTable(None, None)),
+        None => None,
+      });
+    }
     match key.code {
       KeyCode::Char('Y') => Ok(Some(PopUpPayload::ConfirmBypass(self.pending_query.to_owned()))),
       KeyCode::Char('N') | KeyCode::Esc => Ok(Some(PopUpPayload::SetDataTable(None, None))),
@@ -27,10 +35,14 @@ impl PopUp for ConfirmBypass {
   }
 
   fn get_cta_text(&self, app_state: &crate::app::AppState) -> String {
-    "Are you sure you want to bypass the query parser? The query will not be wrapped in a transaction, so it cannot be undone.".to_string()
+    let cta = "Are you sure you want to bypass the query parser? The query will not be wrapped in a transaction, so it cannot be undone.".to_string();
+    NameConfirmation::with_cta_text(cta, app_state)
   }
 
   fn get_actions_text(&self, app_state: &crate::app::AppState) -> String {
+    if NameConfirmation::required(app_state) {
+      return self.name_confirmation.get_actions_text();
+    }
     "[Y]es to confirm | [N]o to cancel".to_string()
   }
 }
@@ -1,6 +1,7 @@
 use crossterm::event::KeyCode;
 
 use super::{PopUp, PopUpPayload};
+use crate::action::ExportFormat;
 
 #[derive(Debug)]
 pub struct ConfirmExport {
@@ -20,8 +21,9 @@ impl PopUp for ConfirmExport {
     app_state: &mut crate::app::AppState,
   ) -> color_eyre::eyre::Result<Option<PopUpPayload>> {
     match key.code {
-      KeyCode::Char('Y') => Ok(Some(PopUpPayload::ConfirmExport(true))),
-      KeyCode::Char('N') | KeyCode::Esc => Ok(Some(PopUpPayload::ConfirmExport(false))),
+      KeyCode::Char('Y') => Ok(Some(PopUpPayload::ConfirmExport(Some(ExportFormat::CSV)))),
+      KeyCode::Char('J') => Ok(Some(PopUpPayload::ConfirmExport(Some(ExportFormat::JSON)))),
+      KeyCode::Char('N') | KeyCode::Esc => Ok(Some(PopUpPayload::ConfirmExport(None))),
       _ => Ok(None),
     }
   }
@@ -34,6 +36,6 @@ impl PopUp for ConfirmExport {
   }
 
   fn get_actions_text(&self, app_state: &crate::app::AppState) -> String {
-    "[Y]es to confirm | [N]o to cancel".to_string()
+    "[Y]es to export csv | [J] to export json | [N]o to cancel".to_string()
   }
 }
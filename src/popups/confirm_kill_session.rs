@@ -0,0 +1,69 @@
+use crossterm::event::KeyCode;
+
+use super::{NameConfirmation, PopUp, PopUpPayload};
+use crate::database::ServerSession;
+
+#[derive(Debug)]
+pub struct ConfirmKillSession {
+  session: ServerSession,
+  terminate: bool,
+  name_confirmation: NameConfirmation,
+}
+
+impl ConfirmKillSession {
+  pub fn new(session: ServerSession, terminate: bool) -> Self {
+    Self { session, terminate, name_confirmation: NameConfirmation::default() }
+  }
+
+  fn payload(&self) -> PopUpPayload {
+    PopUpPayload::KillSession(self.session.id.clone(), self.terminate)
+  }
+}
+
+impl PopUp for ConfirmKillSession {
+  fn handle_key_events(
+    &mut self,
+    key: crossterm::event::KeyEvent,
+    app_state: &mut crate::app::AppState,
+  ) -> color_eyre::eyre::Result<Option<PopUpPayload>> {
+    if NameConfirmation::required(app_state) {
+      return Ok(match self.name_confirmation.handle_key_events(key, app_state) {
+        Some(true) => Some(self.payload()),
+        Some(false) => Some(PopUpPayload::Cancel),
+        None => None,
+      });
+    }
+    match key.code {
+      KeyCode::Char('Y') => Ok(Some(self.payload())),
+      KeyCode::Char('N') | KeyCode::Esc => Ok(Some(PopUpPayload::Cancel)),
+      _ => Ok(None),
+    }
+  }
+
+  fn get_cta_text(&self, app_state: &crate::app::AppState) -> String {
+    let session = &self.session;
+    let who = match (&session.user, &session.client) {
+      (Some(user), Some(client)) => format!("session {} ({user}, {client})", session.id),
+      (Some(user), None) => format!("session {} ({user})", session.id),
+      _ => format!("session {}", session.id),
+    };
+    let cta = match self.terminate {
+      true => format!(
+        "Are you sure you want to terminate {who}? It will be disconnected, and any transaction it has open will be rolled back."
+      ),
+      false => format!("Are you sure you want to cancel the query that {who} is running?"),
+    };
+    let cta = match &session.query {
+      Some(query) => format!("{cta}\n\n{query}"),
+      None => cta,
+    };
+    NameConfirmation::with_cta_text(cta, app_state)
+  }
+
+  fn get_actions_text(&self, app_state: &crate::app::AppState) -> String {
+    if NameConfirmation::required(app_state) {
+      return self.name_confirmation.get_actions_text();
+    }
+    "[Y]es to confirm | [N]o to cancel".to_string()
+  }
+}
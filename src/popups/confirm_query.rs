@@ -1,18 +1,19 @@
 use crossterm::event::KeyCode;
 use sqlparser::ast::Statement;
 
-use super::{PopUp, PopUpPayload};
+use super::{NameConfirmation, PopUp, PopUpPayload};
 use crate::database::statement_type_string;
 
 #[derive(Debug)]
 pub struct ConfirmQuery {
   pending_query: String,
   statement_type: Statement,
+  name_confirmation: NameConfirmation,
 }
 
 impl ConfirmQuery {
   pub fn new(pending_query: String, statement_type: Statement) -> Self {
-    Self { pending_query, statement_type }
+    Self { pending_query, statement_type, name_confirmation: NameConfirmation::default() }
   }
 }
 
@@ -22,6 +23,13 @@ impl PopUp for ConfirmQuery {
     key: crossterm::event::KeyEvent,
     app_state: &mut crate::app::AppState,
   ) -> color_eyre::eyre::Result<Option<PopUpPayload>> {
+    if NameConfirmation::required(app_state) {
+      return Ok(match self.name_confirmation.handle_key_events(key, app_state) {
+        Some(true) => Some(PopUpPayload::ConfirmQuery(self.pending_query.to_owned())),
+        Some(false) => Some(PopUpPayload::SetDataTable(None, None)),
+        None => None,
+      });
+    }
     match key.code {
       KeyCode::Char('Y') => Ok(Some(PopUpPayload::ConfirmQuery(self.pending_query.to_owned()))),
       KeyCode::Char('N') | KeyCode::Esc => Ok(Some(PopUpPayload::SetDataTable(None, None))),
@@ -30,7 +38,7 @@ impl PopUp for ConfirmQuery {
   }
 
   fn get_cta_text(&self, app_state: &crate::app::AppState) -> String {
-    match self.statement_type.clone() {
+    let cta = match self.statement_type.clone() {
       Statement::Explain { statement, .. } => {
         format!(
           "Are you sure you want to run an EXPLAIN ANALYZE that will run a {} statement?",
@@ -43,10 +51,14 @@ impl PopUp for ConfirmQuery {
           statement_type_string(Some(self.statement_type.clone())).to_uppercase()
         )
       },
-    }
+    };
+    NameConfirmation::with_cta_text(cta, app_state)
   }
 
   fn get_actions_text(&self, app_state: &crate::app::AppState) -> String {
+    if NameConfirmation::required(app_state) {
+      return self.name_confirmation.get_actions_text();
+    }
     "[Y]es to confirm | [N]o to cancel".to_string()
   }
 }
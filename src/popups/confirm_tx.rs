@@ -1,18 +1,22 @@
 use crossterm::event::KeyCode;
 use sqlparser::ast::Statement;
 
-use super::{PopUp, PopUpPayload};
+use super::{NameConfirmation, PopUp, PopUpPayload};
 use crate::database::statement_type_string;
 
 #[derive(Debug)]
 pub struct ConfirmTx {
   rows_affected: Option<u64>,
   statement_type: Option<Statement>,
+  // the changed rows are in the data pane, so the popup can be put
+  // aside to look them over
+  inspectable: bool,
+  name_confirmation: NameConfirmation,
 }
 
 impl ConfirmTx {
-  pub fn new(rows_affected: Option<u64>, statement_type: Option<Statement>) -> Self {
-    Self { rows_affected, statement_type }
+  pub fn new(rows_affected: Option<u64>, statement_type: Option<Statement>, inspectable: bool) -> Self {
+    Self { rows_affected, statement_type, inspectable, name_confirmation: NameConfirmation::default() }
   }
 }
 
@@ -22,6 +26,16 @@ impl PopUp for ConfirmTx {
     key: crossterm::event::KeyEvent,
     app_state: &mut crate::app::AppState,
   ) -> color_eyre::eyre::Result<Option<PopUpPayload>> {
+    if self.inspectable && key.code == KeyCode::Tab {
+      return Ok(Some(PopUpPayload::InspectTx));
+    }
+    if NameConfirmation::required(app_state) {
+      return Ok(match self.name_confirmation.handle_key_events(key, app_state) {
+        Some(true) => Some(PopUpPayload::CommitTx),
+        Some(false) => Some(PopUpPayload::RollbackTx),
+        None => None,
+      });
+    }
     match key.code {
       KeyCode::Char('Y') => Ok(Some(PopUpPayload::CommitTx)),
       KeyCode::Char('N') | KeyCode::Esc => Ok(Some(PopUpPayload::RollbackTx)),
@@ -31,7 +45,7 @@ impl PopUp for ConfirmTx {
 
   fn get_cta_text(&self, app_state: &crate::app::AppState) -> String {
     let rows_affected = self.rows_affected.unwrap_or_default();
-    match self.statement_type.clone() {
+    let cta = match self.statement_type.clone() {
       None => {
         format!("Are you sure you want to commit a transaction that will affect {rows_affected} rows?")
       },
@@ -54,10 +68,18 @@ impl PopUp for ConfirmTx {
           statement_type_string(self.statement_type.clone()).to_uppercase()
         )
       },
-    }
+    };
+    NameConfirmation::with_cta_text(cta, app_state)
   }
 
   fn get_actions_text(&self, app_state: &crate::app::AppState) -> String {
-    "[Y]es to confirm | [N]o to cancel".to_string()
+    let actions = match NameConfirmation::required(app_state) {
+      true => self.name_confirmation.get_actions_text(),
+      false => "[Y]es to confirm | [N]o to cancel".to_string(),
+    };
+    match self.inspectable {
+      true => format!("{actions} | [Tab] to inspect changed rows"),
+      false => actions,
+    }
   }
 }
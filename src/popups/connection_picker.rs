@@ -0,0 +1,144 @@
+use crossterm::event::KeyCode;
+
+use super::{PopUp, PopUpPayload};
+use crate::keyring::{Password, find_password};
+
+#[derive(Debug)]
+pub struct ConnectionEntry {
+  pub name: String,
+  /// Set when the connection's password has to be found in the keyring,
+  /// or typed in if it isn't there.
+  pub username: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ConnectionPicker {
+  entries: Vec<ConnectionEntry>,
+  // the connection of the active session, or none when opening a new tab
+  current: Option<String>,
+  selected: usize,
+  // set while the password for the selected connection is being typed
+  password: Option<String>,
+  error: Option<String>,
+}
+
+impl ConnectionPicker {
+  pub fn new(entries: Vec<ConnectionEntry>, current: Option<String>) -> Self {
+    let selected = entries.iter().position(|e| Some(&e.name) == current.as_ref()).unwrap_or(0);
+    Self { entries, current, selected, password: None, error: None }
+  }
+
+  fn payload(&self, name: String, password: Option<Password>) -> PopUpPayload {
+    match self.current {
+      Some(_) => PopUpPayload::SwitchConnection(name, password),
+      None => PopUpPayload::OpenSession(name, password),
+    }
+  }
+
+  fn pick(&mut self) -> Option<PopUpPayload> {
+    let entry = self.entries.get(self.selected)?;
+    let Some(username) = &entry.username else {
+      return Some(self.payload(entry.name.clone(), None));
+    };
+    match find_password(&entry.name, username) {
+      Ok(Some(password)) => Some(self.payload(entry.name.clone(), Some(password))),
+      Ok(None) => {
+        self.password = Some(String::new());
+        None
+      },
+      Err(e) => {
+        self.error = Some(e.to_string());
+        self.password = Some(String::new());
+        None
+      },
+    }
+  }
+}
+
+impl PopUp for ConnectionPicker {
+  fn handle_key_events(
+    &mut self,
+    key: crossterm::event::KeyEvent,
+    app_state: &mut crate::app::AppState,
+  ) -> color_eyre::eyre::Result<Option<PopUpPayload>> {
+    if let Some(password) = self.password.as_mut() {
+      return match key.code {
+        KeyCode::Char(c) => {
+          password.push(c);
+          Ok(None)
+        },
+        KeyCode::Backspace => {
+          password.pop();
+          Ok(None)
+        },
+        KeyCode::Enter => {
+          let password = self.password.take().unwrap_or_default();
+          Ok(Some(self.payload(self.entries[self.selected].name.clone(), Some(password.into()))))
+        },
+        KeyCode::Esc => {
+          self.password = None;
+          self.error = None;
+          Ok(None)
+        },
+        _ => Ok(None),
+      };
+    }
+    match key.code {
+      KeyCode::Char('j') | KeyCode::Down => {
+        if !self.entries.is_empty() {
+          self.selected = (self.selected + 1) % self.entries.len();
+        }
+        Ok(None)
+      },
+      KeyCode::Char('k') | KeyCode::Up => {
+        if !self.entries.is_empty() {
+          self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+        }
+        Ok(None)
+      },
+      KeyCode::Enter => Ok(self.pick()),
+      KeyCode::Esc => Ok(Some(PopUpPayload::Cancel)),
+      _ => Ok(None),
+    }
+  }
+
+  fn get_cta_text(&self, app_state: &crate::app::AppState) -> String {
+    if self.entries.is_empty() {
+      return "No connections to switch to. Add connections to the [db] section of your config.".to_string();
+    }
+    if self.password.is_some() {
+      let entry = &self.entries[self.selected];
+      return format!(
+        "{}Password for {}@{}:",
+        self.error.as_ref().map_or(String::new(), |e| format!("{e}\n\n")),
+        entry.username.as_deref().unwrap_or_default(),
+        entry.name
+      );
+    }
+    let lines: Vec<String> = self
+      .entries
+      .iter()
+      .enumerate()
+      .map(|(i, entry)| {
+        format!(
+          "{} {}{}",
+          if i == self.selected { ">" } else { " " },
+          entry.name,
+          if Some(&entry.name) == self.current.as_ref() { " (connected)" } else { "" }
+        )
+      })
+      .collect();
+    let question = match self.current {
+      Some(_) => "Switch to which connection?",
+      None => "Open which connection in a new tab?",
+    };
+    format!("{question}\n\n{}", lines.join("\n"))
+  }
+
+  fn get_actions_text(&self, app_state: &crate::app::AppState) -> String {
+    match &self.password {
+      Some(password) => format!("{} [<enter>] connect | [<esc>] back", "*".repeat(password.chars().count())),
+      None => "[j|↓] down | [k|↑] up | [<enter>] connect | [<esc>] cancel".to_string(),
+    }
+  }
+}
@@ -0,0 +1,63 @@
+use crossterm::event::KeyCode;
+
+use super::{PopUp, PopUpPayload};
+
+#[derive(Debug, Default)]
+pub struct ListenChannel {
+  channel: String,
+}
+
+impl ListenChannel {
+  pub fn new() -> Self {
+    Self { channel: "".to_string() }
+  }
+}
+
+impl PopUp for ListenChannel {
+  fn handle_key_events(
+    &mut self,
+    key: crossterm::event::KeyEvent,
+    app_state: &mut crate::app::AppState,
+  ) -> color_eyre::eyre::Result<Option<PopUpPayload>> {
+    match key.code {
+      KeyCode::Char(c) => {
+        // the channel is quoted when listened on, so a stray space would
+        // make it one that nothing notifies
+        if c.is_whitespace() {
+          return Ok(None);
+        }
+        self.channel.push(c);
+        Ok(None)
+      },
+      KeyCode::Enter => {
+        if !self.channel.is_empty() {
+          return Ok(Some(PopUpPayload::Listen(self.channel.clone())));
+        }
+        Ok(None)
+      },
+      KeyCode::Esc => Ok(Some(PopUpPayload::Cancel)),
+      KeyCode::Backspace => {
+        self.channel.pop();
+        Ok(None)
+      },
+      _ => Ok(None),
+    }
+  }
+
+  fn get_cta_text(&self, app_state: &crate::app::AppState) -> String {
+    match app_state.session().listening.is_empty() {
+      true => {
+        "Input the channel to LISTEN on and then press [Enter]; press [Esc] to cancel. The name is case-sensitive."
+          .to_string()
+      },
+      false => format!(
+        "Input another channel to LISTEN on and then press [Enter]; press [Esc] to cancel.\n\nAlready listening on {}.",
+        app_state.session().listening.join(", ")
+      ),
+    }
+  }
+
+  fn get_actions_text(&self, app_state: &crate::app::AppState) -> String {
+    format!("> {}_", self.channel)
+  }
+}
@@ -1,14 +1,17 @@
 use color_eyre::eyre::Result;
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyCode, KeyEvent};
 use sqlparser::ast::Statement;
 
-use crate::{app::AppState, database::Rows};
+use crate::{action::ExportFormat, app::AppState, database::Rows, keyring::Password};
 
 pub mod confirm_bypass;
 pub mod confirm_export;
+pub mod confirm_kill_session;
 pub mod confirm_query;
 pub mod confirm_tx;
+pub mod connection_picker;
 pub mod exporting;
+pub mod listen_channel;
 pub mod name_favorite;
 
 // since popups are meant to overlay the entire app and capture
@@ -21,10 +24,15 @@ pub enum PopUpPayload {
   SetDataTable(Option<Result<Rows>>, Option<Statement>),
   CommitTx,
   RollbackTx,
+  InspectTx, // closes the popup, leaving the transaction pending
   ConfirmQuery(String),
   ConfirmBypass(String),
-  ConfirmExport(bool),
+  ConfirmExport(Option<ExportFormat>),
   NamedFavorite(String, Vec<String>),
+  SwitchConnection(String, Option<Password>), // (connection name, password)
+  OpenSession(String, Option<Password>),      // (connection name, password)
+  Listen(String),                             // channel name
+  KillSession(String, bool),                  // (session id, terminate)
 }
 
 pub trait PopUp {
@@ -41,3 +49,52 @@ pub trait PopUp {
     "".to_string()
   }
 }
+
+// on protected connections, a confirmation has to be typed out as the name of
+// the connection or its database instead of being given with a single key
+#[derive(Debug, Default)]
+pub struct NameConfirmation {
+  input: String,
+}
+
+impl NameConfirmation {
+  pub fn required(app_state: &AppState) -> bool {
+    app_state.session().protected
+  }
+
+  fn names(app_state: &AppState) -> Vec<&str> {
+    let session = app_state.session();
+    std::iter::once(session.connection_name.as_str()).chain(session.database_name.as_deref()).collect()
+  }
+
+  // returns whether the action was confirmed once the popup should close
+  pub fn handle_key_events(&mut self, key: KeyEvent, app_state: &AppState) -> Option<bool> {
+    match key.code {
+      KeyCode::Esc => Some(false),
+      KeyCode::Enter if Self::names(app_state).contains(&self.input.trim()) => Some(true),
+      KeyCode::Backspace => {
+        self.input.pop();
+        None
+      },
+      KeyCode::Char(c) => {
+        self.input.push(c);
+        None
+      },
+      _ => None,
+    }
+  }
+
+  // adds what has to be typed to a popup's text, if anything
+  pub fn with_cta_text(cta: String, app_state: &AppState) -> String {
+    match Self::required(app_state) {
+      true => {
+        format!("{cta}\n\nThis connection is protected. Type {} to confirm.", Self::names(app_state).join(" or "))
+      },
+      false => cta,
+    }
+  }
+
+  pub fn get_actions_text(&self) -> String {
+    format!("> {}_ | [Enter] to confirm | [Esc] to cancel", self.input)
+  }
+}
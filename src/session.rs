@@ -0,0 +1,141 @@
+use color_eyre::eyre::Result;
+use ratatui::layout::Rect;
+use sqlparser::ast::Statement;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+  action::Action,
+  cli::Driver,
+  components::{
+    data::{Data, DataComponent},
+    editor::{Editor, EditorComponent},
+    menu::{Menu, MenuComponent},
+  },
+  config::Config,
+  database::{self, Database, ExecutionPolicy, LockWait, Notification, ServerSession},
+  tunnel::SshTunnel,
+};
+
+const MAX_NOTIFICATIONS: usize = 1000;
+
+/// The parts of a session that components read while drawing. Kept in
+/// `AppState` alongside the states of the other sessions.
+#[derive(Default)]
+pub struct SessionState {
+  pub connection_name: String,
+  /// The driver of the session's connection, once it's connected.
+  pub driver: Option<Driver>,
+  pub last_query_start: Option<chrono::DateTime<chrono::Utc>>,
+  pub last_query_end: Option<chrono::DateTime<chrono::Utc>>,
+  pub query_task_running: bool,
+  /// The lines of the last query that was run, so it can be run again.
+  pub last_query: Vec<String>,
+  /// The limit injected into the last query's unbounded SELECTs, if any.
+  pub row_limit: Option<usize>,
+  /// Set to run the next query without injecting a limit.
+  pub skip_row_limit: bool,
+  /// Set while a transaction is waiting to be committed or rolled back,
+  /// with the rows it affected, the statement that started it, and whether
+  /// the data pane shows a preview of the rows it changed.
+  pub pending_tx: Option<(Option<u64>, Option<Statement>, bool)>,
+  /// Set while the pending transaction applies edits made to the results,
+  /// to the SELECT they came from, so it's run again once it's committed.
+  pub rerun_after_commit: Option<String>,
+  /// Set while the session is in a transaction started with `BEGIN`, with
+  /// the number of statements that have run in it.
+  pub transaction: Option<usize>,
+  /// Savepoints created with `Action::CreateSavepoint`, oldest first.
+  pub savepoints: Vec<String>,
+  /// Confirmations have to be typed out as the connection or database name.
+  pub protected: bool,
+  /// The database named in the connection URL, if there is one.
+  pub database_name: Option<String>,
+  /// The channels the session listens on with `LISTEN`, in the order they
+  /// were added.
+  pub listening: Vec<String>,
+  /// The notifications received on those channels, newest first.
+  pub notifications: Vec<Notification>,
+  /// The server's sessions, as the activity monitor last loaded them.
+  pub activity: Vec<ServerSession>,
+  /// Why the activity monitor couldn't load them the last time it tried.
+  pub activity_error: Option<String>,
+  /// The sessions waiting on locks and the ones they wait on, as the lock
+  /// inspector last loaded them.
+  pub lock_waits: Vec<LockWait>,
+  pub lock_waits_error: Option<String>,
+  /// When the activity monitor last loaded either of them.
+  pub activity_loaded_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl SessionState {
+  pub fn new(connection_name: String) -> Self {
+    Self { connection_name, ..Default::default() }
+  }
+
+  /// Keeps the last `MAX_NOTIFICATIONS`, dropping the oldest ones.
+  pub fn add_notifications(&mut self, notifications: Vec<Notification>) {
+    for notification in notifications {
+      self.notifications.insert(0, notification);
+    }
+    self.notifications.truncate(MAX_NOTIFICATIONS);
+  }
+}
+
+/// A tab with its own connection, menu, editor buffer and results.
+pub struct Session {
+  pub database: Box<dyn Database>,
+  pub driver: Driver,
+  /// Only queries can be run on the connection.
+  pub read_only: bool,
+  pub execution_policy: ExecutionPolicy,
+  pub menu: Box<dyn MenuComponent<'static>>,
+  pub editor: Box<dyn EditorComponent>,
+  pub data: Box<dyn DataComponent<'static>>,
+  /// Closed when the session is dropped, after the database it forwards to.
+  pub tunnel: Option<SshTunnel>,
+}
+
+impl Session {
+  pub fn new(
+    database: Box<dyn Database>,
+    driver: Driver,
+    read_only: bool,
+    execution_policy: ExecutionPolicy,
+    tunnel: Option<SshTunnel>,
+  ) -> Self {
+    Self {
+      database,
+      driver,
+      read_only,
+      execution_policy,
+      menu: Box::new(Menu::new()),
+      editor: Box::new(Editor::new()),
+      data: Box::new(Data::new()),
+      tunnel,
+    }
+  }
+
+  pub fn register(&mut self, action_tx: UnboundedSender<Action>, config: &Config, area: Rect) -> Result<()> {
+    self.menu.register_action_handler(action_tx.clone())?;
+    self.editor.register_action_handler(action_tx.clone())?;
+    self.data.register_action_handler(action_tx)?;
+
+    self.menu.register_config_handler(config.clone())?;
+    self.editor.register_config_handler(config.clone())?;
+    self.data.register_config_handler(config.clone())?;
+
+    self.menu.init(area)?;
+    self.editor.init(area)?;
+    self.data.init(area)?;
+    Ok(())
+  }
+}
+
+pub fn new_database(driver: Driver) -> Box<dyn Database> {
+  match driver {
+    Driver::Postgres => Box::new(database::PostgresDriver::new()),
+    Driver::MySql => Box::new(database::MySqlDriver::new()),
+    Driver::Sqlite => Box::new(database::SqliteDriver::new()),
+    Driver::Oracle => Box::new(database::OracleDriver::new()),
+  }
+}
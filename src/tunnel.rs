@@ -0,0 +1,156 @@
+use std::{ffi::OsString, path::PathBuf, process::Stdio, time::Duration};
+
+use color_eyre::eyre::{Result, eyre};
+use serde::Deserialize;
+use tokio::{
+  io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+  net::{TcpListener, TcpStream},
+  process::{Child, Command},
+  time::{Instant, sleep},
+};
+
+const OPEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A bastion host that the database is reached through. Anything left out
+/// falls back to the user's ssh config.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct SshConfig {
+  pub host: String,
+  pub port: Option<u16>,
+  pub user: Option<String>,
+  pub identity_file: Option<PathBuf>,
+}
+
+/// A local port forwarded to the database by an `ssh -L` child process.
+/// The process is killed when the tunnel is dropped.
+#[derive(Debug)]
+pub struct SshTunnel {
+  _child: Child,
+  pub local_port: u16,
+}
+
+impl SshTunnel {
+  /// Starts ssh and waits until the forwarded port accepts connections,
+  /// or fails with whatever ssh printed if it exits first.
+  pub async fn open(ssh: &SshConfig, remote_host: &str, remote_port: u32) -> Result<Self> {
+    let local_port = TcpListener::bind("127.0.0.1:0").await?.local_addr()?.port();
+    let mut child = Command::new("ssh")
+      .args(ssh_args(ssh, local_port, remote_host, remote_port))
+      .stdin(Stdio::null())
+      .stdout(Stdio::null())
+      .stderr(Stdio::piped())
+      .kill_on_drop(true)
+      .spawn()
+      .map_err(|e| eyre!("Couldn't start ssh to open a tunnel through {}: {e}", ssh.host))?;
+
+    let deadline = Instant::now() + OPEN_TIMEOUT;
+    loop {
+      if let Some(status) = child.try_wait()? {
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+          pipe.read_to_string(&mut stderr).await.ok();
+        }
+        return Err(eyre!("SSH tunnel through {} failed ({status}): {}", ssh.host, stderr.trim()));
+      }
+      if TcpStream::connect(("127.0.0.1", local_port)).await.is_ok() {
+        log::info!("Forwarding 127.0.0.1:{local_port} to {remote_host}:{remote_port} through {}", ssh.host);
+        // ssh would block on a full pipe if what it prints later wasn't read
+        if let Some(pipe) = child.stderr.take() {
+          let host = ssh.host.clone();
+          tokio::spawn(async move {
+            let mut lines = BufReader::new(pipe).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+              log::warn!("ssh {host}: {line}");
+            }
+          });
+        }
+        return Ok(Self { _child: child, local_port });
+      }
+      if Instant::now() >= deadline {
+        return Err(eyre!("Timed out after {}s opening an SSH tunnel through {}", OPEN_TIMEOUT.as_secs(), ssh.host));
+      }
+      sleep(Duration::from_millis(100)).await;
+    }
+  }
+}
+
+// batch mode makes ssh fail rather than prompt for a password or an unknown
+// host key, which it couldn't do once the tui is up
+fn ssh_args(ssh: &SshConfig, local_port: u16, remote_host: &str, remote_port: u32) -> Vec<OsString> {
+  let mut args: Vec<OsString> = vec![
+    "-N".into(),
+    "-o".into(),
+    "ExitOnForwardFailure=yes".into(),
+    "-o".into(),
+    "BatchMode=yes".into(),
+    "-L".into(),
+    format!("127.0.0.1:{local_port}:{remote_host}:{remote_port}").into(),
+  ];
+  if let Some(port) = ssh.port {
+    args.extend(["-p".into(), port.to_string().into()]);
+  }
+  if let Some(identity_file) = &ssh.identity_file {
+    args.extend(["-i".into(), identity_file.into()]);
+  }
+  args.push(match &ssh.user {
+    Some(user) => format!("{user}@{}", ssh.host).into(),
+    None => ssh.host.clone().into(),
+  });
+  args
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_ssh_args() {
+    let mut ssh = SshConfig { host: "bastion".to_owned(), port: None, user: None, identity_file: None };
+    assert_eq!(
+      ssh_args(&ssh, 40000, "db.internal", 5432),
+      [
+        "-N",
+        "-o",
+        "ExitOnForwardFailure=yes",
+        "-o",
+        "BatchMode=yes",
+        "-L",
+        "127.0.0.1:40000:db.internal:5432",
+        "bastion"
+      ]
+    );
+
+    ssh.port = Some(2222);
+    ssh.user = Some("deploy".to_owned());
+    ssh.identity_file = Some(PathBuf::from("/home/deploy/.ssh/id_ed25519"));
+    assert_eq!(
+      ssh_args(&ssh, 40000, "db.internal", 5432)[7..],
+      ["-p", "2222", "-i", "/home/deploy/.ssh/id_ed25519", "deploy@bastion"]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_tunnel_failure() {
+    // nothing listens on the discard port, so ssh gives up straight away
+    let ssh = SshConfig { host: "127.0.0.1".to_owned(), port: Some(9), user: None, identity_file: None };
+    let err = SshTunnel::open(&ssh, "localhost", 5432).await.unwrap_err().to_string();
+    assert!(err.contains("127.0.0.1"), "{err}");
+  }
+
+  // run with `cargo test -- --ignored` on a machine where `ssh localhost`
+  // works without a prompt, and postgres listens on localhost at $PGPORT
+  // (5432 by default) for the user and database in $PGUSER and $PGDATABASE
+  #[tokio::test]
+  #[ignore = "needs a local sshd and postgres"]
+  async fn test_query_through_tunnel() {
+    use sqlx::{Connection, Row, postgres::PgConnectOptions};
+
+    let ssh = SshConfig { host: "localhost".to_owned(), port: None, user: None, identity_file: None };
+    let remote_port = std::env::var("PGPORT").ok().and_then(|port| port.parse().ok()).unwrap_or(5432);
+    let tunnel = SshTunnel::open(&ssh, "127.0.0.1", remote_port).await.unwrap();
+    let opts = PgConnectOptions::new().host("127.0.0.1").port(tunnel.local_port);
+    let mut conn = sqlx::PgConnection::connect_with(&opts).await.unwrap();
+    let row = sqlx::query("select 1 + 1").fetch_one(&mut conn).await.unwrap();
+    assert_eq!(row.get::<i32, _>(0), 2);
+  }
+}